@@ -0,0 +1,400 @@
+// 扫描检查点
+//
+// 长时间的目录扫描定期保存检查点，守护进程重启后从检查点继续同一个扫描：
+// - 以目录为提交单位：目录的条目全部列出、其中的文件全部扫描完成后，该目录才算完成
+// - 检查点只包含已完成目录的计数、威胁和文件事件；未完成目录中的文件在恢复后重新扫描，计数不会重复
+// - 每次检查点只返回新完成的目录及其威胁、文件事件（追加保存），累计的只有计数
+// - 恢复时跳过已完成目录中的文件，其子目录仍然遍历（子目录是否完成单独记录）
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::types::{DetectedThreat, ErrorCounts, FileEvent, FilePath, VirusName};
+
+/// 检查点保存间隔（秒）
+pub const CHECKPOINT_INTERVAL_SECS: u64 = 30;
+
+/// 单个扫描根目录已提交的计数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RootCheckpoint {
+    pub discovered: u32,
+    pub scanned: u32,
+    pub threats: u32,
+    pub errors: ErrorCounts,
+    /// 使用缓存结果跳过的文件数（计入 scanned）
+    #[serde(default)]
+    pub cached: u32,
+}
+
+/// 检查点中的威胁记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointThreat {
+    pub file_path: String,
+    pub virus_name: String,
+    pub file_hash: Option<String>,
+    pub file_type: Option<String>,
+    pub container_path: Option<String>,
+}
+
+impl From<&DetectedThreat> for CheckpointThreat {
+    fn from(threat: &DetectedThreat) -> Self {
+        Self {
+            file_path: threat.file_path.0.clone(),
+            virus_name: threat.virus_name.0.clone(),
+            file_hash: threat.file_hash.clone(),
+            file_type: threat.file_type.clone(),
+            container_path: threat.container_path.clone(),
+        }
+    }
+}
+
+impl From<&CheckpointThreat> for DetectedThreat {
+    fn from(threat: &CheckpointThreat) -> Self {
+        Self {
+            file_path: FilePath(threat.file_path.clone()),
+            virus_name: VirusName(threat.virus_name.clone()),
+            file_hash: threat.file_hash.clone(),
+            file_type: threat.file_type.clone(),
+            container_path: threat.container_path.clone(),
+        }
+    }
+}
+
+/// 已完成目录的累计计数（以 JSON 保存在数据库中，大小不随扫描进度增长）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointCounters {
+    /// 各根目录的计数（按提交顺序）
+    pub roots: Vec<RootCheckpoint>,
+    pub errors: ErrorCounts,
+}
+
+impl CheckpointCounters {
+    pub fn discovered_files(&self) -> u32 {
+        self.roots.iter().map(|r| r.discovered).sum()
+    }
+
+    pub fn scanned_files(&self) -> u32 {
+        self.roots.iter().map(|r| r.scanned).sum()
+    }
+
+    pub fn threats_found(&self) -> u32 {
+        self.roots.iter().map(|r| r.threats).sum()
+    }
+
+    pub fn cached_files(&self) -> u32 {
+        self.roots.iter().map(|r| r.cached).sum()
+    }
+}
+
+/// 已完成目录的累计状态：计数，以及逐条追加保存的威胁和文件事件
+///
+/// 旧版本的检查点把威胁和文件事件保存在同一个 JSON 中，反序列化时仍然兼容
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointState {
+    #[serde(flatten)]
+    pub counters: CheckpointCounters,
+    #[serde(default)]
+    pub threats: Vec<CheckpointThreat>,
+    #[serde(default)]
+    pub file_events: Vec<FileEvent>,
+}
+
+/// 一次检查点：自上次检查点以来新完成的目录及其威胁和文件事件，以及到目前为止的累计计数
+#[derive(Debug, Clone)]
+pub struct ScanCheckpoint {
+    pub completed_dirs: Vec<PathBuf>,
+    pub threats: Vec<CheckpointThreat>,
+    pub file_events: Vec<FileEvent>,
+    pub counters: CheckpointCounters,
+}
+
+/// 从检查点恢复扫描所需的状态
+#[derive(Debug, Clone, Default)]
+pub struct ResumeState {
+    pub completed_dirs: HashSet<PathBuf>,
+    pub state: CheckpointState,
+}
+
+/// 发现线程为正在处理的目录分配的编号
+pub type DirId = usize;
+
+/// 尚未完成的目录
+#[derive(Debug)]
+struct OpenDir {
+    path: PathBuf,
+    root_index: usize,
+    /// 条目已全部列出
+    listed: bool,
+    /// 已加入队列但尚未扫描完成的文件数
+    pending: u32,
+    files: u32,
+    scanned: u32,
+    cached: u32,
+    threats: Vec<CheckpointThreat>,
+    errors: ErrorCounts,
+    events: Vec<FileEvent>,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    next_id: DirId,
+    open: HashMap<DirId, OpenDir>,
+    committed: CheckpointCounters,
+    /// 上次检查点之后新完成的目录及其威胁和文件事件
+    completed: Vec<PathBuf>,
+    threats: Vec<CheckpointThreat>,
+    events: Vec<FileEvent>,
+}
+
+/// 跟踪目录完成情况并生成检查点（发现线程与工作线程共享，只在同步代码中短暂加锁）
+#[derive(Debug)]
+pub struct CheckpointTracker {
+    resumed_dirs: HashSet<PathBuf>,
+    state: std::sync::Mutex<TrackerState>,
+}
+
+impl CheckpointTracker {
+    pub fn new(root_count: usize, resume: Option<&ResumeState>) -> Self {
+        let (resumed_dirs, mut committed) = match resume {
+            Some(r) => (r.completed_dirs.clone(), r.state.counters.clone()),
+            None => (HashSet::new(), CheckpointCounters::default()),
+        };
+        committed.roots.resize(root_count, RootCheckpoint::default());
+
+        Self {
+            resumed_dirs,
+            state: std::sync::Mutex::new(TrackerState {
+                committed,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 该目录（或作为根路径的文件）是否在恢复前已完成
+    pub fn is_resumed(&self, path: &Path) -> bool {
+        self.resumed_dirs.contains(path)
+    }
+
+    /// 开始列出一个目录
+    pub fn open_dir(&self, root_index: usize, path: PathBuf) -> DirId {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.open.insert(id, OpenDir {
+            path,
+            root_index,
+            listed: false,
+            pending: 0,
+            files: 0,
+            scanned: 0,
+            cached: 0,
+            threats: Vec::new(),
+            errors: ErrorCounts::default(),
+            events: Vec::new(),
+        });
+        id
+    }
+
+    /// 目录中的文件加入扫描队列
+    pub fn add_file(&self, dir: DirId) {
+        self.update(dir, |d| {
+            d.pending += 1;
+            d.files += 1;
+        });
+    }
+
+    /// 遍历时记录的文件事件（排除、特殊文件）
+    pub fn add_event(&self, dir: DirId, event: FileEvent) {
+        self.update(dir, |d| {
+            if let Some(category) = event.category {
+                d.errors.record(category);
+            }
+            d.events.push(event);
+        });
+    }
+
+    /// 目录的条目已全部列出
+    pub fn close_dir(&self, dir: DirId) {
+        self.update(dir, |d| d.listed = true);
+    }
+
+    /// 文件扫描完成
+    pub fn file_scanned(&self, dir: DirId, threats: &[DetectedThreat], encrypted: bool) {
+        self.update(dir, |d| {
+            d.pending -= 1;
+            d.scanned += 1;
+            d.threats.extend(threats.iter().map(CheckpointThreat::from));
+            if encrypted {
+                d.errors.record(super::types::ErrorCategory::Encrypted);
+            }
+        });
+    }
+
+    /// 文件未修改，沿用缓存的干净结果
+    pub fn file_cached(&self, dir: DirId) {
+        self.update(dir, |d| {
+            d.pending -= 1;
+            d.scanned += 1;
+            d.cached += 1;
+        });
+    }
+
+    /// 文件未能扫描（附带错误事件时计入错误分类）
+    pub fn file_failed(&self, dir: DirId, event: Option<FileEvent>) {
+        self.update(dir, |d| {
+            d.pending -= 1;
+            if let Some(event) = event {
+                if let Some(category) = event.category {
+                    d.errors.record(category);
+                }
+                d.events.push(event);
+            }
+        });
+    }
+
+    /// 生成检查点（新完成的目录及其威胁和文件事件只返回一次）
+    pub fn checkpoint(&self) -> ScanCheckpoint {
+        let mut state = self.state.lock().unwrap();
+        ScanCheckpoint {
+            completed_dirs: std::mem::take(&mut state.completed),
+            threats: std::mem::take(&mut state.threats),
+            file_events: std::mem::take(&mut state.events),
+            counters: state.committed.clone(),
+        }
+    }
+
+    fn update(&self, dir: DirId, f: impl FnOnce(&mut OpenDir)) {
+        let mut state = self.state.lock().unwrap();
+        let done = match state.open.get_mut(&dir) {
+            Some(d) => {
+                f(d);
+                d.listed && d.pending == 0
+            }
+            None => return,
+        };
+
+        if done {
+            let d = state.open.remove(&dir).unwrap();
+            let committed = &mut state.committed;
+            let root = &mut committed.roots[d.root_index];
+            root.discovered += d.files;
+            root.scanned += d.scanned;
+            root.cached += d.cached;
+            root.threats += d.threats.len() as u32;
+            root.errors.add(&d.errors);
+            committed.errors.add(&d.errors);
+            state.threats.extend(d.threats);
+            state.events.extend(d.events);
+            state.completed.push(d.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clamav::types::ErrorCategory;
+
+    fn threat(path: &str) -> DetectedThreat {
+        DetectedThreat {
+            file_path: FilePath(path.to_string()),
+            virus_name: VirusName("Eicar-Test-Signature".to_string()),
+            file_hash: None,
+            file_type: None,
+            container_path: None,
+        }
+    }
+
+    #[test]
+    fn test_directory_commits_after_listing_and_scanning() {
+        let tracker = CheckpointTracker::new(1, None);
+        let dir = tracker.open_dir(0, PathBuf::from("/vol1/a"));
+        tracker.add_file(dir);
+        tracker.add_file(dir);
+        tracker.file_scanned(dir, &[threat("/vol1/a/x")], false);
+        tracker.file_failed(dir, Some(FileEvent::failed("/vol1/a/y".into(), ErrorCategory::Io, None, "io".into())));
+
+        // 条目尚未全部列出
+        let cp = tracker.checkpoint();
+        assert!(cp.completed_dirs.is_empty());
+        assert_eq!(cp.counters.scanned_files(), 0);
+
+        tracker.close_dir(dir);
+        let cp = tracker.checkpoint();
+        assert_eq!(cp.completed_dirs, vec![PathBuf::from("/vol1/a")]);
+        assert_eq!(cp.counters.discovered_files(), 2);
+        assert_eq!(cp.counters.scanned_files(), 1);
+        assert_eq!(cp.counters.threats_found(), 1);
+        assert_eq!(cp.counters.errors.io, 1);
+        assert_eq!(cp.threats.len(), 1);
+        assert_eq!(cp.file_events.len(), 1);
+
+        // 已返回的目录、威胁和文件事件不再重复返回，计数保持累计
+        let cp = tracker.checkpoint();
+        assert!(cp.completed_dirs.is_empty());
+        assert!(cp.threats.is_empty());
+        assert!(cp.file_events.is_empty());
+        assert_eq!(cp.counters.scanned_files(), 1);
+    }
+
+    #[test]
+    fn test_failed_discovery_events_are_counted() {
+        let tracker = CheckpointTracker::new(1, None);
+        let dir = tracker.open_dir(0, PathBuf::from("/vol1/locked"));
+        tracker.add_event(dir, FileEvent::failed("/vol1/locked".into(), ErrorCategory::Permission, None, "denied".into()));
+        tracker.add_event(dir, FileEvent::skipped("/vol1/locked/fifo".into(), "fifo".into()));
+        tracker.close_dir(dir);
+
+        let cp = tracker.checkpoint();
+        assert_eq!(cp.counters.errors.permission, 1);
+        assert_eq!(cp.counters.errors.total(), 1);
+        assert_eq!(cp.file_events.len(), 2);
+    }
+
+    #[test]
+    fn test_pending_files_keep_directory_open() {
+        let tracker = CheckpointTracker::new(1, None);
+        let dir = tracker.open_dir(0, PathBuf::from("/vol1/a"));
+        tracker.add_file(dir);
+        tracker.close_dir(dir);
+        assert!(tracker.checkpoint().completed_dirs.is_empty());
+
+        tracker.file_scanned(dir, &[], true);
+        let cp = tracker.checkpoint();
+        assert_eq!(cp.completed_dirs.len(), 1);
+        assert_eq!(cp.counters.roots[0].errors.encrypted, 1);
+    }
+
+    #[test]
+    fn test_resume_state_is_carried_forward() {
+        let mut resume = ResumeState::default();
+        resume.completed_dirs.insert(PathBuf::from("/vol1/a"));
+        resume.state.counters.roots.push(RootCheckpoint { discovered: 5, scanned: 5, threats: 1, errors: ErrorCounts::default(), cached: 2 });
+        resume.state.threats.push(CheckpointThreat::from(&threat("/vol1/a/x")));
+
+        // 序列化后保持不变；只有计数的 JSON 同样可以读取
+        let json = serde_json::to_string(&resume.state).unwrap();
+        assert_eq!(serde_json::from_str::<CheckpointState>(&json).unwrap(), resume.state);
+        let json = serde_json::to_string(&resume.state.counters).unwrap();
+        assert_eq!(serde_json::from_str::<CheckpointState>(&json).unwrap().counters, resume.state.counters);
+
+        let tracker = CheckpointTracker::new(2, Some(&resume));
+        assert!(tracker.is_resumed(Path::new("/vol1/a")));
+        assert!(!tracker.is_resumed(Path::new("/vol1/b")));
+
+        let dir = tracker.open_dir(1, PathBuf::from("/vol2"));
+        tracker.add_file(dir);
+        tracker.close_dir(dir);
+        tracker.file_scanned(dir, &[], false);
+
+        // 恢复前的威胁已经保存过，不再随检查点返回
+        let cp = tracker.checkpoint();
+        assert_eq!(cp.counters.roots.len(), 2);
+        assert_eq!(cp.counters.scanned_files(), 6);
+        assert_eq!(cp.counters.threats_found(), 1);
+        assert!(cp.threats.is_empty());
+        assert_eq!(cp.counters.cached_files(), 2);
+    }
+}
@@ -0,0 +1,435 @@
+// 病毒库文件工具
+//
+// 解析 CVD/CLD/CUD 文件头，检查病毒库目录是否完整可用；
+// 加载前逐个校验数据库文件，损坏的文件移入 rejected/，并从 known-good/ 恢复上一次成功加载的版本；
+// 病毒库目录中的第三方签名文件（.ndb/.hdb 等）同样校验，损坏的移入 rejected/
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+
+/// 官方病毒库文件扩展名
+pub const OFFICIAL_DB_EXTENSIONS: &[&str] = &["cvd", "cld", "cud"];
+
+/// 病毒库目录中可以单独加载的签名文件扩展名（第三方签名、白名单等）
+pub const SIGNATURE_DB_EXTENSIONS: &[&str] = &[
+    "hdb", "hsb", "hdu", "hsu", "mdb", "msb", "mdu", "msu", "ndb", "ndu", "ldb", "ldu",
+    "idb", "cdb", "crb", "fp", "sfp", "ign", "ign2", "pdb", "gdb", "wdb", "ftm", "cbc", "yar", "yara",
+];
+
+/// 被拒绝的数据库文件存放目录（位于病毒库目录下，cl_load 不会加载子目录）
+pub const REJECTED_DIR: &str = "rejected";
+
+/// 上一次成功加载的数据库文件副本目录（位于病毒库目录下）
+pub const KNOWN_GOOD_DIR: &str = "known-good";
+
+/// CVD 文件头长度
+const CVD_HEADER_LEN: usize = 512;
+
+/// CVD 文件头信息
+///
+/// 文件头格式: ClamAV-VDB:构建时间:版本号:签名数:功能级别:MD5:数字签名:构建者:构建时间戳
+#[derive(Debug, Clone, PartialEq)]
+pub struct CvdHeader {
+    pub build_time: String,
+    pub version: u32,
+    pub signatures: u32,
+    pub functionality_level: u32,
+}
+
+impl CvdHeader {
+    /// 从文件头字节解析
+    pub fn parse(header: &[u8]) -> Option<Self> {
+        let header_str = String::from_utf8_lossy(header);
+        let parts: Vec<&str> = header_str.trim_end_matches(['\0', ' ']).split(':').collect();
+
+        if parts.len() < 5 || parts[0] != "ClamAV-VDB" {
+            return None;
+        }
+
+        Some(Self {
+            build_time: parts[1].trim().to_string(),
+            version: parts[2].trim().parse().ok()?,
+            signatures: parts[3].trim().parse().ok()?,
+            functionality_level: parts[4].trim().parse().ok()?,
+        })
+    }
+
+    /// 读取并解析数据库文件头
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let mut file = std::fs::File::open(path).ok()?;
+        let mut header = [0u8; CVD_HEADER_LEN];
+        file.read_exact(&mut header).ok()?;
+        Self::parse(&header)
+    }
+}
+
+/// 列出目录中的官方病毒库文件（按文件名排序）
+pub fn database_files<P: AsRef<Path>>(db_dir: P) -> Vec<PathBuf> {
+    files_with_extensions(db_dir.as_ref(), OFFICIAL_DB_EXTENSIONS)
+}
+
+/// 列出目录中的其他签名数据库文件（按文件名排序）
+pub fn signature_files<P: AsRef<Path>>(db_dir: P) -> Vec<PathBuf> {
+    files_with_extensions(db_dir.as_ref(), SIGNATURE_DB_EXTENSIONS)
+}
+
+fn files_with_extensions(dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .filter(|p| {
+                p.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| extensions.contains(&ext))
+                    .unwrap_or(false)
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+/// 读取指定数据库（如 "daily"）的版本号，优先 .cld 其次 .cvd
+pub fn database_version<P: AsRef<Path>>(db_dir: P, name: &str) -> Option<u32> {
+    ["cld", "cvd", "cud"].iter()
+        .filter_map(|ext| CvdHeader::read(db_dir.as_ref().join(format!("{}.{}", name, ext))))
+        .map(|h| h.version)
+        .next()
+}
+
+/// 检查病毒库目录是否可用于加载
+///
+/// 目录中至少有一个官方数据库文件，且每个文件头都能正确解析
+pub fn check_database_dir<P: AsRef<Path>>(db_dir: P) -> Result<(), String> {
+    let files = database_files(&db_dir);
+    if files.is_empty() {
+        return Err(format!("No database files found in {}", db_dir.as_ref().display()));
+    }
+
+    for file in &files {
+        if CvdHeader::read(file).is_none() {
+            return Err(format!("Invalid database header: {}", file.display()));
+        }
+    }
+
+    Ok(())
+}
+
+/// 校验未通过的数据库文件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedDatabase {
+    /// 文件名
+    pub file: String,
+    /// 拒绝原因
+    pub reason: String,
+    /// 移入 rejected/ 后的路径（移动失败时为空）
+    pub moved_to: Option<String>,
+}
+
+/// 病毒库目录校验结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DatabaseVerification {
+    /// 校验通过的文件名
+    pub accepted: Vec<String>,
+    /// 被拒绝的文件
+    pub rejected: Vec<RejectedDatabase>,
+    /// 从 known-good/ 恢复的文件名
+    pub restored: Vec<String>,
+}
+
+/// 数据库名称（去掉扩展名，如 daily.cld -> daily）
+fn database_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn is_official_database(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| OFFICIAL_DB_EXTENSIONS.contains(&ext))
+        .unwrap_or(false)
+}
+
+/// 校验病毒库目录中的每个数据库文件
+///
+/// verify 对官方数据库文件做完整校验（通常为 cl_cvdhead + cl_cvdverify），
+/// verify_signatures 校验其他签名文件（通常为在临时引擎中加载并编译）。
+/// 校验失败的文件移入 rejected/；若某个官方数据库因此没有可用文件，
+/// 从 known-good/ 恢复该数据库上一次成功加载的版本
+pub fn verify_database_dir<P, F, G>(db_dir: P, verify: F, verify_signatures: G) -> DatabaseVerification
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> Result<(), String>,
+    G: Fn(&Path) -> Result<(), String>,
+{
+    let db_dir = db_dir.as_ref();
+    let mut result = DatabaseVerification::default();
+
+    let official = database_files(db_dir).into_iter().map(|file| {
+        let check = match CvdHeader::read(&file) {
+            Some(_) => verify(&file),
+            None => Err("invalid or truncated database header".to_string()),
+        };
+        (file, check)
+    });
+    let signatures = signature_files(db_dir).into_iter().map(|file| {
+        let check = verify_signatures(&file);
+        (file, check)
+    });
+
+    for (file, check) in official.chain(signatures) {
+        match check {
+            Ok(()) => result.accepted.push(file_name(&file)),
+            Err(reason) => {
+                tracing::error!("Rejecting database file {}: {}", file.display(), reason);
+                let moved_to = reject_file(db_dir, &file)
+                    .map_err(|e| tracing::error!("Failed to move {} to {}/: {}", file.display(), REJECTED_DIR, e))
+                    .ok();
+                result.rejected.push(RejectedDatabase {
+                    file: file_name(&file),
+                    reason,
+                    moved_to: moved_to.map(|p| p.to_string_lossy().to_string()),
+                });
+            }
+        }
+    }
+
+    // 被拒绝且没有其他可用版本的官方数据库，从 known-good/ 恢复
+    let accepted_names: Vec<String> = result.accepted.iter()
+        .filter(|f| is_official_database(Path::new(f)))
+        .map(|f| database_name(Path::new(f)))
+        .collect();
+    let missing: Vec<String> = result.rejected.iter()
+        .filter(|r| is_official_database(Path::new(&r.file)))
+        .map(|r| database_name(Path::new(&r.file)))
+        .filter(|name| !accepted_names.contains(name))
+        .collect();
+
+    for name in missing {
+        if result.restored.iter().any(|f| database_name(Path::new(f)) == name) {
+            continue;
+        }
+        match restore_known_good(db_dir, &name, &verify) {
+            Some(restored) => {
+                tracing::warn!("Restored last known-good {} for rejected database {}", restored, name);
+                result.accepted.push(restored.clone());
+                result.restored.push(restored);
+            }
+            None => tracing::error!("No known-good copy available for database {}", name),
+        }
+    }
+
+    result
+}
+
+/// 将文件移入 rejected/，文件名附加时间戳避免覆盖
+fn reject_file(db_dir: &Path, file: &Path) -> std::io::Result<PathBuf> {
+    let rejected_dir = db_dir.join(REJECTED_DIR);
+    std::fs::create_dir_all(&rejected_dir)?;
+    let dest = rejected_dir.join(format!(
+        "{}.{}",
+        file_name(file),
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    std::fs::rename(file, &dest)?;
+    Ok(dest)
+}
+
+/// 从 known-good/ 恢复指定数据库，返回恢复的文件名
+fn restore_known_good<F>(db_dir: &Path, name: &str, verify: &F) -> Option<String>
+where
+    F: Fn(&Path) -> Result<(), String>,
+{
+    let candidate = database_files(db_dir.join(KNOWN_GOOD_DIR))
+        .into_iter()
+        .find(|p| database_name(p) == name)?;
+
+    if let Err(e) = verify(&candidate) {
+        tracing::error!("Known-good copy {} failed verification: {}", candidate.display(), e);
+        return None;
+    }
+
+    let dest = db_dir.join(file_name(&candidate));
+    std::fs::copy(&candidate, &dest)
+        .map_err(|e| tracing::error!("Failed to restore {}: {}", candidate.display(), e))
+        .ok()?;
+    Some(file_name(&candidate))
+}
+
+/// 记录成功加载的数据库文件到 known-good/，供后续加载失败时恢复
+///
+/// 只保存官方数据库文件；优先使用硬链接避免复制大文件；同名数据库的其他扩展名副本会被删除
+pub fn save_known_good<P: AsRef<Path>>(db_dir: P, files: &[String]) -> std::io::Result<()> {
+    let db_dir = db_dir.as_ref();
+    let known_good = db_dir.join(KNOWN_GOOD_DIR);
+    std::fs::create_dir_all(&known_good)?;
+
+    for file in files {
+        let src = db_dir.join(file);
+        if !src.is_file() || !is_official_database(&src) {
+            continue;
+        }
+        let dest = known_good.join(file);
+
+        // 同一数据库的旧副本（例如 daily.cvd 被 daily.cld 取代）
+        let name = database_name(&src);
+        for old in database_files(&known_good) {
+            if database_name(&old) == name && old != dest {
+                std::fs::remove_file(&old)?;
+            }
+        }
+
+        let unchanged = match (std::fs::metadata(&src), std::fs::metadata(&dest)) {
+            (Ok(a), Ok(b)) => a.len() == b.len()
+                && CvdHeader::read(&src).map(|h| h.version) == CvdHeader::read(&dest).map(|h| h.version),
+            _ => false,
+        };
+        if unchanged {
+            continue;
+        }
+
+        let tmp = known_good.join(format!(".{}.tmp", file));
+        let _ = std::fs::remove_file(&tmp);
+        if std::fs::hard_link(&src, &tmp).is_err() {
+            std::fs::copy(&src, &tmp)?;
+        }
+        std::fs::rename(&tmp, &dest)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cvd_header() {
+        let mut header = b"ClamAV-VDB:10 Feb 2026 07-25 +0000:27908:2077845:90:X:Y:raynman:1770708300".to_vec();
+        header.resize(CVD_HEADER_LEN, b' ');
+
+        let parsed = CvdHeader::parse(&header).unwrap();
+        assert_eq!(parsed.build_time, "10 Feb 2026 07-25 +0000");
+        assert_eq!(parsed.version, 27908);
+        assert_eq!(parsed.signatures, 2077845);
+        assert_eq!(parsed.functionality_level, 90);
+    }
+
+    #[test]
+    fn test_parse_invalid_header() {
+        assert!(CvdHeader::parse(b"not a database").is_none());
+        assert!(CvdHeader::parse(b"ClamAV-VDB:time:abc:1:2").is_none());
+    }
+
+    #[test]
+    fn test_check_database_dir() {
+        let dir = std::env::temp_dir().join(format!("clamav-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(check_database_dir(&dir).is_err());
+
+        let mut header = b"ClamAV-VDB:10 Feb 2026 07-25 +0000:27908:100:90:X:Y:builder:0".to_vec();
+        header.resize(CVD_HEADER_LEN, b' ');
+        std::fs::write(dir.join("daily.cvd"), &header).unwrap();
+        assert!(check_database_dir(&dir).is_ok());
+        assert_eq!(database_version(&dir, "daily"), Some(27908));
+
+        std::fs::write(dir.join("main.cvd"), b"truncated").unwrap();
+        assert!(check_database_dir(&dir).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn write_db(path: &Path, version: u32) {
+        let mut header = format!("ClamAV-VDB:10 Feb 2026 07-25 +0000:{}:100:90:X:Y:builder:0", version).into_bytes();
+        header.resize(CVD_HEADER_LEN, b' ');
+        std::fs::write(path, &header).unwrap();
+    }
+
+    #[test]
+    fn test_verify_rejects_and_restores_known_good() {
+        let dir = std::env::temp_dir().join(format!("clamav-verify-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_db(&dir.join("main.cvd"), 62);
+        write_db(&dir.join("daily.cvd"), 27900);
+
+        // 第一次加载成功，记录 known-good
+        let ok = verify_database_dir(&dir, |_| Ok(()), |_| Ok(()));
+        assert_eq!(ok.accepted, vec!["daily.cvd".to_string(), "main.cvd".to_string()]);
+        save_known_good(&dir, &ok.accepted).unwrap();
+
+        // 下载中断：daily.cvd 被半截的 daily.cld 取代
+        std::fs::remove_file(dir.join("daily.cvd")).unwrap();
+        std::fs::write(dir.join("daily.cld"), b"ClamAV-VDB:partial").unwrap();
+
+        let result = verify_database_dir(&dir, |_| Ok(()), |_| Ok(()));
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].file, "daily.cld");
+        assert!(result.rejected[0].moved_to.as_ref().unwrap().contains(REJECTED_DIR));
+        assert_eq!(result.restored, vec!["daily.cvd".to_string()]);
+        assert!(!dir.join("daily.cld").exists());
+        assert_eq!(database_version(&dir, "daily"), Some(27900));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_verify_reports_verifier_failure() {
+        let dir = std::env::temp_dir().join(format!("clamav-verify-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_db(&dir.join("bytecode.cvd"), 335);
+
+        let result = verify_database_dir(
+            &dir,
+            |_| Err("Can't verify database integrity".to_string()),
+            |_| Ok(()),
+        );
+        assert!(result.accepted.is_empty());
+        assert_eq!(result.rejected[0].reason, "Can't verify database integrity");
+        assert!(result.restored.is_empty());
+        assert!(database_files(&dir).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_verify_rejects_corrupt_signature_files() {
+        let dir = std::env::temp_dir().join(format!("clamav-verify-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_db(&dir.join("main.cvd"), 62);
+        std::fs::write(dir.join("third-party.ndb"), b"Sig:0:*:6576696c\n").unwrap();
+        std::fs::write(dir.join("broken.hdb"), b"not a hash signature").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+        let result = verify_database_dir(&dir, |_| Ok(()), |path| {
+            if path.extension().and_then(|e| e.to_str()) == Some("hdb") {
+                Err("Malformed database".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result.accepted, vec!["main.cvd".to_string(), "third-party.ndb".to_string()]);
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].file, "broken.hdb");
+        assert!(result.restored.is_empty());
+        assert!(!dir.join("broken.hdb").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        // known-good/ 只保存官方数据库
+        save_known_good(&dir, &result.accepted).unwrap();
+        assert!(dir.join(KNOWN_GOOD_DIR).join("main.cvd").exists());
+        assert!(!dir.join(KNOWN_GOOD_DIR).join("third-party.ndb").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -2,21 +2,28 @@
 //
 // 此模块实现扫描引擎的核心功能：
 // - 单文件扫描
-// - 目录扫描（两线程模式：发现 + 扫描）
+// - 目录扫描（发现线程 + N 个扫描工作线程，支持多个根目录和排除规则）
 // - 实时进度回调（含 EMA 速率计算）
-// - 暂停/恢复控制
+// - 暂停/恢复控制（在文件边界暂停，保留队列和计数）
+// - 定期检查点，从检查点恢复扫描
+// - 增量扫描（跳过扫描结果缓存中未修改的干净文件）
 // - 扫描任务管理
 
 use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::Arc;
-use std::time::{Instant, SystemTime};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 use anyhow::Result;
-use tokio::sync::{mpsc, oneshot, Mutex as AsyncMutex};
+use tokio::sync::{mpsc, oneshot, Mutex as AsyncMutex, Notify};
 
 use super::types::*;
-use super::ffi::{ClamAVEngine, ScanOptions, ClamAVError};
+use super::ffi::{ClamAVEngine, ScanOptions, ScanResult, ClamAVError};
+use super::manager::EngineHandle;
+use super::scanner::Scanner;
+use super::traversal::{io_error_category, open_discovered, DiscoveryOptions, Entry, FileId, Traversal};
+use super::checkpoint::{CheckpointTracker, DirId, ResumeState, RootCheckpoint, ScanCheckpoint, CHECKPOINT_INTERVAL_SECS};
+use super::verdict_cache::{FileIdentity, Verdict, VerdictCachePolicy};
 
 // 为 ClamAVEngine 实现 Send 和 Sync
 // 因为 ClamAVEngine 内部使用原生指针，需要 unsafe 实现
@@ -45,6 +52,8 @@ impl Default for TaskPriority {
 pub enum ScanTarget {
     File(PathBuf),
     Directory(PathBuf),
+    /// 多个根路径（文件或目录）在同一任务中扫描，分别统计进度和结果
+    Roots(Vec<PathBuf>),
 }
 
 impl ScanTarget {
@@ -57,10 +66,21 @@ impl ScanTarget {
         }
     }
 
+    /// 多个路径组成的扫描目标：只有一个路径时退化为单文件或单目录
+    pub fn from_paths<P: AsRef<Path>>(paths: &[P]) -> Option<Self> {
+        match paths {
+            [] => None,
+            [path] => Some(Self::from_path(path)),
+            _ => Some(Self::Roots(paths.iter().map(|p| p.as_ref().to_path_buf()).collect())),
+        }
+    }
+
+    /// 目标路径（多根目录时为第一个根目录）
     pub fn path(&self) -> &Path {
         match self {
             Self::File(p) => p,
             Self::Directory(p) => p,
+            Self::Roots(roots) => roots.first().map(|p| p.as_path()).unwrap_or(Path::new("")),
         }
     }
 }
@@ -71,6 +91,56 @@ pub type ProgressCallback = Arc<dyn Fn(ScanProgress) + Send + Sync>;
 /// 完成回调类型 (task_id, result，使用引用因为 anyhow::Error 不实现 Clone)
 pub type CompletionCallback = Arc<dyn Fn(&str, &Result<ScanOutcome>) + Send + Sync>;
 
+/// 检查点回调类型 (task_id, checkpoint)，目录扫描期间定期调用
+pub type CheckpointCallback = Arc<dyn Fn(&str, ScanCheckpoint) + Send + Sync>;
+
+/// 检查点回调和保存间隔（任务循环与目录扫描共享）
+#[derive(Clone)]
+struct CheckpointHooks {
+    callback: Arc<AsyncMutex<Option<CheckpointCallback>>>,
+    interval_ms: Arc<AtomicU64>,
+    /// 当前执行的任务（处理任务时设置）
+    task_id: String,
+}
+
+impl CheckpointHooks {
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.load(Ordering::Relaxed))
+    }
+
+    async fn save(&self, checkpoint: ScanCheckpoint) {
+        let cb = self.callback.lock().await;
+        if let Some(ref f) = *cb {
+            f(&self.task_id, checkpoint);
+        }
+    }
+}
+
+/// 任务循环共享的引擎状态（ScanEngine 创建时构造，交给任务处理循环）
+struct EngineContext<S: Scanner> {
+    engine: EngineHandle<S>,
+    task_queue: Arc<AsyncMutex<TaskQueue>>,
+    progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
+    completion_callback: Arc<AsyncMutex<Option<CompletionCallback>>>,
+    checkpoint: CheckpointHooks,
+    cancel_flag: Arc<AsyncMutex<bool>>,
+    pause: Arc<PauseControl>,
+    workers: Arc<AtomicUsize>,
+}
+
+/// 单个扫描任务的执行上下文（execute_scan 和 scan_directory 使用）
+struct TaskContext<S: Scanner> {
+    engine: EngineHandle<S>,
+    progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
+    checkpoint: CheckpointHooks,
+    cancel_flag: Arc<AsyncMutex<bool>>,
+    pause: Arc<PauseControl>,
+    workers: usize,
+    discovery: DiscoveryOptions,
+    resume: Option<Arc<ResumeState>>,
+    cache: Option<VerdictCachePolicy>,
+}
+
 /// 扫描任务状态
 #[derive(Debug, Clone, PartialEq)]
 pub enum TaskState {
@@ -82,6 +152,47 @@ pub enum TaskState {
     Cancelled,
 }
 
+/// 任务暂停控制
+///
+/// 发现线程和工作线程在文件边界检查，暂停期间等待恢复；文件队列、计数和已发现的文件都保留
+#[derive(Debug, Default)]
+pub struct PauseControl {
+    paused: AtomicBool,
+    notify: Notify,
+}
+
+impl PauseControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// 恢复并唤醒所有等待的线程
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// 暂停时等待恢复，未暂停时立即返回
+    pub async fn wait_if_paused(&self) {
+        loop {
+            // 先注册等待再检查状态，避免错过检查与等待之间的 resume
+            let notified = self.notify.notified();
+            if !self.is_paused() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
 /// 扫描任务
 #[derive(Debug, Clone)]
 pub struct ScanTask {
@@ -94,6 +205,12 @@ pub struct ScanTask {
     pub started_at: Option<SystemTime>,
     pub completed_at: Option<SystemTime>,
     pub progress: ScanProgress,
+    /// 目录遍历选项（排除规则、符号链接、文件系统边界）
+    pub discovery: DiscoveryOptions,
+    /// 从检查点恢复时的已完成状态
+    pub resume: Option<Arc<ResumeState>>,
+    /// 扫描结果缓存（目录扫描跳过未修改的干净文件）
+    pub cache: Option<VerdictCachePolicy>,
 }
 
 impl ScanTask {
@@ -108,9 +225,29 @@ impl ScanTask {
             started_at: None,
             completed_at: None,
             progress: ScanProgress::new(),
+            discovery: DiscoveryOptions::default(),
+            resume: None,
+            cache: None,
         }
     }
 
+    pub fn with_discovery(mut self, discovery: DiscoveryOptions) -> Self {
+        self.discovery = discovery;
+        self
+    }
+
+    /// 从检查点继续扫描
+    pub fn with_resume(mut self, resume: ResumeState) -> Self {
+        self.resume = Some(Arc::new(resume));
+        self
+    }
+
+    /// 使用扫描结果缓存
+    pub fn with_cache(mut self, cache: VerdictCachePolicy) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     pub fn with_priority(mut self, priority: TaskPriority) -> Self {
         self.priority = priority;
         self
@@ -198,7 +335,7 @@ impl Default for TaskQueue {
 #[derive(Debug)]
 pub enum EngineCommand {
     SubmitTask {
-        task: ScanTask,
+        task: Box<ScanTask>,
         reply: oneshot::Sender<Result<TaskId>>,
     },
     CancelTask {
@@ -226,42 +363,54 @@ pub enum EngineCommand {
 }
 
 /// 扫描引擎
-pub struct ScanEngine {
-    engine: Arc<ClamAVEngine>,
+pub struct ScanEngine<S: Scanner = ClamAVEngine> {
+    engine: EngineHandle<S>,
     task_queue: Arc<AsyncMutex<TaskQueue>>,
     command_tx: mpsc::UnboundedSender<EngineCommand>,
     progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
     completion_callback: Arc<AsyncMutex<Option<CompletionCallback>>>,
+    checkpoint: CheckpointHooks,
     cancel_flag: Arc<AsyncMutex<bool>>,
+    pause: Arc<PauseControl>,
+    /// 目录扫描的工作线程数（下一个任务开始时生效）
+    workers: Arc<AtomicUsize>,
 }
 
-impl ScanEngine {
+/// 默认扫描工作线程数（与 ClamAVConfig.max_threads 的默认值一致）
+pub const DEFAULT_SCAN_WORKERS: usize = 4;
+
+impl<S: Scanner> ScanEngine<S> {
     /// 创建新的扫描引擎
-    pub fn new(clamav_engine: Arc<ClamAVEngine>) -> Self {
+    pub fn new(clamav_engine: EngineHandle<S>) -> Self {
         let (command_tx, mut command_rx) = mpsc::unbounded_channel();
 
         let engine = clamav_engine;
         let task_queue = Arc::new(AsyncMutex::new(TaskQueue::new()));
         let progress_callback = Arc::new(AsyncMutex::new(None));
         let completion_callback = Arc::new(AsyncMutex::new(None));
+        let checkpoint = CheckpointHooks {
+            callback: Arc::new(AsyncMutex::new(None)),
+            interval_ms: Arc::new(AtomicU64::new(CHECKPOINT_INTERVAL_SECS * 1000)),
+            task_id: String::new(),
+        };
         let cancel_flag = Arc::new(AsyncMutex::new(false));
+        let pause = Arc::new(PauseControl::new());
+        let workers = Arc::new(AtomicUsize::new(DEFAULT_SCAN_WORKERS));
 
         // 启动任务处理循环
-        let engine_clone = engine.clone();
-        let queue_clone = task_queue.clone();
-        let progress_clone = progress_callback.clone();
-        let completion_clone = completion_callback.clone();
-        let cancel_clone = cancel_flag.clone();
+        let context = EngineContext {
+            engine: engine.clone(),
+            task_queue: task_queue.clone(),
+            progress_callback: progress_callback.clone(),
+            completion_callback: completion_callback.clone(),
+            checkpoint: checkpoint.clone(),
+            cancel_flag: cancel_flag.clone(),
+            pause: pause.clone(),
+            workers: workers.clone(),
+        };
 
         tokio::spawn(async move {
-            Self::run_task_loop(
-                engine_clone,
-                queue_clone,
-                progress_clone,
-                completion_clone,
-                cancel_clone,
-                &mut command_rx,
-            ).await;
+            Self::run_task_loop(context, &mut command_rx).await;
         });
 
         Self {
@@ -270,10 +419,29 @@ impl ScanEngine {
             command_tx,
             progress_callback,
             completion_callback,
+            checkpoint,
             cancel_flag,
+            pause,
+            workers,
         }
     }
 
+    /// 设置目录扫描的工作线程数（至少 1 个）
+    pub fn with_workers(self, workers: usize) -> Self {
+        self.set_workers(workers);
+        self
+    }
+
+    /// 修改目录扫描的工作线程数，正在运行的任务不受影响
+    pub fn set_workers(&self, workers: usize) {
+        self.workers.store(workers.max(1), Ordering::Relaxed);
+    }
+
+    /// 目录扫描的工作线程数
+    pub fn workers(&self) -> usize {
+        self.workers.load(Ordering::Relaxed)
+    }
+
     /// 设置进度回调
     pub async fn set_progress_callback(&self, callback: ProgressCallback) {
         let mut cb = self.progress_callback.lock().await;
@@ -286,14 +454,46 @@ impl ScanEngine {
         *cb = Some(callback);
     }
 
+    /// 设置检查点回调
+    pub async fn set_checkpoint_callback(&self, callback: CheckpointCallback) {
+        let mut cb = self.checkpoint.callback.lock().await;
+        *cb = Some(callback);
+    }
+
+    /// 设置检查点保存间隔
+    pub fn with_checkpoint_interval(self, interval: Duration) -> Self {
+        self.checkpoint.interval_ms.store(interval.as_millis() as u64, Ordering::Relaxed);
+        self
+    }
+
     /// 提交扫描任务
-    pub async fn submit_task(&self, target: ScanTarget, priority: TaskPriority, options: ScanOptions) -> Result<TaskId> {
-        let task = ScanTask::new(target, priority, options);
-        let task_id = task.id.clone();
+    pub async fn submit_task(
+        &self,
+        target: ScanTarget,
+        priority: TaskPriority,
+        options: ScanOptions,
+        discovery: DiscoveryOptions,
+    ) -> Result<TaskId> {
+        self.submit_scan_task(ScanTask::new(target, priority, options).with_discovery(discovery)).await
+    }
+
+    /// 提交从检查点继续的扫描任务：已完成目录中的文件不再扫描，计数从检查点开始累计
+    pub async fn submit_resumed_task(
+        &self,
+        target: ScanTarget,
+        priority: TaskPriority,
+        options: ScanOptions,
+        discovery: DiscoveryOptions,
+        resume: ResumeState,
+    ) -> Result<TaskId> {
+        self.submit_scan_task(ScanTask::new(target, priority, options).with_discovery(discovery).with_resume(resume)).await
+    }
 
+    /// 提交已构造的扫描任务（需要同时设置检查点、缓存等选项时使用）
+    pub async fn submit_scan_task(&self, task: ScanTask) -> Result<TaskId> {
         let (reply_tx, reply_rx) = oneshot::channel();
         self.command_tx.send(EngineCommand::SubmitTask {
-            task,
+            task: Box::new(task),
             reply: reply_tx,
         })?;
 
@@ -367,29 +567,24 @@ impl ScanEngine {
 
     /// 任务处理循环
     async fn run_task_loop(
-        engine: Arc<ClamAVEngine>,
-        task_queue: Arc<AsyncMutex<TaskQueue>>,
-        progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
-        completion_callback: Arc<AsyncMutex<Option<CompletionCallback>>>,
-        cancel_flag: Arc<AsyncMutex<bool>>,
+        context: EngineContext<S>,
         command_rx: &mut mpsc::UnboundedReceiver<EngineCommand>,
     ) {
+        let task_queue = &context.task_queue;
+        let cancel_flag = &context.cancel_flag;
+        let pause = &context.pause;
+
         while let Some(cmd) = command_rx.recv().await {
             match cmd {
                 EngineCommand::SubmitTask { task, reply } => {
                     let mut queue = task_queue.lock().await;
-                    queue.push(task.clone());
-                    let _ = reply.send(Ok(task.id));
+                    let task_id = task.id.clone();
+                    queue.push(*task);
+                    let _ = reply.send(Ok(task_id));
                     drop(queue);
 
                     // 如果没有当前任务，开始处理新任务
-                    Self::process_next_task(
-                        engine.clone(),
-                        task_queue.clone(),
-                        progress_callback.clone(),
-                        completion_callback.clone(),
-                        cancel_flag.clone(),
-                    ).await;
+                    Self::process_next_task(&context).await;
                 }
 
                 EngineCommand::CancelTask { task_id, reply } => {
@@ -399,6 +594,8 @@ impl ScanEngine {
                         *flag = true;
                         tracing::info!("Set cancel flag for task: {}", task_id);
                     }
+                    // 唤醒暂停中的线程，使其看到取消标志
+                    pause.resume();
 
                     let mut queue = task_queue.lock().await;
                     let current = queue.current();
@@ -420,13 +617,13 @@ impl ScanEngine {
                 }
 
                 EngineCommand::PauseTask { task_id, reply } => {
-                    let queue = task_queue.lock().await;
-                    let current = queue.current();
-                    let result = match current {
-                        Some(task) if task.id == task_id => {
-                            // 暂停当前任务
-                            let mut flag = cancel_flag.lock().await;
-                            *flag = true;
+                    let mut queue = task_queue.lock().await;
+                    let result = match queue.current_task.as_mut() {
+                        Some(task) if task.id == task_id && task.state == TaskState::Running => {
+                            // 暂停当前任务：各线程在处理完当前文件后等待
+                            pause.pause();
+                            task.state = TaskState::Paused;
+                            tracing::info!("Paused task: {}", task_id);
                             true
                         }
                         _ => false,
@@ -435,18 +632,18 @@ impl ScanEngine {
                 }
 
                 EngineCommand::ResumeTask { task_id, reply } => {
-                    let mut flag = cancel_flag.lock().await;
-                    *flag = false;
-                    let _ = reply.send(Ok(true));
-
-                    // 重新开始任务处理
-                    Self::process_next_task(
-                        engine.clone(),
-                        task_queue.clone(),
-                        progress_callback.clone(),
-                        completion_callback.clone(),
-                        cancel_flag.clone(),
-                    ).await;
+                    let mut queue = task_queue.lock().await;
+                    let result = match queue.current_task.as_mut() {
+                        Some(task) if task.id == task_id && task.state == TaskState::Paused => {
+                            // 从暂停处继续
+                            task.state = TaskState::Running;
+                            pause.resume();
+                            tracing::info!("Resumed task: {}", task_id);
+                            true
+                        }
+                        _ => false,
+                    };
+                    let _ = reply.send(Ok(result));
                 }
 
                 EngineCommand::GetTask { task_id, reply } => {
@@ -478,14 +675,8 @@ impl ScanEngine {
     }
 
     /// 处理下一个任务
-    async fn process_next_task(
-        engine: Arc<ClamAVEngine>,
-        task_queue: Arc<AsyncMutex<TaskQueue>>,
-        progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
-        completion_callback: Arc<AsyncMutex<Option<CompletionCallback>>>,
-        cancel_flag: Arc<AsyncMutex<bool>>,
-    ) {
-        let mut queue = task_queue.lock().await;
+    async fn process_next_task(context: &EngineContext<S>) {
+        let mut queue = context.task_queue.lock().await;
 
         // 如果已有任务在运行，跳过
         if queue.current().is_some() {
@@ -494,7 +685,7 @@ impl ScanEngine {
         }
 
         // 获取下一个任务
-        let task = match queue.pop() {
+        let mut task = match queue.pop() {
             Some(t) => t,
             None => {
                 tracing::debug!("No tasks in queue");
@@ -503,28 +694,38 @@ impl ScanEngine {
         };
 
         tracing::info!("Processing scan task: id={}, target={:?}", task.id, task.target);
+        task.state = TaskState::Running;
+        task.started_at = Some(SystemTime::now());
         queue.set_current(task.clone());
         let task_id = task.id.clone();
         let target = task.target.clone();
-        let options = task.options.clone();
+        let options = task.options;
+        let task_context = TaskContext {
+            engine: context.engine.clone(),
+            progress_callback: context.progress_callback.clone(),
+            checkpoint: CheckpointHooks { task_id: task_id.clone(), ..context.checkpoint.clone() },
+            cancel_flag: context.cancel_flag.clone(),
+            pause: context.pause.clone(),
+            workers: context.workers.load(Ordering::Relaxed),
+            discovery: task.discovery.clone(),
+            resume: task.resume.clone(),
+            cache: task.cache.clone(),
+        };
         drop(queue);
 
-        // 重置取消标志
+        // 重置取消标志和暂停状态
         {
-            let mut flag = cancel_flag.lock().await;
+            let mut flag = context.cancel_flag.lock().await;
             *flag = false;
         }
+        context.pause.resume();
 
         // 在独立的 tokio task 中执行扫描，避免阻塞命令循环
+        let task_queue = context.task_queue.clone();
+        let completion_callback = context.completion_callback.clone();
         tokio::spawn(async move {
             tracing::info!("Starting scan execution for task {} in background", task_id);
-            let result = Self::execute_scan(
-                engine,
-                &target,
-                &options,
-                progress_callback,
-                cancel_flag.clone(),
-            ).await;
+            let result = Self::execute_scan(task_context, &target, &options).await;
 
             // 更新任务状态
             tracing::info!("Scan task {} completed with result: {:?}", task_id, result.is_ok());
@@ -543,12 +744,16 @@ impl ScanEngine {
 
     /// 执行扫描
     async fn execute_scan(
-        engine: Arc<ClamAVEngine>,
+        context: TaskContext<S>,
         target: &ScanTarget,
         options: &ScanOptions,
-        progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
-        cancel_flag: Arc<AsyncMutex<bool>>,
     ) -> Result<ScanOutcome> {
+        // 多根目录：不存在的根目录单独记录，其余根目录照常扫描
+        if let ScanTarget::Roots(roots) = target {
+            tracing::info!("Executing scan for {} roots: {:?}", roots.len(), roots);
+            return Self::scan_directory(context, roots, options).await;
+        }
+
         let path = target.path();
 
         // 检查路径是否存在
@@ -562,17 +767,17 @@ impl ScanEngine {
 
         match target {
             ScanTarget::File(_) => {
-                Self::scan_file(engine, path, options, progress_callback, cancel_flag).await
+                Self::scan_file(context.engine, path, options, context.progress_callback, context.cancel_flag).await
             }
-            ScanTarget::Directory(_) => {
-                Self::scan_directory(engine, path, options, progress_callback, cancel_flag).await
+            _ => {
+                Self::scan_directory(context, &[path.to_path_buf()], options).await
             }
         }
     }
 
     /// 扫描单个文件
     async fn scan_file(
-        engine: Arc<ClamAVEngine>,
+        engine: EngineHandle<S>,
         path: &Path,
         options: &ScanOptions,
         progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
@@ -592,6 +797,8 @@ impl ScanEngine {
                 current_file: Some(FilePath(path.display().to_string())),
                 discovered_files: DiscoveredFiles(1),
                 scan_rate: None,
+                worker_files: Vec::new(),
+                roots: Vec::new(),
             },
         ).await;
 
@@ -600,74 +807,141 @@ impl ScanEngine {
             return Ok(ScanOutcome::failed("Scan cancelled".to_string()));
         }
 
-        // 在 spawn_blocking 中执行同步扫描
+        // 在 spawn_blocking 中执行同步扫描（扫描时获取当前引擎，重载后自动使用新引擎）
         let engine_clone = engine.clone();
         let path_str = path.to_string_lossy().to_string();
-        let result = tokio::task::spawn_blocking(move || {
-            engine_clone.scan_file(&path_str, options)
-        }).await??;
+        let scan_result = tokio::task::spawn_blocking(move || {
+            engine_clone.current()?.scan_file(&path_str, options)
+        }).await?;
+
+        // 文件未能扫描时记录错误分类，而不是让整个任务失败
+        let result = match scan_result {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::warn!("Error scanning {} [{}]: {}", path.display(), e.category(), e);
+                let mut errors = ErrorCounts::default();
+                errors.record(e.category());
+                let event = FileEvent::failed(path.display().to_string(), e.category(), e.code(), e.to_string());
+                return Ok(ScanOutcome::success(1, 0, Vec::new())
+                    .with_errors(errors)
+                    .with_file_events(vec![event]));
+            }
+        };
+
+        let mut errors = ErrorCounts::default();
+        if result.is_encrypted() {
+            errors.record(ErrorCategory::Encrypted);
+        }
 
         // 更新进度
-        let is_infected = result.is_infected;
         Self::update_progress(
             &progress_callback,
             ScanProgress {
                 percent: ProgressPercent(100),
                 scanned_files: ScannedFiles(1),
                 total_files: TotalFiles(1),
-                threats_found: ThreatsFound(if is_infected { 1 } else { 0 }),
+                threats_found: ThreatsFound(result.virus_names.len() as u32),
                 current_file: None,
                 discovered_files: DiscoveredFiles(1),
                 scan_rate: None,
+                worker_files: Vec::new(),
+                roots: Vec::new(),
             },
         ).await;
 
-        let threats = if is_infected {
-            vec![(
-                FilePath(result.filename),
-                VirusName(result.virus_name.unwrap_or_else(|| "Unknown".to_string()))
-            )]
-        } else {
-            vec![]
-        };
+        let threats = result.threats();
 
         Ok(ScanOutcome::success(
             1,
             1,
             threats,
-        ))
+        ).with_errors(errors))
     }
 
-    /// 扫描目录（两线程 + EMA 模式）
-    /// 发现线程：遍历目录，统计文件数，发送文件到队列
-    /// 扫描线程：从队列取文件并扫描
-    /// EMA：计算扫描速率，估算剩余时间
+    /// 扫描目录（发现线程 + 工作线程池 + EMA 模式）
+    /// 发现线程：依次遍历各根目录（见 traversal 模块：不跟随符号链接循环、跳过特殊文件、按 inode 去重），
+    ///           跳过被排除的路径（被排除的目录不再进入），按根目录统计文件数，发送文件到队列
+    /// 工作线程：N 个工作线程共享同一队列，各自取文件并扫描（cl_engine 可在线程间共享）
+    /// 进度线程：每 100ms 汇总计数，计算 EMA 扫描速率并上报各工作线程的当前文件；按间隔保存检查点
+    /// 从检查点恢复时：跳过已完成目录中的文件，计数、威胁和文件事件从检查点开始累计
+    /// 使用缓存时：未修改的干净文件不再扫描，直接计为已扫描
     async fn scan_directory(
-        engine: Arc<ClamAVEngine>,
-        path: &Path,
+        context: TaskContext<S>,
+        roots: &[PathBuf],
         options: &ScanOptions,
-        progress_callback: Arc<AsyncMutex<Option<ProgressCallback>>>,
-        cancel_flag: Arc<AsyncMutex<bool>>,
     ) -> Result<ScanOutcome> {
-        tracing::info!("Starting directory scan (two-thread + EMA mode): {}", path.display());
+        let TaskContext {
+            engine,
+            progress_callback,
+            checkpoint,
+            cancel_flag,
+            pause,
+            workers,
+            discovery,
+            resume,
+            cache,
+        } = context;
+        let workers = workers.max(1);
+        tracing::info!("Starting directory scan ({} workers + EMA mode): {:?}", workers, roots);
 
         // 检查取消标志
         if *cancel_flag.lock().await {
             return Ok(ScanOutcome::failed("Scan cancelled".to_string()));
         }
 
+        // 目录完成情况（检查点），恢复时从检查点的状态开始
+        let tracker = Arc::new(CheckpointTracker::new(roots.len(), resume.as_deref()));
+        let resumed = resume.map(|r| r.state.clone()).unwrap_or_default();
+        if !resumed.counters.roots.is_empty() {
+            tracing::info!("Resuming directory scan from checkpoint: {} files already scanned, {} threats",
+                          resumed.counters.scanned_files(), resumed.counters.threats_found());
+        }
+
         // 共享状态（使用原子操作提高性能）
-        let discovered_count = Arc::new(AtomicU32::new(0));  // 已发现的文件数
-        let scanned_count = Arc::new(AtomicU32::new(0));     // 已扫描的文件数
-        let threats_count = Arc::new(AtomicU32::new(0));     // 发现的威胁数
-        let discovery_complete = Arc::new(AtomicBool::new(false)); // 发现是否完成
+        let discovered_count = Arc::new(AtomicU32::new(resumed.counters.discovered_files()));  // 已发现的文件数
+        let scanned_count = Arc::new(AtomicU32::new(resumed.counters.scanned_files()));        // 已扫描的文件数
+        let threats_count = Arc::new(AtomicU32::new(resumed.counters.threats_found()));        // 发现的威胁数
+        let cached_count = Arc::new(AtomicU32::new(resumed.counters.cached_files()));          // 使用缓存结果跳过的文件数
+        let workers_complete = Arc::new(AtomicBool::new(false)); // 工作线程是否全部结束
         let cancelled = Arc::new(AtomicBool::new(false));    // 是否取消
+        let scan_start_time = Arc::new(OnceLock::<Instant>::new()); // 第一个文件开始扫描的时间
+        let root_counters: Arc<Vec<RootCounters>> = Arc::new((0..roots.len())
+            .map(|i| resumed.counters.roots.get(i).map(RootCounters::resumed).unwrap_or_default())
+            .collect());
+
+        // 不存在的根目录直接记录失败原因，不参与遍历
+        let root_failures: Vec<Option<String>> = roots.iter()
+            .map(|root| (!root.exists()).then(|| format!("Path does not exist: {}", root.display())))
+            .collect();
+        if root_failures.iter().all(|f| f.is_some()) {
+            let error = format!("No scan root exists: {:?}", roots);
+            tracing::error!("{}", error);
+            return Ok(ScanOutcome::failed(error));
+        }
+
+        // 缓存的结果只对完整的扫描选项有效
+        let cache = match cache {
+            Some(cache) if *options == ScanOptions::default() => Some(cache),
+            Some(_) => {
+                tracing::info!("Verdict cache disabled: scan options differ from defaults");
+                None
+            }
+            None => None,
+        };
 
-        // 文件队列通道（发现线程 -> 扫描线程）
-        let (file_tx, mut file_rx) = mpsc::unbounded_channel::<PathBuf>();
+        // 文件队列通道（发现线程 -> 工作线程，附带根目录编号和所在目录）；发现线程结束时发送端释放，队列取空后通道关闭
+        let (file_tx, file_rx) = mpsc::unbounded_channel::<(usize, DirId, PathBuf, FileId)>();
+        let file_rx = Arc::new(AsyncMutex::new(file_rx));
+
+        // 各工作线程正在扫描的文件（只在同步代码中短暂加锁）
+        let worker_files = Arc::new(std::sync::Mutex::new(vec![None::<String>; workers]));
 
         // 威胁收集（需要 Mutex 保护）
-        let all_threats = Arc::new(AsyncMutex::new(Vec::new()));
+        let all_threats = Arc::new(AsyncMutex::new(resumed.threats.iter().map(DetectedThreat::from).collect::<Vec<_>>()));
+        let error_counts = Arc::new(AsyncMutex::new(resumed.counters.errors));
+        // 未被扫描的文件记录（只在同步代码中短暂加锁）
+        let file_events = Arc::new(std::sync::Mutex::new(resumed.file_events.clone()));
+        let resumed_scanned = resumed.counters.scanned_files();
 
         // 发送初始进度
         Self::update_progress(
@@ -677,203 +951,361 @@ impl ScanEngine {
                 scanned_files: ScannedFiles(0),
                 total_files: TotalFiles(0),
                 threats_found: ThreatsFound(0),
-                current_file: Some(FilePath(path.display().to_string())),
+                current_file: roots.first().map(|p| FilePath(p.display().to_string())),
                 discovered_files: DiscoveredFiles(0),
                 scan_rate: None,
+                worker_files: vec![None; workers],
+                roots: root_progress(roots, &root_counters),
             },
         ).await;
 
         // ========== 发现线程 ==========
         let discovery_cancelled = cancelled.clone();
         let discovery_discovered = discovered_count.clone();
-        let discovery_path = path.to_path_buf();
+        let discovery_roots: Vec<(usize, PathBuf)> = roots.iter().cloned().enumerate()
+            .filter(|(index, _)| root_failures[*index].is_none())
+            .collect();
+        let discovery_counters = root_counters.clone();
+        let discovery_events = file_events.clone();
+        let discovery_errors = error_counts.clone();
+        let discovery_pause = pause.clone();
+        let discovery_tracker = tracker.clone();
+        let mut traversal = Traversal::new(discovery);
 
         let discovery_handle = tokio::spawn(async move {
-            let mut dir_queue = vec![discovery_path];
             let mut dirs_scanned: u32 = 0;
 
-            while let Some(dir) = dir_queue.pop() {
-                // 检查取消
-                if discovery_cancelled.load(Ordering::Relaxed) {
-                    tracing::info!("Discovery cancelled");
-                    break;
-                }
+            'roots: for (root_index, root) in discovery_roots {
+                let root_counter = &discovery_counters[root_index];
 
-                let entries = match std::fs::read_dir(&dir) {
-                    Ok(e) => e,
-                    Err(e) => {
-                        tracing::trace!("Failed to read directory {}: {}", dir.display(), e);
+                let mut dir_queue = match traversal.enter_root(&root) {
+                    Entry::Directory(dir) => vec![dir],
+                    Entry::File(file, file_id) => {
+                        root_counter.discovery_complete.store(true, Ordering::Relaxed);
+                        if discovery_tracker.is_resumed(&file) {
+                            continue;
+                        }
+                        // 根路径本身是文件时直接加入队列（作为只含该文件的目录提交）
+                        let dir_id = discovery_tracker.open_dir(root_index, file.clone());
+                        discovery_tracker.add_file(dir_id);
+                        discovery_tracker.close_dir(dir_id);
+                        discovery_discovered.fetch_add(1, Ordering::Relaxed);
+                        root_counter.discovered.fetch_add(1, Ordering::Relaxed);
+                        if file_tx.send((root_index, dir_id, file, file_id)).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Entry::Skipped(event) => {
+                        // 已在其他根路径下遍历过，或是特殊文件
+                        root_counter.discovery_complete.store(true, Ordering::Relaxed);
+                        if discovery_tracker.is_resumed(&root) {
+                            continue;
+                        }
+                        let dir_id = discovery_tracker.open_dir(root_index, root.clone());
+                        if let Some(event) = event {
+                            Self::record_discovery_event(&discovery_events, &discovery_errors, root_counter, event.clone()).await;
+                            discovery_tracker.add_event(dir_id, event);
+                        }
+                        discovery_tracker.close_dir(dir_id);
                         continue;
                     }
                 };
 
-                dirs_scanned += 1;
+                while let Some(dir) = dir_queue.pop() {
+                    // 检查取消
+                    if discovery_cancelled.load(Ordering::Relaxed) {
+                        tracing::info!("Discovery cancelled");
+                        break 'roots;
+                    }
+
+                    // 恢复前已完成的目录只继续遍历子目录
+                    let resumed_dir = discovery_tracker.is_resumed(&dir);
 
-                for entry in entries {
-                    let entry = match entry {
+                    let entries = match traversal.read_dir(&dir) {
                         Ok(e) => e,
-                        Err(_) => continue,
+                        Err(e) => {
+                            // 无法读取的目录记录为失败，其中的文件没有被扫描
+                            tracing::warn!("Failed to read directory {}: {}", dir.display(), e);
+                            if !resumed_dir {
+                                let event = FileEvent::failed(dir.display().to_string(), io_error_category(&e), None, e.to_string());
+                                Self::record_discovery_event(&discovery_events, &discovery_errors, root_counter, event.clone()).await;
+                                let dir_id = discovery_tracker.open_dir(root_index, dir.clone());
+                                discovery_tracker.add_event(dir_id, event);
+                                discovery_tracker.close_dir(dir_id);
+                            }
+                            continue;
+                        }
                     };
-                    let entry_path = entry.path();
 
-                    if discovery_cancelled.load(Ordering::Relaxed) {
-                        break;
-                    }
+                    dirs_scanned += 1;
+
+                    let dir_id = (!resumed_dir)
+                        .then(|| discovery_tracker.open_dir(root_index, dir.clone()));
+
+                    for entry in entries {
+                        let entry = match entry {
+                            Ok(e) => e,
+                            Err(e) => {
+                                // 列目录中途出错，剩余条目无法读取
+                                tracing::warn!("Failed to list directory {}: {}", dir.display(), e);
+                                if let Some(dir_id) = dir_id {
+                                    let event = FileEvent::failed(dir.display().to_string(), io_error_category(&e), None, e.to_string());
+                                    Self::record_discovery_event(&discovery_events, &discovery_errors, root_counter, event.clone()).await;
+                                    discovery_tracker.add_event(dir_id, event);
+                                }
+                                break;
+                            }
+                        };
 
-                    if entry_path.is_dir() {
-                        dir_queue.push(entry_path);
-                    } else if entry_path.is_file() {
-                        // 增加发现计数
-                        discovery_discovered.fetch_add(1, Ordering::Relaxed);
-                        // 发送文件到扫描队列
-                        if file_tx.send(entry_path).is_err() {
+                        // 暂停时在条目之间等待
+                        discovery_pause.wait_if_paused().await;
+
+                        if discovery_cancelled.load(Ordering::Relaxed) {
                             break;
                         }
+
+                        match (traversal.classify(entry.path()), dir_id) {
+                            (Entry::Directory(dir), _) => dir_queue.push(dir),
+                            (Entry::File(file, file_id), Some(dir_id)) => {
+                                // 增加发现计数
+                                discovery_discovered.fetch_add(1, Ordering::Relaxed);
+                                root_counter.discovered.fetch_add(1, Ordering::Relaxed);
+                                discovery_tracker.add_file(dir_id);
+                                // 发送文件到扫描队列
+                                if file_tx.send((root_index, dir_id, file, file_id)).is_err() {
+                                    break 'roots;
+                                }
+                            }
+                            (Entry::Skipped(Some(event)), Some(dir_id)) => {
+                                Self::record_discovery_event(&discovery_events, &discovery_errors, root_counter, event.clone()).await;
+                                discovery_tracker.add_event(dir_id, event);
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    if let Some(dir_id) = dir_id {
+                        discovery_tracker.close_dir(dir_id);
                     }
                 }
+
+                root_counter.discovery_complete.store(true, Ordering::Relaxed);
             }
 
             tracing::info!("Discovery complete: {} dirs traversed", dirs_scanned);
         });
 
-        // ========== 扫描线程 ==========
-        let scan_cancelled = cancelled.clone();
-        let scan_scanned = scanned_count.clone();
-        let scan_threats = threats_count.clone();
-        let scan_discovered = discovered_count.clone();
-        let scan_discovery_complete = discovery_complete.clone();
-        let scan_all_threats = all_threats.clone();
-        let scan_engine = engine.clone();
-        let scan_options = *options;
-        let scan_progress = progress_callback.clone();
-        let scan_cancel_flag = cancel_flag.clone();
+        // ========== 工作线程 ==========
+        let mut worker_handles = Vec::with_capacity(workers);
+
+        for worker_id in 0..workers {
+            let worker_cancelled = cancelled.clone();
+            let worker_scanned = scanned_count.clone();
+            let worker_threats = threats_count.clone();
+            let worker_all_threats = all_threats.clone();
+            let worker_errors = error_counts.clone();
+            let worker_roots = root_counters.clone();
+            let worker_events = file_events.clone();
+            let worker_start_time = scan_start_time.clone();
+            let worker_current = worker_files.clone();
+            let worker_rx = file_rx.clone();
+            let worker_engine = engine.clone();
+            let worker_options = *options;
+            let worker_cancel_flag = cancel_flag.clone();
+            let worker_pause = pause.clone();
+            let worker_tracker = tracker.clone();
+            let worker_cached = cached_count.clone();
+            let worker_cache = cache.clone();
+
+            worker_handles.push(tokio::spawn(async move {
+                while !worker_cancelled.load(Ordering::Relaxed) {
+                    // 从共享队列取下一个文件；通道关闭表示发现完成且队列已取空
+                    let (root_index, dir_id, file_path, file_id) = match worker_rx.lock().await.recv().await {
+                        Some(item) => item,
+                        None => break,
+                    };
+                    let root_counter = &worker_roots[root_index];
 
-        // EMA 参数
-        const EMA_ALPHA: f32 = 0.3;  // EMA 平滑系数
+                    // 暂停时持有已取出的文件等待，恢复后继续扫描
+                    worker_pause.wait_if_paused().await;
 
-        let scan_handle = tokio::spawn(async move {
-            let mut ema_rate: f32 = 0.0;  // EMA 扫描速率
-            let mut scan_start_time: Option<Instant> = None;
-            let mut last_progress_update = Instant::now();
-
-            while !scan_cancelled.load(Ordering::Relaxed) {
-                // 尝试接收文件
-                let file_path = match file_rx.try_recv() {
-                    Ok(p) => p,
-                    Err(mpsc::error::TryRecvError::Empty) => {
-                        // 队列为空，检查发现是否完成
-                        if scan_discovery_complete.load(Ordering::Relaxed) {
-                            break;
-                        }
-                        // 短暂等待
-                        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-                        continue;
-                    }
-                    Err(mpsc::error::TryRecvError::Disconnected) => {
+                    // 初始化扫描计时
+                    worker_start_time.get_or_init(Instant::now);
+
+                    // 检查取消标志
+                    if *worker_cancel_flag.lock().await {
+                        worker_cancelled.store(true, Ordering::Relaxed);
                         break;
                     }
-                };
 
-                // 初始化扫描计时
-                if scan_start_time.is_none() {
-                    scan_start_time = Some(Instant::now());
-                }
+                    worker_current.lock().unwrap()[worker_id] = Some(file_path.display().to_string());
+
+                    // 执行扫描（在 spawn_blocking 中执行同步操作）
+                    let engine_clone = worker_engine.clone();
+                    let file_clone = file_path.clone();
+                    let cache_clone = worker_cache.clone();
+
+                    // 每个文件都获取当前引擎：重载期间正在扫描的文件继续使用旧引擎
+                    let scan_result = tokio::task::spawn_blocking(move || {
+                        scan_cached(&engine_clone, cache_clone.as_ref(), &file_clone, file_id, worker_options)
+                    }).await;
+
+                    match scan_result {
+                        Ok(Ok(FileScan::Cached)) => {
+                            // 未修改的干净文件，沿用缓存的结果
+                            worker_scanned.fetch_add(1, Ordering::Relaxed);
+                            worker_cached.fetch_add(1, Ordering::Relaxed);
+                            root_counter.scanned.fetch_add(1, Ordering::Relaxed);
+                            worker_tracker.file_cached(dir_id);
+                        }
+                        Ok(Ok(FileScan::TooLarge(size))) => {
+                            // libclamav 对超过 max_file_size 的文件直接返回干净，需要单独记录为跳过
+                            tracing::debug!("Skipping {} ({} bytes): exceeds max_file_size", file_path.display(), size);
+                            let event = FileEvent::skipped(file_path.display().to_string(), "exceeds max_file_size".to_string());
+                            worker_events.lock().unwrap().push(event.clone());
+                            worker_tracker.file_failed(dir_id, Some(event));
+                        }
+                        Ok(Ok(FileScan::Scanned(result))) => {
+                            worker_scanned.fetch_add(1, Ordering::Relaxed);
+                            root_counter.scanned.fetch_add(1, Ordering::Relaxed);
 
-                // 检查取消标志
-                {
-                    let flag = scan_cancel_flag.lock().await;
-                    if *flag {
-                        scan_cancelled.store(true, Ordering::Relaxed);
-                        break;
+                            if result.is_encrypted() {
+                                worker_errors.lock().await.record(ErrorCategory::Encrypted);
+                                root_counter.errors.lock().unwrap().record(ErrorCategory::Encrypted);
+                            }
+
+                            // 每个匹配的签名记录为一条威胁
+                            let threats = if result.is_infected { result.threats() } else { Vec::new() };
+                            if !threats.is_empty() {
+                                tracing::warn!("THREAT FOUND in {}: {:?}", result.filename, result.virus_names);
+                                worker_threats.fetch_add(result.virus_names.len() as u32, Ordering::Relaxed);
+                                root_counter.threats.fetch_add(result.virus_names.len() as u32, Ordering::Relaxed);
+                                worker_all_threats.lock().await.extend(threats.iter().cloned());
+                            }
+                            worker_tracker.file_scanned(dir_id, &threats, result.is_encrypted());
+                        }
+                        Ok(Err(e)) => {
+                            tracing::warn!("Error scanning {} [{}]: {}", file_path.display(), e.category(), e);
+                            worker_errors.lock().await.record(e.category());
+                            root_counter.errors.lock().unwrap().record(e.category());
+                            let event = FileEvent::failed(
+                                file_path.display().to_string(),
+                                e.category(),
+                                e.code(),
+                                e.to_string(),
+                            );
+                            worker_events.lock().unwrap().push(event.clone());
+                            worker_tracker.file_failed(dir_id, Some(event));
+                        }
+                        Err(e) => {
+                            tracing::trace!("Spawn blocking error: {}", e);
+                            worker_tracker.file_failed(dir_id, None);
+                        }
                     }
+
+                    worker_current.lock().unwrap()[worker_id] = None;
                 }
 
-                let file_str = file_path.display().to_string();
+                tracing::debug!("Scan worker {} complete", worker_id);
+            }));
+        }
 
-                // 执行扫描（在 spawn_blocking 中执行同步操作）
-                let engine_clone = scan_engine.clone();
-                let file_clone = file_path.clone();
-                let options_copy = scan_options;
+        // ========== 进度线程 ==========
+        let progress_complete = workers_complete.clone();
+        let progress_scanned = scanned_count.clone();
+        let progress_threats = threats_count.clone();
+        let progress_discovered = discovered_count.clone();
+        let progress_start_time = scan_start_time.clone();
+        let progress_files = worker_files.clone();
+        let progress_roots = root_counters.clone();
+        let progress_root_paths = roots.to_vec();
+        let progress_cb = progress_callback.clone();
+        let progress_tracker = tracker.clone();
+        let checkpoint_interval = checkpoint.interval();
 
-                let scan_result = tokio::task::spawn_blocking(move || {
-                    engine_clone.scan_file(&file_clone.to_string_lossy(), options_copy)
-                }).await;
+        // EMA 参数
+        const EMA_ALPHA: f32 = 0.3;  // EMA 平滑系数
 
-                match scan_result {
-                    Ok(Ok(result)) => {
-                        let scanned = scan_scanned.fetch_add(1, Ordering::Relaxed) + 1;
+        let progress_handle = tokio::spawn(async move {
+            let mut ema_rate: f32 = 0.0;  // EMA 扫描速率
+            let mut last_checkpoint = Instant::now();
 
-                        if result.is_infected {
-                            tracing::warn!("THREAT FOUND in {}: {:?}", result.filename, result.virus_name);
-                            scan_threats.fetch_add(1, Ordering::Relaxed);
+            // 每 100ms 更新一次进度（避免过于频繁）
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                if progress_complete.load(Ordering::Relaxed) {
+                    break;
+                }
 
-                            let mut threats = scan_all_threats.lock().await;
-                            threats.push((
-                                FilePath(result.filename),
-                                VirusName(result.virus_name.unwrap_or_else(|| "Unknown".to_string()))
-                            ));
-                        }
+                let scanned = progress_scanned.load(Ordering::Relaxed);
+                let discovered = progress_discovered.load(Ordering::Relaxed);
+                let threats = progress_threats.load(Ordering::Relaxed);
 
-                        // 计算 EMA 速率
-                        if let Some(start) = scan_start_time {
-                            let elapsed = start.elapsed().as_secs_f32();
-                            if elapsed > 0.0 {
-                                let instant_rate = scanned as f32 / elapsed;
-                                // EMA 公式: new_ema = alpha * new_value + (1 - alpha) * old_ema
-                                if ema_rate == 0.0 {
-                                    ema_rate = instant_rate;
-                                } else {
-                                    ema_rate = EMA_ALPHA * instant_rate + (1.0 - EMA_ALPHA) * ema_rate;
-                                }
-                            }
-                        }
+                // 定期保存检查点
+                if last_checkpoint.elapsed() >= checkpoint_interval {
+                    checkpoint.save(progress_tracker.checkpoint()).await;
+                    last_checkpoint = Instant::now();
+                }
 
-                        // 每 100ms 更新一次进度（避免过于频繁）
-                        if last_progress_update.elapsed().as_millis() > 100 {
-                            let discovered = scan_discovered.load(Ordering::Relaxed);
-                            let threats = scan_threats.load(Ordering::Relaxed);
-
-                            // 计算进度百分比
-                            let percent = if discovered > 0 {
-                                ((scanned as f32 / discovered as f32) * 100.0).min(100.0) as u8
-                            } else {
-                                0
-                            };
-
-                            Self::update_progress(
-                                &scan_progress,
-                                ScanProgress {
-                                    percent: ProgressPercent(percent),
-                                    scanned_files: ScannedFiles(scanned),
-                                    total_files: TotalFiles(discovered), // 使用已发现数作为"当前已知总数"
-                                    threats_found: ThreatsFound(threats),
-                                    current_file: Some(FilePath(file_str.clone())),
-                                    discovered_files: DiscoveredFiles(discovered),
-                                    scan_rate: if ema_rate > 0.0 { Some(ScanRate(ema_rate)) } else { None },
-                                },
-                            ).await;
-
-                            last_progress_update = Instant::now();
+                // 计算 EMA 速率（只统计本次运行扫描的文件）
+                if let Some(start) = progress_start_time.get() {
+                    let elapsed = start.elapsed().as_secs_f32();
+                    let scanned_now = scanned.saturating_sub(resumed_scanned);
+                    if elapsed > 0.0 && scanned_now > 0 {
+                        let instant_rate = scanned_now as f32 / elapsed;
+                        // EMA 公式: new_ema = alpha * new_value + (1 - alpha) * old_ema
+                        if ema_rate == 0.0 {
+                            ema_rate = instant_rate;
+                        } else {
+                            ema_rate = EMA_ALPHA * instant_rate + (1.0 - EMA_ALPHA) * ema_rate;
                         }
                     }
-                    Ok(Err(e)) => {
-                        tracing::trace!("Error scanning {}: {}", file_path.display(), e);
-                    }
-                    Err(e) => {
-                        tracing::trace!("Spawn blocking error: {}", e);
-                    }
                 }
-            }
 
-            tracing::info!("Scan thread complete");
+                // 计算进度百分比
+                let percent = if discovered > 0 {
+                    ((scanned as f32 / discovered as f32) * 100.0).min(100.0) as u8
+                } else {
+                    0
+                };
+
+                let files: Vec<Option<FilePath>> = progress_files.lock().unwrap()
+                    .iter()
+                    .map(|f| f.clone().map(FilePath))
+                    .collect();
+
+                Self::update_progress(
+                    &progress_cb,
+                    ScanProgress {
+                        percent: ProgressPercent(percent),
+                        scanned_files: ScannedFiles(scanned),
+                        total_files: TotalFiles(discovered), // 使用已发现数作为"当前已知总数"
+                        threats_found: ThreatsFound(threats),
+                        current_file: files.iter().flatten().next().cloned(),
+                        discovered_files: DiscoveredFiles(discovered),
+                        scan_rate: if ema_rate > 0.0 { Some(ScanRate(ema_rate)) } else { None },
+                        worker_files: files,
+                        roots: root_progress(&progress_root_paths, &progress_roots),
+                    },
+                ).await;
+            }
         });
 
         // 等待发现线程完成
         discovery_handle.await?;
-        discovery_complete.store(true, Ordering::Relaxed);
 
-        // 等待扫描线程完成
-        scan_handle.await?;
+        // 等待所有工作线程完成
+        for handle in worker_handles {
+            handle.await?;
+        }
+        workers_complete.store(true, Ordering::Relaxed);
+        progress_handle.await?;
+
+        // 写入缓冲的缓存记录（取消时已扫描文件的结果同样保留）
+        if let Some(cache) = cache {
+            tokio::task::spawn_blocking(move || cache.flush()).await?;
+        }
 
         // 检查是否被取消
         if cancelled.load(Ordering::Relaxed) {
@@ -884,10 +1316,13 @@ impl ScanEngine {
         let final_scanned = scanned_count.load(Ordering::Relaxed);
         let final_discovered = discovered_count.load(Ordering::Relaxed);
         let final_threats = threats_count.load(Ordering::Relaxed);
+        let final_cached = cached_count.load(Ordering::Relaxed);
         let threats = all_threats.lock().await.clone();
+        let errors = *error_counts.lock().await;
+        let events = std::mem::take(&mut *file_events.lock().unwrap());
 
-        tracing::info!("Directory scan complete: {}/{} files scanned, {} threats found",
-                      final_scanned, final_discovered, final_threats);
+        tracing::info!("Directory scan complete: {}/{} files scanned ({} unchanged, skipped via cache), {} threats found, {} errors, {} unscanned paths recorded",
+                      final_scanned, final_discovered, final_cached, final_threats, errors.total(), events.len());
 
         // 最终进度更新
         Self::update_progress(
@@ -900,14 +1335,39 @@ impl ScanEngine {
                 current_file: None,
                 discovered_files: DiscoveredFiles(final_discovered),
                 scan_rate: None,
+                worker_files: vec![None; workers],
+                roots: root_progress(roots, &root_counters),
             },
         ).await;
 
+        let root_outcomes = roots.iter()
+            .zip(root_counters.iter())
+            .zip(root_failures)
+            .map(|((root, counters), failure)| match failure {
+                Some(message) => RootOutcome::failed(root.display().to_string(), message),
+                None => counters.outcome(root),
+            })
+            .collect();
+
         Ok(ScanOutcome::success(
             final_discovered,
             final_scanned,
             threats,
-        ))
+        ).with_errors(errors).with_roots(root_outcomes).with_file_events(events).with_cached_files(final_cached))
+    }
+
+    /// 记录发现阶段未被扫描的路径；带错误分类的事件（无法读取的目录、无法 stat 的条目）同时计入错误数
+    async fn record_discovery_event(
+        events: &std::sync::Mutex<Vec<FileEvent>>,
+        errors: &AsyncMutex<ErrorCounts>,
+        root_counter: &RootCounters,
+        event: FileEvent,
+    ) {
+        if let Some(category) = event.category {
+            errors.lock().await.record(category);
+            root_counter.errors.lock().unwrap().record(category);
+        }
+        events.lock().unwrap().push(event);
     }
 
     /// 更新进度回调
@@ -922,9 +1382,111 @@ impl ScanEngine {
     }
 }
 
+/// 单个文件的扫描结果
+enum FileScan {
+    /// 未修改的干净文件，沿用缓存的结果
+    Cached,
+    Scanned(ScanResult),
+    /// 文件大小（字节）超过 max_file_size，未扫描
+    TooLarge(u64),
+}
+
+/// 扫描单个文件，使用缓存时跳过未修改的干净文件
+///
+/// 文件由 open_discovered 打开，确认仍是发现时的普通文件后扫描该文件描述符；
+/// 缓存使用打开后 fstat 的元数据：扫描期间被修改的文件 ctime 会变化，下次扫描不会命中缓存
+fn scan_cached<S: Scanner>(
+    engine: &EngineHandle<S>,
+    cache: Option<&VerdictCachePolicy>,
+    path: &Path,
+    file_id: FileId,
+    options: ScanOptions,
+) -> Result<FileScan, ClamAVError> {
+    let scanner = engine.current()?;
+    let path_str = path.to_string_lossy();
+    let (file, metadata) = open_discovered(path, file_id)
+        .map_err(|e| ClamAVError::from_io(&path_str, &e))?;
+    let identity = FileIdentity::from_metadata(&metadata);
+
+    // 超过 max_file_size 的文件 libclamav 不读取内容并返回 CL_CLEAN（alert_exceeds_max 默认关闭）；
+    // 在查询缓存之前跳过，这些文件既不写入缓存，也不沿用之前记录的干净结果
+    let max_file_size = scanner.info().limits.max_file_size;
+    if max_file_size > 0 && identity.size > max_file_size {
+        return Ok(FileScan::TooLarge(identity.size));
+    }
+
+    if let Some(cache) = cache {
+        if cache.can_skip(&identity, scanner.info()) {
+            return Ok(FileScan::Cached);
+        }
+    }
+
+    let result = scanner.scan_opened(&file, &path_str, options)?;
+
+    // 加密等未完整扫描的文件不缓存干净结果
+    if let Some(cache) = cache {
+        if result.is_infected {
+            cache.record(identity, Verdict::Infected, scanner.info());
+        } else if !result.is_encrypted() {
+            cache.record(identity, Verdict::Clean, scanner.info());
+        }
+    }
+
+    Ok(FileScan::Scanned(result))
+}
+
+/// 单个扫描根目录的计数器（发现线程与工作线程共享）
+#[derive(Default)]
+struct RootCounters {
+    discovered: AtomicU32,
+    scanned: AtomicU32,
+    threats: AtomicU32,
+    errors: std::sync::Mutex<ErrorCounts>,
+    discovery_complete: AtomicBool,
+}
+
+impl RootCounters {
+    /// 从检查点的计数开始
+    fn resumed(checkpoint: &RootCheckpoint) -> Self {
+        Self {
+            discovered: AtomicU32::new(checkpoint.discovered),
+            scanned: AtomicU32::new(checkpoint.scanned),
+            threats: AtomicU32::new(checkpoint.threats),
+            errors: std::sync::Mutex::new(checkpoint.errors),
+            discovery_complete: AtomicBool::new(false),
+        }
+    }
+
+    fn outcome(&self, root: &Path) -> RootOutcome {
+        RootOutcome {
+            path: root.display().to_string(),
+            total_files: self.discovered.load(Ordering::Relaxed),
+            scanned_files: self.scanned.load(Ordering::Relaxed),
+            threats_found: self.threats.load(Ordering::Relaxed),
+            errors: *self.errors.lock().unwrap(),
+            error_message: None,
+        }
+    }
+}
+
+/// 汇总各根目录的进度
+fn root_progress(roots: &[PathBuf], counters: &[RootCounters]) -> Vec<RootProgress> {
+    roots.iter()
+        .zip(counters)
+        .map(|(root, c)| RootProgress {
+            path: FilePath(root.display().to_string()),
+            discovered_files: DiscoveredFiles(c.discovered.load(Ordering::Relaxed)),
+            scanned_files: ScannedFiles(c.scanned.load(Ordering::Relaxed)),
+            threats_found: ThreatsFound(c.threats.load(Ordering::Relaxed)),
+            discovery_complete: c.discovery_complete.load(Ordering::Relaxed),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clamav::mock::{MockRule, MockScanner};
 
     #[test]
     fn test_task_queue_priority() {
@@ -960,4 +1522,558 @@ mod tests {
         let target = ScanTarget::from_path("/tmp");
         assert!(matches!(target, ScanTarget::Directory(_)));
     }
+
+    /// 使用模拟引擎执行一次扫描并等待完成回调
+    async fn run_mock_scan(scanner: MockScanner, target: ScanTarget) -> ScanOutcome {
+        run_mock_scan_with(scanner, target, DEFAULT_SCAN_WORKERS, DiscoveryOptions::default()).await
+    }
+
+    async fn run_mock_scan_with(
+        scanner: MockScanner,
+        target: ScanTarget,
+        workers: usize,
+        discovery: DiscoveryOptions,
+    ) -> ScanOutcome {
+        let handle = EngineHandle::new();
+        handle.swap(Arc::new(scanner));
+        let engine = ScanEngine::new(handle).with_workers(workers);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        engine.set_completion_callback(Arc::new(move |_task_id: &str, result: &Result<ScanOutcome>| {
+            let _ = tx.send(result.as_ref().map(|o| o.clone()).map_err(|e| e.to_string()));
+        })).await;
+
+        engine.submit_task(target, TaskPriority::Normal, ScanOptions::default(), discovery).await.unwrap();
+
+        tokio::time::timeout(std::time::Duration::from_secs(10), rx.recv())
+            .await
+            .expect("scan did not complete")
+            .unwrap()
+            .unwrap()
+    }
+
+    fn temp_scan_dir(files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scan-engine-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_directory_scan_with_mock_scanner() {
+        let dir = temp_scan_dir(&[
+            ("clean1.txt", b"hello"),
+            ("clean2.txt", b"world"),
+            ("payload.bin", b"xxEICARxx"),
+        ]);
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::content(b"EICAR").infected("Eicar-Test-Signature"));
+
+        let outcome = run_mock_scan(scanner.clone(), ScanTarget::Directory(dir.clone())).await;
+
+        assert_eq!(outcome.status, ScanStatus::Completed);
+        assert_eq!(outcome.scanned_files, 3);
+        assert_eq!(outcome.threats.len(), 1);
+        assert_eq!(outcome.threats[0].virus_name.0, "Eicar-Test-Signature");
+        assert!(outcome.threats[0].file_path.0.ends_with("payload.bin"));
+        assert_eq!(scanner.scan_count(), 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_single_file_scan_with_mock_scanner() {
+        let dir = temp_scan_dir(&[("tool.exe", b"MZ")]);
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::path_suffix(".exe").infected("Win.Test.Tool").in_container("setup.cab/tool.exe"));
+
+        let outcome = run_mock_scan(scanner, ScanTarget::File(dir.join("tool.exe"))).await;
+
+        assert_eq!(outcome.scanned_files, 1);
+        assert_eq!(outcome.threats.len(), 1);
+        assert_eq!(outcome.threats[0].container_path.as_deref(), Some("setup.cab/tool.exe"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_scan_errors_are_counted_by_category() {
+        use crate::clamav::ffi::{CL_EACCES, CL_EMAXSIZE};
+
+        let dir = temp_scan_dir(&[
+            ("clean.txt", b"hello"),
+            ("huge.iso", b"data"),
+            ("locked.db", b"data"),
+        ]);
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::path_suffix(".iso").error(ClamAVError::scan_error(CL_EMAXSIZE, "too big".to_string())))
+            .with_rule(MockRule::path_suffix(".db").error(ClamAVError::scan_error(CL_EACCES, "denied".to_string())));
+
+        let outcome = run_mock_scan(scanner, ScanTarget::Directory(dir.clone())).await;
+
+        assert_eq!(outcome.status, ScanStatus::Completed);
+        assert_eq!(outcome.errors.limits, 1);
+        assert_eq!(outcome.errors.permission, 1);
+        assert_eq!(outcome.errors.total(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_directory_scan_uses_worker_pool() {
+        let files: Vec<(String, &[u8])> = (0..8).map(|i| (format!("file{}.txt", i), &b"data"[..])).collect();
+        let file_refs: Vec<(&str, &[u8])> = files.iter().map(|(n, c)| (n.as_str(), *c)).collect();
+        let dir = temp_scan_dir(&file_refs);
+        let scanner = MockScanner::new()
+            .with_delay(std::time::Duration::from_millis(200))
+            .with_rule(MockRule::path_suffix("file3.txt").infected("Test.Sig"));
+
+        // 每个文件扫描 200ms，多个工作线程同时扫描
+        let outcome = run_mock_scan_with(scanner.clone(), ScanTarget::Directory(dir.clone()), 4, DiscoveryOptions::default()).await;
+
+        assert_eq!(outcome.status, ScanStatus::Completed);
+        assert_eq!(outcome.scanned_files, 8);
+        assert_eq!(outcome.total_files, 8);
+        assert_eq!(outcome.threats.len(), 1);
+        assert_eq!(scanner.scan_count(), 8);
+        assert!(scanner.max_concurrent() >= 2, "max concurrent scans: {}", scanner.max_concurrent());
+        assert!(scanner.max_concurrent() <= 4, "max concurrent scans: {}", scanner.max_concurrent());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_scan_engine_workers_at_least_one() {
+        let engine = ScanEngine::new(EngineHandle::<MockScanner>::new()).with_workers(0);
+        assert_eq!(engine.workers(), 1);
+        engine.set_workers(8);
+        assert_eq!(engine.workers(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_continue_same_scan() {
+        let files: Vec<(String, &[u8])> = (0..8).map(|i| (format!("file{}.txt", i), b"data" as &[u8])).collect();
+        let names: Vec<(&str, &[u8])> = files.iter().map(|(n, c)| (n.as_str(), *c)).collect();
+        let dir = temp_scan_dir(&names);
+
+        let scanner = MockScanner::new().with_delay(std::time::Duration::from_millis(50));
+        let handle = EngineHandle::new();
+        handle.swap(Arc::new(scanner.clone()));
+        let engine = ScanEngine::new(handle).with_workers(1);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        engine.set_completion_callback(Arc::new(move |_task_id: &str, result: &Result<ScanOutcome>| {
+            let _ = tx.send(result.as_ref().map(|o| o.clone()).map_err(|e| e.to_string()));
+        })).await;
+
+        let task_id = engine.submit_task(
+            ScanTarget::Directory(dir.clone()),
+            TaskPriority::Normal,
+            ScanOptions::default(),
+            DiscoveryOptions::default(),
+        ).await.unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(120)).await;
+        assert!(engine.pause_task(&task_id).await.unwrap());
+        assert_eq!(engine.get_task(&task_id).await.unwrap().state, TaskState::Paused);
+
+        // 暂停前已开始的文件扫描完成后不再有新的扫描
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let paused_count = scanner.scan_count();
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        assert_eq!(scanner.scan_count(), paused_count);
+        assert!(paused_count < 8);
+        assert!(rx.try_recv().is_err());
+
+        assert!(engine.resume_task(&task_id).await.unwrap());
+        assert_eq!(engine.get_task(&task_id).await.unwrap().state, TaskState::Running);
+
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(10), rx.recv())
+            .await
+            .expect("scan did not complete")
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.status, ScanStatus::Completed);
+        assert_eq!(outcome.scanned_files, 8);
+        assert_eq!(scanner.scan_count(), 8);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_multi_root_scan_reports_each_root() {
+        let vol1 = temp_scan_dir(&[("a.txt", b"hello"), ("b.txt", b"world")]);
+        let vol2 = temp_scan_dir(&[("payload.bin", b"xxEICARxx")]);
+        let missing = std::env::temp_dir().join(format!("scan-engine-missing-{}", uuid::Uuid::new_v4()));
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::content(b"EICAR").infected("Eicar-Test-Signature"));
+
+        let target = ScanTarget::from_paths(&[vol1.clone(), vol2.clone(), missing.clone()]).unwrap();
+        assert!(matches!(target, ScanTarget::Roots(_)));
+        let outcome = run_mock_scan(scanner, target).await;
+
+        assert_eq!(outcome.status, ScanStatus::Completed);
+        assert_eq!(outcome.scanned_files, 3);
+        assert_eq!(outcome.threats.len(), 1);
+        assert_eq!(outcome.roots.len(), 3);
+
+        assert_eq!(outcome.roots[0].path, vol1.display().to_string());
+        assert_eq!(outcome.roots[0].scanned_files, 2);
+        assert_eq!(outcome.roots[0].threats_found, 0);
+        assert_eq!(outcome.roots[1].scanned_files, 1);
+        assert_eq!(outcome.roots[1].threats_found, 1);
+        assert_eq!(outcome.roots[2].total_files, 0);
+        assert!(outcome.roots[2].error_message.as_deref().unwrap().contains("does not exist"));
+
+        let _ = std::fs::remove_dir_all(&vol1);
+        let _ = std::fs::remove_dir_all(&vol2);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_subtrees_are_not_walked() {
+        use crate::clamav::exclusion::{ExclusionRules, ExclusionSource};
+
+        let dir = temp_scan_dir(&[("keep.txt", b"hello"), ("image.iso", b"xxEICARxx")]);
+        std::fs::create_dir_all(dir.join("app/node_modules/pkg")).unwrap();
+        std::fs::write(dir.join("app/node_modules/pkg/index.js"), b"xxEICARxx").unwrap();
+        std::fs::write(dir.join("app/main.js"), b"ok").unwrap();
+
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::content(b"EICAR").infected("Eicar-Test-Signature"));
+        let exclusions = ExclusionRules::new()
+            .with_patterns(["**/node_modules"], ExclusionSource::Config).unwrap()
+            .with_extensions(["iso"], ExclusionSource::Scan);
+
+        let outcome = run_mock_scan_with(scanner.clone(), ScanTarget::Directory(dir.clone()), 2, DiscoveryOptions::new().with_exclusions(exclusions)).await;
+
+        assert_eq!(outcome.status, ScanStatus::Completed);
+        assert_eq!(outcome.total_files, 2);
+        assert_eq!(outcome.scanned_files, 2);
+        assert!(outcome.threats.is_empty());
+        assert_eq!(scanner.scan_count(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_unscanned_files_are_accounted() {
+        use crate::clamav::exclusion::{ExclusionRules, ExclusionSource};
+        use crate::clamav::ffi::{CL_EACCES, CL_EMAXSIZE, CL_ETIMEOUT};
+
+        let dir = temp_scan_dir(&[
+            ("clean.txt", b"hello"),
+            ("huge.iso", b"data"),
+            ("locked.db", b"data"),
+            ("slow.pdf", b"data"),
+            ("cache.tmp", b"data"),
+        ]);
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::path_suffix(".iso").error(ClamAVError::scan_error(CL_EMAXSIZE, "too big".to_string())))
+            .with_rule(MockRule::path_suffix(".db").error(ClamAVError::scan_error(CL_EACCES, "denied".to_string())))
+            .with_rule(MockRule::path_suffix(".pdf").error(ClamAVError::scan_error(CL_ETIMEOUT, "timeout".to_string())));
+        let exclusions = ExclusionRules::new().with_extensions(["tmp"], ExclusionSource::Scan);
+
+        let outcome = run_mock_scan_with(scanner, ScanTarget::Directory(dir.clone()), 2, DiscoveryOptions::new().with_exclusions(exclusions)).await;
+
+        assert_eq!(outcome.scanned_files, 1);
+        assert_eq!(outcome.skipped_files, 1);
+        assert_eq!(outcome.errored_files, 1);
+        assert_eq!(outcome.timed_out_files, 1);
+        assert_eq!(outcome.excluded_files, 1);
+        assert_eq!(outcome.file_events.len(), 4);
+
+        let locked = outcome.file_events.iter().find(|e| e.path.ends_with("locked.db")).unwrap();
+        assert_eq!(locked.reason, FileEventReason::Error);
+        assert_eq!(locked.category, Some(ErrorCategory::Permission));
+        assert_eq!(locked.error_code, Some(CL_EACCES));
+
+        let excluded = outcome.file_events.iter().find(|e| e.path.ends_with("cache.tmp")).unwrap();
+        assert_eq!(excluded.reason, FileEventReason::Excluded);
+        assert_eq!(excluded.detail.as_deref(), Some("tmp"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_files_over_max_file_size_are_skipped() {
+        use crate::clamav::ffi::{EngineInfo, EngineLimits};
+
+        let dir = temp_scan_dir(&[("small.txt", b"data"), ("large.bin", b"0123456789abcdef")]);
+        let info = EngineInfo {
+            limits: EngineLimits { max_file_size: 8, ..Default::default() },
+            ..Default::default()
+        };
+        let scanner = MockScanner::new().with_info(info);
+
+        let outcome = run_mock_scan(scanner.clone(), ScanTarget::Directory(dir.clone())).await;
+
+        assert_eq!(outcome.total_files, 2);
+        assert_eq!(outcome.scanned_files, 1);
+        assert_eq!(outcome.skipped_files, 1);
+        assert_eq!(outcome.errors.total(), 0);
+        assert_eq!(scanner.scan_count(), 1);
+        let event = outcome.file_events.iter().find(|e| e.path.ends_with("large.bin")).unwrap();
+        assert_eq!(event.reason, FileEventReason::Skipped);
+        assert_eq!(event.detail.as_deref(), Some("exceeds max_file_size"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_directories_are_counted() {
+        let dir = temp_scan_dir(&[("clean.txt", b"hello")]);
+        let locked = dir.join("locked");
+        std::fs::create_dir_all(&locked).unwrap();
+        std::fs::write(locked.join("hidden.txt"), b"data").unwrap();
+
+        // root 不受目录权限限制，由遍历选项模拟无法读取的目录
+        let discovery = DiscoveryOptions { unreadable_dirs: vec![locked.clone()], ..Default::default() };
+        let outcome = run_mock_scan_with(MockScanner::new(), ScanTarget::Directory(dir.clone()), 1, discovery).await;
+
+        assert_eq!(outcome.scanned_files, 1);
+        assert_eq!(outcome.errors.permission, 1);
+        assert_eq!(outcome.roots[0].errors.permission, 1);
+        let event = outcome.file_events.iter().find(|e| e.path == locked.display().to_string()).unwrap();
+        assert_eq!(event.category, Some(ErrorCategory::Permission));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_record_completed_directories() {
+        let dir = temp_scan_dir(&[]);
+        for sub in ["a", "b"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+            for i in 0..3 {
+                std::fs::write(dir.join(sub).join(format!("file{}.txt", i)), b"data").unwrap();
+            }
+        }
+
+        let handle = EngineHandle::new();
+        handle.swap(Arc::new(MockScanner::new().with_delay(std::time::Duration::from_millis(30))));
+        let engine = ScanEngine::new(handle)
+            .with_workers(1)
+            .with_checkpoint_interval(std::time::Duration::from_millis(50));
+
+        let checkpoints = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let saved = checkpoints.clone();
+        engine.set_checkpoint_callback(Arc::new(move |task_id: &str, cp: ScanCheckpoint| {
+            saved.lock().unwrap().push((task_id.to_string(), cp));
+        })).await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        engine.set_completion_callback(Arc::new(move |_task_id: &str, result: &Result<ScanOutcome>| {
+            let _ = tx.send(result.as_ref().map(|o| o.clone()).map_err(|e| e.to_string()));
+        })).await;
+
+        let task_id = engine.submit_task(ScanTarget::Directory(dir.clone()), TaskPriority::Normal, ScanOptions::default(), DiscoveryOptions::default())
+            .await.unwrap();
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(10), rx.recv())
+            .await.expect("scan did not complete").unwrap().unwrap();
+        assert_eq!(outcome.scanned_files, 6);
+
+        let checkpoints = checkpoints.lock().unwrap();
+        assert!(!checkpoints.is_empty());
+        assert!(checkpoints.iter().all(|(id, _)| *id == task_id));
+        let checkpoints: Vec<ScanCheckpoint> = checkpoints.iter().map(|(_, cp)| cp.clone()).collect();
+
+        // 每个目录只提交一次，计数只包含已完成的目录
+        let mut completed: Vec<PathBuf> = checkpoints.iter().flat_map(|c| c.completed_dirs.clone()).collect();
+        let total = completed.len();
+        completed.sort();
+        completed.dedup();
+        assert_eq!(completed.len(), total);
+        for cp in checkpoints.iter() {
+            assert_eq!(cp.counters.scanned_files() % 3, 0);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_scan_skips_completed_directories() {
+        let dir = temp_scan_dir(&[]);
+        for sub in ["done", "todo"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+            std::fs::write(dir.join(sub).join("clean.txt"), b"data").unwrap();
+            std::fs::write(dir.join(sub).join("payload.bin"), b"xxEICARxx").unwrap();
+        }
+
+        // 检查点：done 目录已完成，其中发现 1 个威胁
+        let mut resume = ResumeState::default();
+        resume.completed_dirs.insert(dir.join("done"));
+        resume.state.counters.roots.push(RootCheckpoint { discovered: 2, scanned: 2, threats: 1, errors: ErrorCounts::default(), cached: 0 });
+        resume.state.threats.push(crate::clamav::checkpoint::CheckpointThreat {
+            file_path: dir.join("done/payload.bin").display().to_string(),
+            virus_name: "Eicar-Test-Signature".to_string(),
+            file_hash: None,
+            file_type: None,
+            container_path: None,
+        });
+
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::content(b"EICAR").infected("Eicar-Test-Signature"));
+        let handle = EngineHandle::new();
+        handle.swap(Arc::new(scanner.clone()));
+        let engine = ScanEngine::new(handle);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        engine.set_completion_callback(Arc::new(move |_task_id: &str, result: &Result<ScanOutcome>| {
+            let _ = tx.send(result.as_ref().map(|o| o.clone()).map_err(|e| e.to_string()));
+        })).await;
+
+        engine.submit_resumed_task(
+            ScanTarget::Directory(dir.clone()),
+            TaskPriority::Normal,
+            ScanOptions::default(),
+            DiscoveryOptions::default(),
+            resume,
+        ).await.unwrap();
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(10), rx.recv())
+            .await.expect("scan did not complete").unwrap().unwrap();
+
+        // 只扫描 todo 目录，结果包含检查点之前的计数和威胁
+        assert_eq!(scanner.scan_count(), 2);
+        assert_eq!(outcome.scanned_files, 4);
+        assert_eq!(outcome.total_files, 4);
+        assert_eq!(outcome.threats.len(), 2);
+        assert_eq!(outcome.roots[0].threats_found, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_files_over_max_file_size_bypass_verdict_cache() {
+        use crate::clamav::ffi::{EngineInfo, EngineLimits};
+        use crate::clamav::verdict_cache::{CachedVerdict, MemoryVerdictCache, Verdict, VerdictCache};
+
+        let dir = temp_scan_dir(&[
+            ("small.txt", b"data"),
+            ("large.bin", b"0123456789abcdef"),
+            ("stale.bin", b"0123456789abcdef"),
+        ]);
+        let info = EngineInfo {
+            db_version: 100,
+            fingerprint: "a".to_string(),
+            limits: EngineLimits { max_file_size: 8, ..Default::default() },
+            ..Default::default()
+        };
+
+        // 之前记录的干净结果（文件实际未被读取）
+        let store = Arc::new(MemoryVerdictCache::new());
+        store.put(CachedVerdict {
+            identity: FileIdentity::of(&dir.join("stale.bin")).unwrap(),
+            verdict: Verdict::Clean,
+            db_version: 100,
+            engine_fingerprint: "a".to_string(),
+        });
+
+        let scanner = MockScanner::new().with_info(info);
+        let handle = EngineHandle::new();
+        handle.swap(Arc::new(scanner.clone()));
+        let engine = ScanEngine::new(handle);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        engine.set_completion_callback(Arc::new(move |_task_id: &str, result: &Result<ScanOutcome>| {
+            let _ = tx.send(result.as_ref().map(|o| o.clone()).map_err(|e| e.to_string()));
+        })).await;
+
+        let task = ScanTask::new(ScanTarget::Directory(dir.clone()), TaskPriority::Normal, ScanOptions::default())
+            .with_cache(VerdictCachePolicy::new(store.clone()));
+        engine.submit_scan_task(task).await.unwrap();
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(10), rx.recv())
+            .await.expect("scan did not complete").unwrap().unwrap();
+
+        assert_eq!(scanner.scan_count(), 1);
+        assert_eq!(outcome.cached_files, 0);
+        assert_eq!(outcome.skipped_files, 2);
+
+        // 只有实际扫描的文件写入缓存
+        assert_eq!(store.len(), 2);
+        let large = FileIdentity::of(&dir.join("large.bin")).unwrap();
+        assert!(store.get(large.dev, large.ino).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_verdict_cache_skips_unchanged_clean_files() {
+        use crate::clamav::ffi::EngineInfo;
+        use crate::clamav::verdict_cache::MemoryVerdictCache;
+
+        let dir = temp_scan_dir(&[
+            ("clean1.txt", b"hello"),
+            ("clean2.txt", b"world"),
+            ("payload.bin", b"xxEICARxx"),
+        ]);
+        let store = Arc::new(MemoryVerdictCache::new());
+
+        // 返回（扫描结果, 实际扫描的文件数）
+        let run = |db_version: u32, fingerprint: &'static str, force_rescan: bool| {
+            let dir = dir.clone();
+            let cache = VerdictCachePolicy::new(store.clone())
+                .with_version_threshold(5)
+                .with_force_rescan(force_rescan);
+            async move {
+                let scanner = MockScanner::new()
+                    .with_rule(MockRule::content(b"EICAR").infected("Eicar-Test-Signature"))
+                    .with_info(EngineInfo { db_version, fingerprint: fingerprint.to_string(), ..Default::default() });
+                let handle = EngineHandle::new();
+                handle.swap(Arc::new(scanner.clone()));
+                let engine = ScanEngine::new(handle);
+
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                engine.set_completion_callback(Arc::new(move |_task_id: &str, result: &Result<ScanOutcome>| {
+                    let _ = tx.send(result.as_ref().map(|o| o.clone()).map_err(|e| e.to_string()));
+                })).await;
+
+                let task = ScanTask::new(ScanTarget::Directory(dir), TaskPriority::Normal, ScanOptions::default())
+                    .with_cache(cache);
+                engine.submit_scan_task(task).await.unwrap();
+                let outcome = tokio::time::timeout(std::time::Duration::from_secs(10), rx.recv())
+                    .await.expect("scan did not complete").unwrap().unwrap();
+                (outcome, scanner.scan_count())
+            }
+        };
+
+        let (outcome, scanned) = run(100, "a", false).await;
+        assert_eq!(scanned, 3);
+        assert_eq!(outcome.cached_files, 0);
+        assert_eq!(store.len(), 3);
+
+        // 未修改的干净文件跳过，感染文件每次都扫描
+        let (outcome, scanned) = run(102, "a", false).await;
+        assert_eq!(scanned, 1);
+        assert_eq!(outcome.cached_files, 2);
+        assert_eq!(outcome.scanned_files, 3);
+        assert_eq!(outcome.threats.len(), 1);
+
+        // 修改过的文件重新扫描
+        std::fs::write(dir.join("clean1.txt"), b"hello again").unwrap();
+        let (outcome, scanned) = run(102, "a", false).await;
+        assert_eq!(scanned, 2);
+        assert_eq!(outcome.cached_files, 1);
+
+        // 强制完整扫描
+        let (outcome, scanned) = run(102, "a", true).await;
+        assert_eq!(scanned, 3);
+        assert_eq!(outcome.cached_files, 0);
+
+        // 病毒库版本超出阈值后重新扫描
+        let (outcome, scanned) = run(108, "a", false).await;
+        assert_eq!(scanned, 3);
+        assert_eq!(outcome.cached_files, 0);
+
+        // 病毒库版本不变，但引擎指纹变化（例如上传了自定义签名）
+        let (outcome, scanned) = run(108, "b", false).await;
+        assert_eq!(scanned, 3);
+        assert_eq!(outcome.cached_files, 0);
+        let (outcome, scanned) = run(108, "b", false).await;
+        assert_eq!(scanned, 1);
+        assert_eq!(outcome.cached_files, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,304 @@
+// 扫描排除规则
+//
+// 目录遍历时按规则跳过路径，被排除的目录整棵子树不再遍历：
+// - 路径前缀：以 / 开头的普通路径，按路径组件匹配（/proc 不会排除 /procfs）
+// - glob：包含 * ? [ { 的模式；不含 / 时匹配文件名（*.iso），否则匹配完整路径（**/node_modules）
+// - 名称：不含 / 的普通名称（node_modules、@eaDir）匹配任意层级的文件名，等同于 **/name
+// - 正则：以 re: 开头，匹配完整路径
+// - 扩展名：单独的扩展名列表，只作用于文件，不区分大小写
+//
+// 规则来源分为全局配置和单次扫描，explain 返回第一条命中的规则及其来源
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use serde::Serialize;
+
+/// 正则规则前缀
+pub const REGEX_PREFIX: &str = "re:";
+
+/// 显式 glob 规则前缀（模式本身不含通配符时使用）
+pub const GLOB_PREFIX: &str = "glob:";
+
+/// 排除规则类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionKind {
+    Prefix,
+    Glob,
+    Regex,
+    Extension,
+}
+
+/// 排除规则来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionSource {
+    /// 全局配置（ScanConfig.exclude_paths / exclude_extensions）
+    Config,
+    /// 本次扫描请求附带的排除规则
+    Scan,
+}
+
+/// 命中的排除规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExclusionMatch {
+    /// 用户填写的原始规则
+    pub rule: String,
+    pub kind: ExclusionKind,
+    pub source: ExclusionSource,
+}
+
+/// 规则无法解析
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExclusionError {
+    pub rule: String,
+    pub message: String,
+}
+
+impl fmt::Display for ExclusionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid exclusion rule '{}': {}", self.rule, self.message)
+    }
+}
+
+impl std::error::Error for ExclusionError {}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Prefix(PathBuf),
+    /// (匹配器, 是否只匹配文件名)
+    Glob(GlobMatcher, bool),
+    Regex(Regex),
+    Extension(String),
+}
+
+#[derive(Debug, Clone)]
+struct ExclusionRule {
+    rule: String,
+    source: ExclusionSource,
+    matcher: Matcher,
+}
+
+impl ExclusionRule {
+    fn parse(rule: &str, source: ExclusionSource) -> Result<Self, ExclusionError> {
+        let trimmed = rule.trim();
+        let error = |message: String| ExclusionError { rule: trimmed.to_string(), message };
+
+        let matcher = if let Some(pattern) = trimmed.strip_prefix(REGEX_PREFIX) {
+            Matcher::Regex(Regex::new(pattern).map_err(|e| error(e.to_string()))?)
+        } else if let Some(pattern) = trimmed.strip_prefix(GLOB_PREFIX) {
+            Self::glob(pattern).map_err(error)?
+        } else if trimmed.contains(['*', '?', '[', '{']) {
+            Self::glob(trimmed).map_err(error)?
+        } else if trimmed.starts_with('/') {
+            Matcher::Prefix(PathBuf::from(trimmed))
+        } else if !trimmed.contains('/') {
+            // 不含通配符的 glob 只匹配同名的文件或目录
+            Self::glob(trimmed).map_err(error)?
+        } else {
+            return Err(error("expected an absolute path, a name, a glob pattern or re:<regex>".to_string()));
+        };
+
+        Ok(Self { rule: trimmed.to_string(), source, matcher })
+    }
+
+    fn glob(pattern: &str) -> Result<Matcher, String> {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Matcher::Glob(glob.compile_matcher(), !pattern.contains('/')))
+    }
+
+    fn kind(&self) -> ExclusionKind {
+        match self.matcher {
+            Matcher::Prefix(_) => ExclusionKind::Prefix,
+            Matcher::Glob(..) => ExclusionKind::Glob,
+            Matcher::Regex(_) => ExclusionKind::Regex,
+            Matcher::Extension(_) => ExclusionKind::Extension,
+        }
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        match &self.matcher {
+            Matcher::Prefix(prefix) => path.starts_with(prefix),
+            Matcher::Glob(glob, true) => path.file_name().is_some_and(|name| glob.is_match(name)),
+            Matcher::Glob(glob, false) => glob.is_match(path),
+            Matcher::Regex(regex) => regex.is_match(&path.to_string_lossy()),
+            Matcher::Extension(ext) => !is_dir && path.extension()
+                .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case(ext)),
+        }
+    }
+}
+
+/// 扫描排除规则集
+#[derive(Debug, Clone, Default)]
+pub struct ExclusionRules {
+    rules: Vec<ExclusionRule>,
+}
+
+impl ExclusionRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加路径规则（前缀 / glob / 正则），空行忽略
+    pub fn with_patterns<I, P>(mut self, patterns: I, source: ExclusionSource) -> Result<Self, ExclusionError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            if !pattern.trim().is_empty() {
+                self.rules.push(ExclusionRule::parse(pattern, source)?);
+            }
+        }
+        Ok(self)
+    }
+
+    /// 添加路径规则，无法解析的规则记录警告后忽略
+    ///
+    /// 用于已保存的配置：旧版本不校验排除规则，其中的无效规则不应导致扫描无法启动
+    pub fn with_valid_patterns<I, P>(mut self, patterns: I, source: ExclusionSource) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            if pattern.trim().is_empty() {
+                continue;
+            }
+            match ExclusionRule::parse(pattern, source) {
+                Ok(rule) => self.rules.push(rule),
+                Err(e) => tracing::warn!("Ignoring exclusion rule: {}", e),
+            }
+        }
+        self
+    }
+
+    /// 添加扩展名规则（"iso"、".iso" 均可）
+    pub fn with_extensions<I, P>(mut self, extensions: I, source: ExclusionSource) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        for ext in extensions {
+            let ext = ext.as_ref().trim().trim_start_matches('.');
+            if !ext.is_empty() {
+                self.rules.push(ExclusionRule {
+                    rule: ext.to_string(),
+                    source,
+                    matcher: Matcher::Extension(ext.to_string()),
+                });
+            }
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// 路径是否被排除
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        self.explain(path, is_dir).is_some()
+    }
+
+    /// 返回排除该路径的第一条规则
+    pub fn explain(&self, path: &Path, is_dir: bool) -> Option<ExclusionMatch> {
+        self.rules.iter()
+            .find(|r| r.matches(path, is_dir))
+            .map(|r| ExclusionMatch {
+                rule: r.rule.clone(),
+                kind: r.kind(),
+                source: r.source,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(patterns: &[&str]) -> ExclusionRules {
+        ExclusionRules::new().with_patterns(patterns, ExclusionSource::Config).unwrap()
+    }
+
+    #[test]
+    fn test_prefix_matches_path_components() {
+        let rules = rules(&["/proc", "/vol1/tmp/"]);
+        assert!(rules.is_excluded(Path::new("/proc"), true));
+        assert!(rules.is_excluded(Path::new("/proc/1/maps"), false));
+        assert!(!rules.is_excluded(Path::new("/procfs/data"), false));
+        assert!(rules.is_excluded(Path::new("/vol1/tmp/a.txt"), false));
+    }
+
+    #[test]
+    fn test_glob_patterns() {
+        let rules = rules(&["**/node_modules", "*.iso"]);
+        assert!(rules.is_excluded(Path::new("/vol1/app/web/node_modules"), true));
+        assert!(!rules.is_excluded(Path::new("/vol1/app/node_modules_backup"), true));
+        assert!(rules.is_excluded(Path::new("/vol1/images/ubuntu.iso"), false));
+        assert!(!rules.is_excluded(Path::new("/vol1/images/ubuntu.iso.txt"), false));
+
+        let m = rules.explain(Path::new("/vol2/node_modules"), true).unwrap();
+        assert_eq!(m.rule, "**/node_modules");
+        assert_eq!(m.kind, ExclusionKind::Glob);
+    }
+
+    #[test]
+    fn test_bare_names_match_any_level() {
+        let rules = rules(&["node_modules", "@eaDir", "Thumbs.db"]);
+        assert!(rules.is_excluded(Path::new("/vol1/app/web/node_modules"), true));
+        assert!(rules.is_excluded(Path::new("/vol1/photos/@eaDir"), true));
+        assert!(rules.is_excluded(Path::new("/vol1/photos/2023/Thumbs.db"), false));
+        assert!(!rules.is_excluded(Path::new("/vol1/app/node_modules_backup"), true));
+        assert!(!rules.is_excluded(Path::new("/vol1/photos/thumbs.db.bak"), false));
+
+        let m = rules.explain(Path::new("/vol2/node_modules"), true).unwrap();
+        assert_eq!(m.rule, "node_modules");
+        assert_eq!(m.kind, ExclusionKind::Glob);
+    }
+
+    #[test]
+    fn test_regex_and_extension_rules() {
+        let rules = ExclusionRules::new()
+            .with_patterns([r"re:/\.snapshot(/|$)"], ExclusionSource::Config).unwrap()
+            .with_extensions(["VMDK", ".qcow2"], ExclusionSource::Scan);
+
+        assert!(rules.is_excluded(Path::new("/vol1/.snapshot/daily/a"), false));
+        assert!(!rules.is_excluded(Path::new("/vol1/snapshot/a"), false));
+
+        let m = rules.explain(Path::new("/vol1/vm/disk.vmdk"), false).unwrap();
+        assert_eq!(m.kind, ExclusionKind::Extension);
+        assert_eq!(m.source, ExclusionSource::Scan);
+        // 扩展名规则不作用于目录
+        assert!(!rules.is_excluded(Path::new("/vol1/backup.qcow2"), true));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let err = ExclusionRules::new()
+            .with_patterns(["re:(unclosed"], ExclusionSource::Scan)
+            .unwrap_err();
+        assert_eq!(err.rule, "re:(unclosed");
+
+        assert!(ExclusionRules::new().with_patterns(["relative/path"], ExclusionSource::Config).is_err());
+        assert!(ExclusionRules::new().with_patterns(["", "  "], ExclusionSource::Config).unwrap().is_empty());
+
+        // 已保存配置中的无效规则被忽略，其余规则照常生效
+        let rules = ExclusionRules::new()
+            .with_valid_patterns(["relative/path", "re:(unclosed", "/proc"], ExclusionSource::Config);
+        assert_eq!(rules.len(), 1);
+        assert!(rules.is_excluded(Path::new("/proc/1"), false));
+    }
+}
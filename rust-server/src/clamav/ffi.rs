@@ -2,8 +2,16 @@
 // 适配 ClamAV 1.5.1 API
 
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_uint, c_int, c_void};
+use std::os::raw::{c_char, c_uint, c_int, c_longlong, c_void};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
 use std::ptr;
+use serde::Serialize;
+
+use super::database::{DatabaseVerification, RejectedDatabase};
+use super::library::*;
+use super::traversal::{io_error_category, open_regular};
+use super::types::{DetectedThreat, ErrorCategory, FilePath, VirusName};
 
 // ============ ClamAV C API 类型绑定 ============
 
@@ -19,6 +27,61 @@ pub struct cl_engine {
     _private: [u8; 0],
 }
 
+/// 病毒发现回调类型
+pub type clcb_virus_found = extern "C" fn(fd: c_int, virname: *const c_char, context: *mut c_void);
+
+/// 文件检查回调类型：每一层（顶层文件及容器内的每个成员）扫描前调用
+///
+/// ancestors 为长度 recursion_level 的祖先文件名数组，元素可能为 NULL
+pub type clcb_file_inspection = extern "C" fn(
+    fd: c_int,
+    file_type: *const c_char,
+    ancestors: *const *const c_char,
+    parent_file_size: usize,
+    file_name: *const c_char,
+    file_size: usize,
+    file_buffer: *const c_char,
+    recursion_level: u32,
+    layer_attributes: u32,
+    context: *mut c_void,
+) -> cl_error_t;
+
+/// 扫描后回调类型：每一层扫描完成后调用
+pub type clcb_post_scan = extern "C" fn(
+    fd: c_int,
+    result: c_int,
+    virname: *const c_char,
+    context: *mut c_void,
+) -> cl_error_t;
+
+/// CVD 文件头结构体（与 C 结构体 struct cl_cvd 布局一致）
+#[repr(C)]
+pub struct cl_cvd {
+    pub time: *mut c_char,
+    pub version: c_uint,
+    pub sigs: c_uint,
+    pub fl: c_uint,
+    pub md5: *mut c_char,
+    pub dsig: *mut c_char,
+    pub builder: *mut c_char,
+    pub stime: c_uint,
+}
+
+/// 文件映射结构体 (opaque pointer)
+#[repr(C)]
+pub struct cl_fmap_t {
+    _private: [u8; 0],
+}
+
+/// 病毒库目录状态结构体（与 C 结构体 struct cl_stat 布局一致）
+#[repr(C)]
+pub struct cl_stat {
+    dir: *mut c_char,
+    stattab: *mut c_void,
+    statdname: *mut *mut c_char,
+    entries: c_uint,
+}
+
 /// 扫描结果枚举
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -86,6 +149,10 @@ pub enum cl_engine_field {
     CL_ENGINE_CVDCERTSDIR,
 }
 
+// 字节码签名信任级别 (CL_ENGINE_BYTECODE_SECURITY)
+pub const CL_BYTECODE_TRUST_ALL: i64 = 0;
+pub const CL_BYTECODE_TRUST_SIGNED: i64 = 1;
+
 // 扫描选项常量 - general 字段
 pub const CL_SCAN_GENERAL_ALLMATCHES: u32 = 0x1;
 pub const CL_SCAN_GENERAL_COLLECT_METADATA: u32 = 0x2;
@@ -102,6 +169,7 @@ pub const CL_SCAN_PARSE_MAIL: u32 = 0x40;
 pub const CL_SCAN_PARSE_OLE2: u32 = 0x80;
 pub const CL_SCAN_PARSE_HTML: u32 = 0x100;
 pub const CL_SCAN_PARSE_PE: u32 = 0x200;
+pub const CL_SCAN_PARSE_ONENOTE: u32 = 0x400;
 
 // 默认解析选项：启用所有解析器
 pub const CL_SCAN_PARSE_DEFAULT: u32 = CL_SCAN_PARSE_ARCHIVE
@@ -113,65 +181,72 @@ pub const CL_SCAN_PARSE_DEFAULT: u32 = CL_SCAN_PARSE_ARCHIVE
     | CL_SCAN_PARSE_MAIL
     | CL_SCAN_PARSE_OLE2
     | CL_SCAN_PARSE_HTML
-    | CL_SCAN_PARSE_PE;
+    | CL_SCAN_PARSE_PE
+    | CL_SCAN_PARSE_ONENOTE;
+
+// 启发式告警选项 (cl_scan_options.heuristic)
+pub const CL_SCAN_HEURISTIC_BROKEN: u32 = 0x2;
+pub const CL_SCAN_HEURISTIC_EXCEEDS_MAX: u32 = 0x4;
+pub const CL_SCAN_HEURISTIC_PHISHING_SSL_MISMATCH: u32 = 0x8;
+pub const CL_SCAN_HEURISTIC_PHISHING_CLOAK: u32 = 0x10;
+pub const CL_SCAN_HEURISTIC_MACROS: u32 = 0x20;
+pub const CL_SCAN_HEURISTIC_ENCRYPTED_ARCHIVE: u32 = 0x40;
+pub const CL_SCAN_HEURISTIC_ENCRYPTED_DOC: u32 = 0x80;
+pub const CL_SCAN_HEURISTIC_PARTITION_INTXN: u32 = 0x100;
+pub const CL_SCAN_HEURISTIC_STRUCTURED: u32 = 0x200;
+pub const CL_SCAN_HEURISTIC_STRUCTURED_SSN_NORMAL: u32 = 0x400;
+pub const CL_SCAN_HEURISTIC_STRUCTURED_SSN_STRIPPED: u32 = 0x800;
+pub const CL_SCAN_HEURISTIC_STRUCTURED_CC: u32 = 0x1000;
 
 // 错误码常量
 pub const CL_CLEAN: cl_error_t = 0;
 pub const CL_SUCCESS: cl_error_t = 0;
 pub const CL_VIRUS: cl_error_t = 1;
+pub const CL_ENULLARG: cl_error_t = 2;
+pub const CL_EARG: cl_error_t = 3;
+pub const CL_EMALFDB: cl_error_t = 4;
+pub const CL_ECVD: cl_error_t = 5;
+pub const CL_EVERIFY: cl_error_t = 6;
+pub const CL_EUNPACK: cl_error_t = 7;
+pub const CL_EOPEN: cl_error_t = 8;
+pub const CL_ECREAT: cl_error_t = 9;
+pub const CL_EUNLINK: cl_error_t = 10;
+pub const CL_ESTAT: cl_error_t = 11;
+pub const CL_EREAD: cl_error_t = 12;
+pub const CL_ESEEK: cl_error_t = 13;
+pub const CL_EWRITE: cl_error_t = 14;
+pub const CL_EDUP: cl_error_t = 15;
+pub const CL_EACCES: cl_error_t = 16;
+pub const CL_ETMPFILE: cl_error_t = 17;
+pub const CL_ETMPDIR: cl_error_t = 18;
+pub const CL_EMAP: cl_error_t = 19;
+pub const CL_EMEM: cl_error_t = 20;
+pub const CL_ETIMEOUT: cl_error_t = 21;
+pub const CL_BREAK: cl_error_t = 22;
+pub const CL_EMAXREC: cl_error_t = 23;
+pub const CL_EMAXSIZE: cl_error_t = 24;
+pub const CL_EMAXFILES: cl_error_t = 25;
+pub const CL_EFORMAT: cl_error_t = 26;
+pub const CL_EPARSE: cl_error_t = 27;
+pub const CL_EBYTECODE: cl_error_t = 28;
 
 // 数据库选项常量（与 ClamAV 1.5.1 C API 对齐）
 pub const CL_DB_PHISHING: u32 = 0x2;
 pub const CL_DB_PHISHING_URLS: u32 = 0x8;
 pub const CL_DB_PUA: u32 = 0x10;
+pub const CL_DB_PUA_MODE: u32 = 0x80;
+pub const CL_DB_PUA_INCLUDE: u32 = 0x100;
+pub const CL_DB_PUA_EXCLUDE: u32 = 0x200;
+pub const CL_DB_OFFICIAL_ONLY: u32 = 0x1000;
 pub const CL_DB_BYTECODE: u32 = 0x2000;
 pub const CL_DB_STDOPT: u32 = CL_DB_PHISHING | CL_DB_PHISHING_URLS | CL_DB_BYTECODE;
 
 // ============ FFI 函数声明 ============
 
+// libclamav 函数在运行时加载（见 library.rs），这里仅声明 libc 的 free
 extern "C" {
-    /// 初始化 ClamAV 库
-    fn cl_init(initoptions: c_uint) -> cl_error_t;
-
-    /// 创建新的扫描引擎
-    fn cl_engine_new() -> *mut cl_engine;
-
-    /// 设置引擎字符串选项
-    fn cl_engine_set_str(
-        engine: *mut cl_engine,
-        field: cl_engine_field,
-        str: *const c_char,
-    ) -> cl_error_t;
-
-    /// 编译扫描引擎
-    fn cl_engine_compile(engine: *mut cl_engine) -> cl_error_t;
-
-    /// 释放扫描引擎
-    fn cl_engine_free(engine: *mut cl_engine) -> cl_error_t;
-
-    /// 加载病毒数据库
-    fn cl_load(
-        path: *const c_char,
-        engine: *mut cl_engine,
-        signo: *mut c_uint,
-        dboptions: c_uint,
-    ) -> cl_error_t;
-
-    /// 扫描文件（扩展版本）
-    fn cl_scanfile_ex(
-        filename: *const c_char,
-        verdict_out: *mut cl_verdict_t,
-        last_alert_out: *mut *const c_char,
-        scanned_out: *mut u64,
-        engine: *const cl_engine,
-        scanoptions: *const cl_scan_options,
-        context: *mut c_void,
-        hash_hint: *const c_char,
-        hash_out: *mut *mut c_char,
-        hash_alg: *const c_char,
-        file_type_hint: *const c_char,
-        file_type_out: *mut *mut c_char,
-    ) -> cl_error_t;
+    /// 释放 libclamav 分配的内存（hash_out / file_type_out）
+    fn free(ptr: *mut c_void);
 }
 
 // ============ Rust 封装结构体 ============
@@ -180,15 +255,92 @@ extern "C" {
 pub struct ClamAVEngine {
     engine: *mut cl_engine,
     initialized: bool,
+    info: EngineInfo,
+    /// 扫描时启用的启发式告警类别
+    heuristic_alerts: HeuristicAlerts,
+}
+
+/// 引擎加载信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EngineInfo {
+    /// 已加载签名数量（含自定义签名）
+    pub signatures: u32,
+    /// libclamav 版本（cl_retver）
+    pub library_version: String,
+    /// libclamav 功能级别（cl_retflevel）
+    pub functionality_level: u32,
+    /// 引擎病毒库版本（CL_ENGINE_DB_VERSION）
+    pub db_version: u32,
+    /// 引擎病毒库构建时间（CL_ENGINE_DB_TIME，Unix 时间戳）
+    pub db_time: i64,
+    /// 加载病毒库耗时（毫秒）
+    pub load_duration_ms: u64,
+    /// 编译引擎耗时（毫秒）
+    pub compile_duration_ms: u64,
+    /// 已加载的数据库文件
+    pub loaded_files: Vec<String>,
+    /// 引擎就绪时间（Unix 时间戳）
+    pub loaded_at: i64,
+    /// 初始化时应用的引擎设置
+    pub settings: EngineSettings,
+    /// 初始化时应用的资源限制
+    pub limits: EngineLimits,
+    /// 实际加载的 libclamav 路径
+    pub library_path: String,
+    /// 加载前校验未通过、被移入 rejected/ 的数据库文件
+    pub rejected_files: Vec<RejectedDatabase>,
+    /// 从 known-good/ 恢复的数据库文件
+    pub restored_files: Vec<String>,
+    /// 影响检测结果的引擎配置指纹（自定义签名、加载选项、资源限制、引擎设置、启发式告警）
+    ///
+    /// 官方病毒库的变化由 db_version 体现，不计入指纹
+    pub fingerprint: String,
+}
+
+/// 引擎运行设置（字节码安全、缓存、启发式总开关）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EngineSettings {
+    /// 仅加载受信任（官方签名）的字节码签名
+    pub bytecode_trusted_only: bool,
+    /// 单个字节码签名执行超时（毫秒）
+    pub bytecode_timeout_ms: u64,
+    /// 是否启用引擎内部的干净文件缓存
+    pub cache_enabled: bool,
+    /// 引擎缓存条目数（0 表示使用 libclamav 默认值）
+    pub cache_size: u32,
+    /// 启发式扫描总开关，关闭后忽略扫描选项中的 heuristics 和启发式告警类别
+    pub heuristic_scan: bool,
+}
+
+impl Default for EngineSettings {
+    /// 与 libclamav 默认值一致
+    fn default() -> Self {
+        Self {
+            bytecode_trusted_only: true,
+            bytecode_timeout_ms: 10000,
+            cache_enabled: true,
+            cache_size: 0,
+            heuristic_scan: true,
+        }
+    }
 }
 
 /// ClamAV 扫描选项
-#[derive(Debug, Clone, Copy)]
+///
+/// 每个 scan_* 开关对应 cl_scan_options.parse 中的一个解析器位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ScanOptions {
     pub scan_archive: bool,
     pub scan_pdf: bool,
     pub scan_elf: bool,
     pub scan_mail: bool,
+    pub scan_ole2: bool,
+    pub scan_html: bool,
+    pub scan_pe: bool,
+    pub scan_swf: bool,
+    pub scan_hwp: bool,
+    pub scan_xmldocs: bool,
+    pub scan_onenote: bool,
     pub heuristics: bool,
 }
 
@@ -199,17 +351,423 @@ impl Default for ScanOptions {
             scan_pdf: true,
             scan_elf: true,
             scan_mail: true,
+            scan_ole2: true,
+            scan_html: true,
+            scan_pe: true,
+            scan_swf: true,
+            scan_hwp: true,
+            scan_xmldocs: true,
+            scan_onenote: true,
             heuristics: true,
         }
     }
 }
 
+impl ScanOptions {
+    /// 构建 cl_scan_options.parse 位掩码
+    ///
+    /// 以 !0 为基础（ClamAV 推荐方式，保留未单独暴露的解析器），
+    /// 再清除被关闭的解析器对应的位
+    pub fn parse_flags(&self) -> u32 {
+        let toggles = [
+            (self.scan_archive, CL_SCAN_PARSE_ARCHIVE),
+            (self.scan_elf, CL_SCAN_PARSE_ELF),
+            (self.scan_pdf, CL_SCAN_PARSE_PDF),
+            (self.scan_swf, CL_SCAN_PARSE_SWF),
+            (self.scan_hwp, CL_SCAN_PARSE_HWP),
+            (self.scan_xmldocs, CL_SCAN_PARSE_XMLDOCS),
+            (self.scan_mail, CL_SCAN_PARSE_MAIL),
+            (self.scan_ole2, CL_SCAN_PARSE_OLE2),
+            (self.scan_html, CL_SCAN_PARSE_HTML),
+            (self.scan_pe, CL_SCAN_PARSE_PE),
+            (self.scan_onenote, CL_SCAN_PARSE_ONENOTE),
+        ];
+
+        toggles.iter().fold(!0u32, |parse, &(enabled, flag)| {
+            if enabled { parse } else { parse & !flag }
+        })
+    }
+
+    /// 转换为 libclamav 的 cl_scan_options 结构体
+    pub fn to_cl_scan_options(&self) -> cl_scan_options {
+        let mut scan_opts = cl_scan_options {
+            general: 0,
+            parse: self.parse_flags(),
+            heuristic: 0,
+            mail: 0,
+            dev: 0,
+        };
+
+        if self.heuristics {
+            scan_opts.general |= CL_SCAN_GENERAL_HEURISTICS;
+        }
+
+        // 启用 all-match 模式以确保检测所有威胁
+        scan_opts.general |= CL_SCAN_GENERAL_ALLMATCHES;
+
+        scan_opts
+    }
+}
+
+/// 引擎资源限制
+///
+/// 在引擎编译前通过 cl_engine_set_num 设置，0 表示不限制
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EngineLimits {
+    /// 单个文件最大大小（字节），超过的文件不扫描
+    pub max_file_size: u64,
+    /// 单个文件（含解压内容）最大扫描数据量（字节）
+    pub max_scan_size: u64,
+    /// 压缩包最大嵌套深度
+    pub max_recursion: u32,
+    /// 单个容器内最大扫描文件数
+    pub max_files: u32,
+    /// 嵌入式 PE 最大大小（字节）
+    pub max_embedded_pe: u64,
+    /// 单个文件最大扫描时间（毫秒）
+    pub max_scan_time_ms: u64,
+}
+
+impl Default for EngineLimits {
+    /// 与 libclamav 内置默认值保持一致
+    fn default() -> Self {
+        Self {
+            max_file_size: 100 * 1024 * 1024,
+            max_scan_size: 400 * 1024 * 1024,
+            max_recursion: 17,
+            max_files: 10000,
+            max_embedded_pe: 40 * 1024 * 1024,
+            max_scan_time_ms: 120_000,
+        }
+    }
+}
+
+/// 病毒库加载选项
+///
+/// 在 cl_load 时转换为 dboptions 位掩码，PUA 类别通过 CL_ENGINE_PUA_CATEGORIES 设置
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseOptions {
+    /// 是否加载 PUA（潜在不受欢迎程序）签名
+    pub detect_pua: bool,
+    /// 仅加载这些 PUA 类别（如 Win, Packed, Tool）
+    pub pua_include: Vec<String>,
+    /// 排除这些 PUA 类别，优先于 pua_include
+    pub pua_exclude: Vec<String>,
+    /// 仅加载官方签名库（跳过自定义/第三方签名）
+    pub official_only: bool,
+    /// 是否加载钓鱼邮件签名
+    pub phishing_signatures: bool,
+    /// 是否加载钓鱼 URL 签名
+    pub phishing_urls: bool,
+}
+
+impl Default for DatabaseOptions {
+    /// 与 CL_DB_STDOPT 保持一致
+    fn default() -> Self {
+        Self {
+            detect_pua: false,
+            pua_include: Vec::new(),
+            pua_exclude: Vec::new(),
+            official_only: false,
+            phishing_signatures: true,
+            phishing_urls: true,
+        }
+    }
+}
+
+impl DatabaseOptions {
+    /// 生成 cl_load 使用的 dboptions 位掩码
+    pub fn db_flags(&self) -> u32 {
+        let mut flags = CL_DB_BYTECODE;
+
+        if self.phishing_signatures {
+            flags |= CL_DB_PHISHING;
+        }
+        if self.phishing_urls {
+            flags |= CL_DB_PHISHING_URLS;
+        }
+        if self.official_only {
+            flags |= CL_DB_OFFICIAL_ONLY;
+        }
+        if self.detect_pua {
+            flags |= CL_DB_PUA;
+            // 与 clamd 一致：排除列表优先，其次才是包含列表
+            if !self.pua_exclude.is_empty() {
+                flags |= CL_DB_PUA_MODE | CL_DB_PUA_EXCLUDE;
+            } else if !self.pua_include.is_empty() {
+                flags |= CL_DB_PUA_MODE | CL_DB_PUA_INCLUDE;
+            }
+        }
+
+        flags
+    }
+
+    /// 生成 CL_ENGINE_PUA_CATEGORIES 字符串（格式 ".Win.Packed."）
+    ///
+    /// 未启用 PUA 或未指定类别时返回 None
+    pub fn pua_categories(&self) -> Option<String> {
+        if !self.detect_pua {
+            return None;
+        }
+
+        let categories = if !self.pua_exclude.is_empty() {
+            &self.pua_exclude
+        } else {
+            &self.pua_include
+        };
+
+        let names: Vec<&str> = categories.iter()
+            .map(|c| c.trim().trim_matches('.'))
+            .filter(|c| !c.is_empty() && !c.contains('.'))
+            .collect();
+
+        if names.is_empty() {
+            None
+        } else {
+            Some(format!(".{}.", names.join(".")))
+        }
+    }
+}
+
+/// 启发式告警类别
+///
+/// 对应 cl_scan_options.heuristic 中的各个位，默认全部关闭（与 clamscan 默认一致）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeuristicAlerts {
+    /// 加密的压缩包
+    pub encrypted_archive: bool,
+    /// 加密的文档（PDF、Office）
+    pub encrypted_doc: bool,
+    /// 损坏的可执行文件
+    pub broken_executables: bool,
+    /// 钓鱼邮件中 SSL 链接与显示地址不一致
+    pub phishing_ssl_mismatch: bool,
+    /// 钓鱼邮件中伪装（cloaked）的 URL
+    pub phishing_cloak: bool,
+    /// 含宏的 OLE2 文档
+    pub ole2_macros: bool,
+    /// 分区表相互重叠的磁盘映像
+    pub partition_intersection: bool,
+    /// 结构化数据：信用卡号
+    pub structured_cc: bool,
+    /// 结构化数据：美国社会安全号码（SSN）
+    pub structured_ssn: bool,
+    /// 文件超出扫描限制（大小、嵌套深度、文件数等）
+    pub exceeds_max: bool,
+}
+
+impl HeuristicAlerts {
+    /// 转换为 cl_scan_options.heuristic 位掩码
+    pub fn flags(&self) -> u32 {
+        let toggles = [
+            (self.encrypted_archive, CL_SCAN_HEURISTIC_ENCRYPTED_ARCHIVE),
+            (self.encrypted_doc, CL_SCAN_HEURISTIC_ENCRYPTED_DOC),
+            (self.broken_executables, CL_SCAN_HEURISTIC_BROKEN),
+            (self.phishing_ssl_mismatch, CL_SCAN_HEURISTIC_PHISHING_SSL_MISMATCH),
+            (self.phishing_cloak, CL_SCAN_HEURISTIC_PHISHING_CLOAK),
+            (self.ole2_macros, CL_SCAN_HEURISTIC_MACROS),
+            (self.partition_intersection, CL_SCAN_HEURISTIC_PARTITION_INTXN),
+            (self.structured_cc, CL_SCAN_HEURISTIC_STRUCTURED | CL_SCAN_HEURISTIC_STRUCTURED_CC),
+            (self.structured_ssn, CL_SCAN_HEURISTIC_STRUCTURED
+                | CL_SCAN_HEURISTIC_STRUCTURED_SSN_NORMAL
+                | CL_SCAN_HEURISTIC_STRUCTURED_SSN_STRIPPED),
+            (self.exceeds_max, CL_SCAN_HEURISTIC_EXCEEDS_MAX),
+        ];
+
+        toggles.iter().fold(0u32, |heuristic, &(enabled, flag)| {
+            if enabled { heuristic | flag } else { heuristic }
+        })
+    }
+}
+
 /// 扫描结果
 #[derive(Debug, Clone)]
 pub struct ScanResult {
     pub filename: String,
-    pub virus_name: Option<String>,
+    /// 所有匹配的签名名称（all-match 模式下可能有多个）
+    pub virus_names: Vec<String>,
     pub is_infected: bool,
+    /// 文件 SHA-256 哈希
+    pub file_hash: Option<String>,
+    /// ClamAV 识别的文件类型（如 CL_TYPE_PE）
+    pub file_type: Option<String>,
+    /// 每个签名命中的容器内成员路径（与 virus_names 一一对应，顶层文件命中时为 None）
+    pub container_paths: Vec<Option<String>>,
+}
+
+impl ScanResult {
+    /// 是否因内容加密而无法完整扫描（启发式 Heuristics.Encrypted.* 告警）
+    pub fn is_encrypted(&self) -> bool {
+        self.virus_names.iter().any(|n| n.starts_with("Heuristics.Encrypted."))
+    }
+
+    /// 主要签名名称（第一个匹配）
+    pub fn virus_name(&self) -> Option<&str> {
+        self.virus_names.first().map(String::as_str)
+    }
+
+    /// 转换为威胁列表（每个匹配签名一条）
+    pub fn threats(&self) -> Vec<DetectedThreat> {
+        self.virus_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| DetectedThreat {
+                file_path: FilePath(self.filename.clone()),
+                virus_name: VirusName(name.clone()),
+                file_hash: self.file_hash.clone(),
+                file_type: self.file_type.clone(),
+                container_path: self.container_paths.get(i).cloned().flatten(),
+            })
+            .collect()
+    }
+}
+
+/// 单次扫描的回调上下文
+///
+/// 通过 cl_scandesc_ex 的 context 参数传递给 libclamav 回调
+#[derive(Debug, Default)]
+struct ScanContext {
+    alerts: Vec<String>,
+    /// 每个告警发生时所在的容器成员路径（与 alerts 一一对应）
+    alert_containers: Vec<Option<String>>,
+    /// 正在扫描的层（按嵌套深度），文件检查回调压入，扫描后回调弹出
+    layers: Vec<ScanLayer>,
+}
+
+/// 正在扫描的一层（顶层文件或容器成员）
+#[derive(Debug)]
+struct ScanLayer {
+    fd: c_int,
+    /// 容器成员路径，如 "inner.rar/evil.exe"（顶层文件为 None）
+    path: Option<String>,
+}
+
+impl ScanContext {
+    /// 取出去重后的签名名称及其容器成员路径（保持匹配顺序）
+    ///
+    /// 同一签名命中不同成员时分别保留，只去除同一位置的重复告警
+    fn into_alerts(self) -> (Vec<String>, Vec<Option<String>>) {
+        let mut alerts: Vec<String> = Vec::with_capacity(self.alerts.len());
+        let mut containers: Vec<Option<String>> = Vec::with_capacity(self.alerts.len());
+        for (i, alert) in self.alerts.into_iter().enumerate() {
+            let container = self.alert_containers.get(i).cloned().flatten();
+            let seen = alerts.iter().zip(&containers).any(|(a, c)| *a == alert && *c == container);
+            if !seen {
+                alerts.push(alert);
+                containers.push(container);
+            }
+        }
+        (alerts, containers)
+    }
+
+    /// 当前所在的容器成员路径，如 "inner.rar/evil.exe"
+    ///
+    /// 成员扫描完成后已弹出，容器本身的告警（成员之后才匹配的签名、启发式告警）标注为容器的路径
+    fn container_path(&self) -> Option<String> {
+        self.layers.last().and_then(|layer| layer.path.clone())
+    }
+}
+
+/// 容器成员名称缺失时的占位符
+const UNNAMED_MEMBER: &str = "<unnamed>";
+
+/// 读取可能为 NULL 的 C 字符串，只保留最后一段文件名
+///
+/// libclamav 对解包出的成员可能给出临时文件的完整路径
+unsafe fn member_name(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return UNNAMED_MEMBER.to_string();
+    }
+    let name = CStr::from_ptr(ptr).to_string_lossy();
+    match name.rsplit(['/', '\\']).next() {
+        Some(base) if !base.is_empty() => base.to_string(),
+        _ => UNNAMED_MEMBER.to_string(),
+    }
+}
+
+/// 扫描时请求的文件哈希算法
+const SCAN_HASH_ALG: &str = "sha256";
+
+/// 复制 libclamav 分配的字符串并释放原内存
+unsafe fn take_clamav_string(ptr: *mut c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let value = CStr::from_ptr(ptr).to_string_lossy().to_string();
+    free(ptr as *mut c_void);
+    Some(value)
+}
+
+/// 病毒发现回调：all-match 模式下每个匹配的签名都会调用一次
+extern "C" fn virus_found_callback(_fd: c_int, virname: *const c_char, context: *mut c_void) {
+    if virname.is_null() || context.is_null() {
+        return;
+    }
+    unsafe {
+        let ctx = &mut *(context as *mut ScanContext);
+        let container = ctx.container_path();
+        ctx.alerts.push(CStr::from_ptr(virname).to_string_lossy().to_string());
+        ctx.alert_containers.push(container);
+    }
+}
+
+/// 文件检查回调：压入当前扫描层及其容器链，供病毒发现回调标注成员路径
+///
+/// 顶层文件 recursion_level 为 0；第 n 层成员的 ancestors[1..n] 为中间容器名，
+/// ancestors[0] 即顶层文件本身，已由 ScanResult.filename 表示，不计入成员链。
+/// 同一深度及更深的旧层已经扫描完成（未收到扫描后回调时在这里丢弃）
+#[allow(clippy::too_many_arguments)]
+extern "C" fn file_inspection_callback(
+    fd: c_int,
+    _file_type: *const c_char,
+    ancestors: *const *const c_char,
+    _parent_file_size: usize,
+    file_name: *const c_char,
+    _file_size: usize,
+    _file_buffer: *const c_char,
+    recursion_level: u32,
+    _layer_attributes: u32,
+    context: *mut c_void,
+) -> cl_error_t {
+    if context.is_null() {
+        return CL_CLEAN;
+    }
+    unsafe {
+        let ctx = &mut *(context as *mut ScanContext);
+        let level = recursion_level as usize;
+        ctx.layers.truncate(level);
+
+        let path = (level > 0).then(|| {
+            let mut members: Vec<String> = (1..level)
+                .map(|i| member_name(if ancestors.is_null() { ptr::null() } else { *ancestors.add(i) }))
+                .collect();
+            members.push(member_name(file_name));
+            members.join("/")
+        });
+        ctx.layers.push(ScanLayer { fd, path });
+    }
+    CL_CLEAN
+}
+
+/// 扫描后回调：当前层扫描完成，弹出该层，之后的告警属于外层容器
+///
+/// 只弹出文件描述符相同的层（未经过文件检查回调的层，例如命中缓存的成员，不影响外层）
+extern "C" fn post_scan_callback(
+    fd: c_int,
+    _result: c_int,
+    _virname: *const c_char,
+    context: *mut c_void,
+) -> cl_error_t {
+    if context.is_null() {
+        return CL_CLEAN;
+    }
+    unsafe {
+        let ctx = &mut *(context as *mut ScanContext);
+        if ctx.layers.last().is_some_and(|layer| layer.fd == fd) {
+            ctx.layers.pop();
+        }
+    }
+    CL_CLEAN
 }
 
 /// ClamAV 错误类型
@@ -221,17 +779,118 @@ pub enum ClamAVError {
     EngineCompilationFailed(String),
     ScanFailed(String),
     InvalidPath(String),
+    /// libclamav 动态库无法加载或缺少所需符号
+    LibraryUnavailable(String),
+    /// libclamav 扫描函数返回的错误码
+    Scan {
+        code: cl_error_t,
+        category: ErrorCategory,
+        message: String,
+    },
+}
+
+impl ClamAVError {
+    /// 由扫描错误码构造错误（使用 cl_strerror 获取描述）
+    pub fn from_code(api: &str, code: cl_error_t) -> Self {
+        Self::scan_error(code, format!("{}: {}", api, strerror(code)))
+    }
+
+    /// 由扫描错误码和描述构造错误
+    pub fn scan_error(code: cl_error_t, message: String) -> Self {
+        ClamAVError::Scan {
+            code,
+            category: error_category(code),
+            message,
+        }
+    }
+
+    /// 由打开文件失败的 I/O 错误构造错误（按 CL_EOPEN 处理，权限不足单独分类）
+    pub fn from_io(path: &str, error: &std::io::Error) -> Self {
+        ClamAVError::Scan {
+            code: CL_EOPEN,
+            category: io_error_category(error),
+            message: format!("cannot open {}: {}", path, error),
+        }
+    }
+
+    /// 原始 cl_error_t 错误码
+    pub fn code(&self) -> Option<cl_error_t> {
+        match self {
+            ClamAVError::Scan { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// 错误分类
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ClamAVError::Scan { category, .. } => *category,
+            ClamAVError::InvalidPath(_) => ErrorCategory::Io,
+            _ => ErrorCategory::Other,
+        }
+    }
+}
+
+/// 获取错误码的描述信息（cl_strerror）
+pub fn strerror(code: cl_error_t) -> String {
+    if library().is_err() {
+        return format!("error code {}", code);
+    }
+    unsafe {
+        let msg = cl_strerror(code);
+        if msg.is_null() {
+            format!("error code {}", code)
+        } else {
+            CStr::from_ptr(msg).to_string_lossy().to_string()
+        }
+    }
+}
+
+/// 将 cl_error_t 映射到错误分类
+pub fn error_category(code: cl_error_t) -> ErrorCategory {
+    match code {
+        CL_EACCES => ErrorCategory::Permission,
+        CL_EOPEN | CL_ECREAT | CL_EUNLINK | CL_ESTAT | CL_EREAD | CL_ESEEK | CL_EWRITE
+        | CL_EDUP | CL_ETMPFILE | CL_ETMPDIR | CL_EMAP | CL_EMEM => ErrorCategory::Io,
+        CL_EMAXREC | CL_EMAXSIZE | CL_EMAXFILES => ErrorCategory::Limits,
+        CL_EFORMAT | CL_EPARSE | CL_EUNPACK | CL_EMALFDB | CL_EBYTECODE => ErrorCategory::Parse,
+        CL_ETIMEOUT => ErrorCategory::Timeout,
+        _ => ErrorCategory::Other,
+    }
+}
+
+/// 细化打开失败的分类：libclamav 打开文件失败时统一返回 CL_EOPEN，
+/// 通过重新打开文件区分权限不足
+fn refine_open_error(error: ClamAVError, path: &str) -> ClamAVError {
+    match error {
+        ClamAVError::Scan { code: CL_EOPEN, message, .. } => {
+            let denied = std::fs::File::open(path)
+                .map_err(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+                .err()
+                .unwrap_or(false);
+            ClamAVError::Scan {
+                code: CL_EOPEN,
+                category: if denied { ErrorCategory::Permission } else { ErrorCategory::Io },
+                message,
+            }
+        }
+        other => other,
+    }
 }
 
 impl std::fmt::Display for ClamAVError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            ClamAVError::Scan { code, category, message } => {
+                write!(f, "Scan failed ({}): {} (code {})", category, message, code)
+            }
             ClamAVError::InitializationFailed(msg) => write!(f, "Initialization failed: {}", msg),
             ClamAVError::EngineCreationFailed(msg) => write!(f, "Engine creation failed: {}", msg),
             ClamAVError::DatabaseLoadFailed(msg) => write!(f, "Database load failed: {}", msg),
             ClamAVError::EngineCompilationFailed(msg) => write!(f, "Engine compilation failed: {}", msg),
             ClamAVError::ScanFailed(msg) => write!(f, "Scan failed: {}", msg),
             ClamAVError::InvalidPath(msg) => write!(f, "Invalid path: {}", msg),
+            ClamAVError::LibraryUnavailable(msg) => write!(f, "libclamav unavailable: {}", msg),
         }
     }
 }
@@ -250,7 +909,16 @@ impl ClamAVEngine {
     /// # 参数
     /// - db_dir: 病毒库目录路径
     /// - certs_dir: 证书目录路径（可选）
-    pub fn initialize(db_dir: &str, certs_dir: Option<&str>) -> Result<Self, ClamAVError> {
+    /// - limits: 引擎资源限制
+    pub fn initialize(
+        db_dir: &str,
+        custom_sigs_dir: Option<&str>,
+        certs_dir: Option<&str>,
+        limits: &EngineLimits,
+        db_options: &DatabaseOptions,
+        settings: &EngineSettings,
+    ) -> Result<Self, ClamAVError> {
+        let library = library()?;
         unsafe {
             // 初始化 ClamAV 库
             let ret = cl_init(0);
@@ -294,29 +962,85 @@ impl ClamAVEngine {
                 tracing::info!("No certificate directory specified, using ClamAV defaults");
             }
 
-            // 加载病毒数据库
-            let db_dir_cstr = CString::new(db_dir).unwrap_or_else(|_| {
-                CString::new("<invalid>").unwrap()
-            });
-            let mut signo: c_uint = 0;
-            tracing::info!("Loading virus database from: {}", db_dir);
-            let ret = cl_load(
-                db_dir_cstr.as_ptr(),
-                engine,
-                &mut signo,
-                CL_DB_STDOPT as c_uint,
-            );
+            // 设置资源限制
+            if let Err(e) = Self::apply_limits(engine, limits) {
+                cl_engine_free(engine);
+                return Err(e);
+            }
 
-            if ret != CL_SUCCESS {
+            // 设置字节码安全级别和缓存（字节码信任级别需在 cl_load 之前设置）
+            if let Err(e) = Self::apply_settings(engine, settings) {
                 cl_engine_free(engine);
-                return Err(ClamAVError::DatabaseLoadFailed(
-                    format!("cl_load failed with code: {}", ret)
-                ));
+                return Err(e);
+            }
+
+            // 设置 PUA 类别过滤（需在 cl_load 之前设置）
+            if let Some(categories) = db_options.pua_categories() {
+                tracing::info!("Setting PUA categories: {}", categories);
+                let categories_cstr = CString::new(categories.as_str()).unwrap_or_else(|_| {
+                    CString::new("").unwrap()
+                });
+                let ret = cl_engine_set_str(engine, cl_engine_field::CL_ENGINE_PUA_CATEGORIES, categories_cstr.as_ptr());
+                if ret != CL_SUCCESS {
+                    cl_engine_free(engine);
+                    return Err(ClamAVError::InitializationFailed(
+                        format!("Failed to set PUA categories '{}': error code {}", categories, ret)
+                    ));
+                }
             }
+
+            // 注册病毒发现回调，用于收集 all-match 模式下的全部签名；
+            // 文件检查回调记录容器成员链，用于定位压缩包内的命中成员
+            cl_engine_set_clcb_virus_found(engine, virus_found_callback);
+            cl_engine_set_clcb_file_inspection(engine, file_inspection_callback);
+            cl_engine_set_clcb_post_scan(engine, post_scan_callback);
+
+            // 加载病毒数据库
+            let load_started = std::time::Instant::now();
+            let mut loaded_files: Vec<String> = super::database::database_files(db_dir)
+                .iter()
+                .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                .collect();
+            let dboptions = db_options.db_flags();
+            tracing::info!("Loading virus database from: {} (dboptions=0x{:x})", db_dir, dboptions);
+            let mut signo = match load_database(engine, db_dir, dboptions as c_uint) {
+                Ok(n) => n,
+                Err(e) => {
+                    cl_engine_free(engine);
+                    return Err(e);
+                }
+            };
             tracing::info!("Loaded {} signatures from database", signo);
 
+            // 加载自定义签名库（逐个文件加载，空目录不视为错误）
+            // 仅官方签名模式下跳过自定义签名库
+            let custom_sigs_dir = custom_sigs_dir.filter(|_| !db_options.official_only);
+            let mut custom_files = Vec::new();
+            let mut custom_signo: u32 = 0;
+            if let Some(custom_dir) = custom_sigs_dir {
+                for file in custom_signature_files(custom_dir) {
+                    match load_database(engine, &file, dboptions as c_uint) {
+                        Ok(n) => {
+                            tracing::info!("Loaded {} custom signatures from {}", n, file);
+                            signo += n;
+                            custom_signo += n;
+                            custom_files.push(file.clone());
+                            loaded_files.push(file.clone());
+                        }
+                        Err(e) => {
+                            cl_engine_free(engine);
+                            return Err(e);
+                        }
+                    }
+                }
+                tracing::info!("Total signatures loaded: {}", signo);
+            }
+
+            let load_duration = load_started.elapsed();
+
             // 编译引擎
             tracing::info!("Compiling ClamAV engine...");
+            let compile_started = std::time::Instant::now();
             let ret = cl_engine_compile(engine);
             if ret != CL_SUCCESS {
                 cl_engine_free(engine);
@@ -324,17 +1048,185 @@ impl ClamAVEngine {
                     format!("cl_engine_compile failed with code: {}", ret)
                 ));
             }
-            tracing::info!("ClamAV engine compiled successfully");
+            let compile_duration = compile_started.elapsed();
+            tracing::info!("ClamAV engine compiled successfully in {}ms", compile_duration.as_millis());
+
+            let mut err: c_int = 0;
+            let db_version = cl_engine_get_num(engine, cl_engine_field::CL_ENGINE_DB_VERSION, &mut err);
+            let db_time = cl_engine_get_num(engine, cl_engine_field::CL_ENGINE_DB_TIME, &mut err);
+            let library_version = {
+                let ver = cl_retver();
+                if ver.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(ver).to_string_lossy().to_string()
+                }
+            };
+
+            let info = EngineInfo {
+                signatures: signo,
+                library_version,
+                functionality_level: cl_retflevel(),
+                db_version: u32::try_from(db_version).unwrap_or(0),
+                db_time,
+                load_duration_ms: load_duration.as_millis() as u64,
+                compile_duration_ms: compile_duration.as_millis() as u64,
+                loaded_files,
+                loaded_at: chrono::Utc::now().timestamp(),
+                settings: settings.clone(),
+                limits: *limits,
+                library_path: library.path().to_string(),
+                rejected_files: Vec::new(),
+                restored_files: Vec::new(),
+                fingerprint: engine_fingerprint(&custom_files, custom_signo, cl_retflevel(), db_options, limits, settings),
+            };
+            tracing::info!("Engine info: libclamav {} (flevel {}), db version {}, {} signatures",
+                           info.library_version, info.functionality_level, info.db_version, info.signatures);
 
             Ok(Self {
                 engine,
                 initialized: true,
+                info,
+                heuristic_alerts: HeuristicAlerts::default(),
             })
         }
     }
 
+    /// 将资源限制写入引擎（必须在 cl_engine_compile 之前调用）
+    unsafe fn apply_limits(engine: *mut cl_engine, limits: &EngineLimits) -> Result<(), ClamAVError> {
+        let fields = [
+            (cl_engine_field::CL_ENGINE_MAX_FILESIZE, limits.max_file_size, "max_file_size"),
+            (cl_engine_field::CL_ENGINE_MAX_SCANSIZE, limits.max_scan_size, "max_scan_size"),
+            (cl_engine_field::CL_ENGINE_MAX_RECURSION, limits.max_recursion as u64, "max_recursion"),
+            (cl_engine_field::CL_ENGINE_MAX_FILES, limits.max_files as u64, "max_files"),
+            (cl_engine_field::CL_ENGINE_MAX_EMBEDDEDPE, limits.max_embedded_pe, "max_embedded_pe"),
+            (cl_engine_field::CL_ENGINE_MAX_SCANTIME, limits.max_scan_time_ms, "max_scan_time"),
+        ];
+
+        for (field, value, name) in fields {
+            let num = c_longlong::try_from(value).unwrap_or(c_longlong::MAX);
+            let ret = cl_engine_set_num(engine, field, num);
+            if ret != CL_SUCCESS {
+                return Err(ClamAVError::InitializationFailed(
+                    format!("Failed to set engine limit {}={}: error code {}", name, value, ret)
+                ));
+            }
+        }
+
+        tracing::info!("Engine limits applied: {:?}", limits);
+        Ok(())
+    }
+
+    /// 将字节码安全级别、字节码超时和缓存设置写入引擎（必须在 cl_load 之前调用）
+    unsafe fn apply_settings(engine: *mut cl_engine, settings: &EngineSettings) -> Result<(), ClamAVError> {
+        let bytecode_security = if settings.bytecode_trusted_only {
+            CL_BYTECODE_TRUST_SIGNED
+        } else {
+            CL_BYTECODE_TRUST_ALL
+        };
+
+        let mut fields = vec![
+            (cl_engine_field::CL_ENGINE_BYTECODE_SECURITY, bytecode_security, "bytecode_security"),
+            (cl_engine_field::CL_ENGINE_BYTECODE_TIMEOUT,
+             i64::try_from(settings.bytecode_timeout_ms).unwrap_or(i64::MAX), "bytecode_timeout"),
+        ];
+        if settings.cache_enabled {
+            if settings.cache_size > 0 {
+                fields.push((cl_engine_field::CL_ENGINE_CACHE_SIZE, settings.cache_size as i64, "cache_size"));
+            }
+        } else {
+            fields.push((cl_engine_field::CL_ENGINE_DISABLE_CACHE, 1, "disable_cache"));
+        }
+
+        for (field, value, name) in fields {
+            let ret = cl_engine_set_num(engine, field, value as c_longlong);
+            if ret != CL_SUCCESS {
+                return Err(ClamAVError::InitializationFailed(
+                    format!("Failed to set engine setting {}={}: error code {}", name, value, ret)
+                ));
+            }
+        }
+
+        tracing::info!("Engine settings applied: {:?}", settings);
+        Ok(())
+    }
+
+    /// 创建未初始化的引擎实例（仅用于测试）
+    #[cfg(test)]
+    pub(crate) fn uninitialized() -> Self {
+        Self {
+            engine: ptr::null_mut(),
+            initialized: false,
+            info: EngineInfo::default(),
+            heuristic_alerts: HeuristicAlerts::default(),
+        }
+    }
+
+    /// 记录加载前的数据库校验结果
+    pub fn with_database_verification(mut self, verification: &DatabaseVerification) -> Self {
+        self.info.rejected_files = verification.rejected.clone();
+        self.info.restored_files = verification.restored.clone();
+        self
+    }
+
+    /// 设置扫描时启用的启发式告警类别
+    pub fn with_heuristic_alerts(mut self, alerts: HeuristicAlerts) -> Self {
+        tracing::info!("Heuristic alerts: {:?} (flags 0x{:x})", alerts, alerts.flags());
+        let mut hasher = Fnv1a::resume(&self.info.fingerprint);
+        hasher.write(&format!("{:?}", alerts));
+        self.info.fingerprint = hasher.finish();
+        self.heuristic_alerts = alerts;
+        self
+    }
+
+    /// 引擎加载信息
+    pub fn info(&self) -> &EngineInfo {
+        &self.info
+    }
+
+    /// 试编译单个签名文件，校验其语法是否能被 libclamav 接受
+    ///
+    /// 使用独立的临时引擎加载并编译，不影响当前正在使用的引擎
+    ///
+    /// # 返回
+    /// - Ok(签名数量) 或 Err(错误信息)
+    pub fn validate_signatures(path: &str) -> Result<u32, ClamAVError> {
+        library()?;
+        unsafe {
+            let ret = cl_init(0);
+            if ret != CL_SUCCESS && ret != CL_CLEAN {
+                return Err(ClamAVError::InitializationFailed(
+                    format!("cl_init failed with code: {}", ret)
+                ));
+            }
+
+            let engine = cl_engine_new();
+            if engine.is_null() {
+                return Err(ClamAVError::EngineCreationFailed(
+                    "cl_engine_new returned null".to_string()
+                ));
+            }
+
+            let result = load_database(engine, path, CL_DB_STDOPT as c_uint).and_then(|signo| {
+                let ret = cl_engine_compile(engine);
+                if ret != CL_SUCCESS {
+                    return Err(ClamAVError::EngineCompilationFailed(
+                        format!("cl_engine_compile failed with code: {}", ret)
+                    ));
+                }
+                Ok(signo)
+            });
+
+            cl_engine_free(engine);
+            result
+        }
+    }
+
     /// 扫描单个文件
     ///
+    /// 以 O_NONBLOCK 打开并 fstat 确认是普通文件后通过 cl_scandesc_ex 扫描，
+    /// 路径被替换为 FIFO 或设备文件时不会阻塞扫描线程
+    ///
     /// # 参数
     /// - path: 文件路径
     /// - options: 扫描选项
@@ -343,105 +1235,422 @@ impl ClamAVEngine {
             return Err(ClamAVError::ScanFailed("Engine not initialized".to_string()));
         }
 
+        let (file, _) = open_regular(Path::new(path), true)
+            .map_err(|e| ClamAVError::from_io(path, &e))?;
+        self.scan_descriptor(file.as_raw_fd(), Some(path), options)
+    }
+
+    /// 扫描内存缓冲区
+    ///
+    /// 通过 cl_fmap_open_memory 映射缓冲区后调用 cl_scanmap_ex，无需写入临时文件
+    ///
+    /// # 参数
+    /// - data: 待扫描的数据
+    /// - options: 扫描选项
+    pub fn scan_bytes(&self, data: &[u8], options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        if !self.initialized {
+            return Err(ClamAVError::ScanFailed("Engine not initialized".to_string()));
+        }
+
+        let name = format!("<memory:{} bytes>", data.len());
+
         unsafe {
-            let path_cstr = CString::new(path).unwrap_or_else(|_| {
-                CString::new("<invalid>").unwrap()
+            let map = cl_fmap_open_memory(data.as_ptr() as *const c_void, data.len());
+            if map.is_null() {
+                return Err(ClamAVError::ScanFailed(
+                    "cl_fmap_open_memory returned null".to_string()
+                ));
+            }
+
+            tracing::debug!("Calling cl_scanmap_ex for: {}", name);
+
+            let result = self.run_scan(&name, "cl_scanmap_ex", options, |call| {
+                cl_scanmap_ex(
+                    map,
+                    ptr::null(),      // filename
+                    &mut call.verdict,
+                    &mut call.last_alert,
+                    &mut call.scanned,
+                    self.engine,
+                    &call.scan_opts,
+                    &mut call.context as *mut ScanContext as *mut c_void,  // context
+                    ptr::null(),      // hash_hint
+                    &mut call.hash_out,
+                    call.hash_alg.as_ptr(),
+                    ptr::null(),      // file_type_hint
+                    &mut call.file_type_out,
+                )
             });
 
-            // 构建扫描选项 - 正确初始化所有 5 个字段
-            // ClamAV 推荐将 parse 设置为 !0 以启用所有解析器
-            let mut scan_opts = cl_scan_options {
-                general: 0,
-                parse: !0,  // 启用所有解析器（ClamAV 推荐方式）
-                heuristic: 0,
-                mail: 0,
-                dev: 0,
-            };
+            cl_fmap_close(map);
+            result
+        }
+    }
 
-            if options.heuristics {
-                scan_opts.general |= CL_SCAN_GENERAL_HEURISTICS;
-            }
+    /// 扫描已打开的文件描述符
+    ///
+    /// 文件描述符由调用方持有，扫描完成后不会被关闭
+    ///
+    /// # 参数
+    /// - fd: 文件描述符
+    /// - options: 扫描选项
+    pub fn scan_fd(&self, fd: RawFd, options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        self.scan_descriptor(fd, None, options)
+    }
 
-            // 启用 all-match 模式以确保检测所有威胁
-            scan_opts.general |= CL_SCAN_GENERAL_ALLMATCHES;
+    /// 通过 cl_scandesc_ex 扫描已打开的文件描述符
+    ///
+    /// # 参数
+    /// - fd: 文件描述符（由调用方持有，扫描完成后不会被关闭）
+    /// - path: 文件路径，用于扫描结果和日志（None 时使用 <fd:N>）
+    /// - options: 扫描选项
+    pub fn scan_descriptor(&self, fd: RawFd, path: Option<&str>, options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        if !self.initialized {
+            return Err(ClamAVError::ScanFailed("Engine not initialized".to_string()));
+        }
 
-            let mut verdict: cl_verdict_t = cl_verdict_t::CL_VERDICT_NOTHING_FOUND;
-            let mut last_alert: *const c_char = ptr::null();
-            let mut scanned: u64 = 0;
+        let name = path.map(str::to_string).unwrap_or_else(|| format!("<fd:{}>", fd));
+        let filename = path.and_then(|p| CString::new(p).ok());
 
-            tracing::debug!("Calling cl_scanfile_ex for: {}", path);
+        tracing::debug!("Calling cl_scandesc_ex for: {}", name);
 
-            let ret = cl_scanfile_ex(
-                path_cstr.as_ptr(),
-                &mut verdict,
-                &mut last_alert,
-                &mut scanned,
+        self.run_scan(&name, "cl_scandesc_ex", options, |call| unsafe {
+            cl_scandesc_ex(
+                fd,
+                filename.as_ref().map_or(ptr::null(), |f| f.as_ptr()),  // filename
+                &mut call.verdict,
+                &mut call.last_alert,
+                &mut call.scanned,
                 self.engine,
-                &scan_opts,
-                ptr::null_mut(),  // context
+                &call.scan_opts,
+                &mut call.context as *mut ScanContext as *mut c_void,  // context
                 ptr::null(),      // hash_hint
-                ptr::null_mut(),  // hash_out
-                ptr::null(),      // hash_alg
+                &mut call.hash_out,
+                call.hash_alg.as_ptr(),
                 ptr::null(),      // file_type_hint
-                ptr::null_mut(),  // file_type_out
-            );
-
-            tracing::debug!("cl_scanfile_ex returned: {}, verdict: {:?} (raw value: {})", ret, verdict, verdict as i32);
-
-            // 重要：cl_scanfile_ex 不会返回 CL_VIRUS，需要检查 verdict_out 参数！
-            // CL_VERDICT_STRONG_INDICATOR = 2 表示检测到病毒/恶意软件
-            // CL_VERDICT_POTENTIALLY_UNWANTED = 3 表示检测到潜在不受欢迎程序
-
-            // 使用整数值进行比较，避免 enum 匹配问题
-            let verdict_value = verdict as i32;
-            tracing::info!("VERDICT CHECK: value={}, STRONG_INDICATOR=2, POTENTIALLY_UNWANTED=3", verdict_value);
-
-            if verdict_value == 2 || verdict_value == 3 {
-                // 病毒/恶意软件检测到
-                tracing::warn!("VIRUS DETECTED! verdict={}", verdict_value);
-                let virus = if !last_alert.is_null() {
-                    let virus_name = CStr::from_ptr(last_alert).to_string_lossy().to_string();
-                    tracing::warn!("VIRUS FOUND in {}: {}", path, virus_name);
-                    Some(virus_name)
+                &mut call.file_type_out,
+            )
+        })
+    }
+
+    /// 执行一次扫描调用并统一处理扫描结论
+    ///
+    /// # 参数
+    /// - name: 扫描对象名称（用于日志和 ScanResult.filename）
+    /// - api: 实际调用的 libclamav 函数名（用于错误信息）
+    /// - options: 扫描选项
+    /// - scan: 执行具体 libclamav 扫描函数的闭包
+    fn run_scan<F>(&self, name: &str, api: &str, options: ScanOptions, scan: F) -> Result<ScanResult, ClamAVError>
+    where
+        F: FnOnce(&mut ScanCall) -> cl_error_t,
+    {
+        // 引擎关闭启发式扫描时覆盖扫描选项
+        let mut options = options;
+        options.heuristics &= self.info.settings.heuristic_scan;
+
+        let mut call = ScanCall::new(options);
+        // 启发式告警类别由引擎配置决定，扫描选项关闭启发式时一并关闭
+        if options.heuristics {
+            call.scan_opts.heuristic = self.heuristic_alerts.flags();
+        }
+        let ret = scan(&mut call);
+
+        // 无论扫描结果如何都需要释放输出字符串
+        let (file_hash, file_type) = unsafe {
+            (take_clamav_string(call.hash_out), take_clamav_string(call.file_type_out))
+        };
+
+        tracing::debug!("{} returned: {}, verdict: {:?} (raw value: {})", api, ret, call.verdict, call.verdict as i32);
+
+        // 重要：扫描函数不会返回 CL_VIRUS，需要检查 verdict_out 参数！
+        // CL_VERDICT_STRONG_INDICATOR = 2 表示检测到病毒/恶意软件
+        // CL_VERDICT_POTENTIALLY_UNWANTED = 3 表示检测到潜在不受欢迎程序
+
+        // 使用整数值进行比较，避免 enum 匹配问题
+        let verdict_value = call.verdict as i32;
+        tracing::info!("VERDICT CHECK: value={}, STRONG_INDICATOR=2, POTENTIALLY_UNWANTED=3", verdict_value);
+
+        if verdict_value == 2 || verdict_value == 3 {
+            // 病毒/恶意软件检测到
+            tracing::warn!("VIRUS DETECTED! verdict={}", verdict_value);
+
+            // 优先使用回调收集到的全部匹配签名，回调未触发时回退到 last_alert
+            let last_alert = call.last_alert;
+            let (mut virus_names, mut container_paths) = call.context.into_alerts();
+            if virus_names.is_empty() {
+                let virus_name = if !last_alert.is_null() {
+                    unsafe { CStr::from_ptr(last_alert).to_string_lossy().to_string() }
                 } else {
-                    tracing::warn!("VIRUS FOUND in {}: Unknown", path);
-                    Some("Unknown".to_string())
+                    "Unknown".to_string()
                 };
+                virus_names.push(virus_name);
+                container_paths.push(None);
+            }
+            tracing::warn!("VIRUS FOUND in {}: {}", name, virus_names.join(", "));
 
+            Ok(ScanResult {
+                filename: name.to_string(),
+                virus_names,
+                is_infected: true,
+                file_hash,
+                file_type,
+                container_paths,
+            })
+        } else if verdict_value == 1 {
+            // 受信任文件
+            tracing::debug!("File trusted: {}", name);
+            Ok(ScanResult {
+                filename: name.to_string(),
+                virus_names: Vec::new(),
+                is_infected: false,
+                file_hash,
+                file_type,
+                container_paths: Vec::new(),
+            })
+        } else {
+            // 没有发现威胁 (verdict_value == 0) 或其他情况
+            // 检查返回码是否有错误
+            if ret != CL_CLEAN && ret != CL_SUCCESS {
+                let error = refine_open_error(ClamAVError::from_code(api, ret), name);
+                tracing::warn!("{} failed for {}: {}", api, name, error);
+                Err(error)
+            } else {
+                tracing::debug!("File clean: {}", name);
                 Ok(ScanResult {
-                    filename: path.to_string(),
-                    virus_name: virus,
-                    is_infected: true,
-                })
-            } else if verdict_value == 1 {
-                // 受信任文件
-                tracing::debug!("File trusted: {}", path);
-                Ok(ScanResult {
-                    filename: path.to_string(),
-                    virus_name: None,
+                    filename: name.to_string(),
+                    virus_names: Vec::new(),
                     is_infected: false,
+                    file_hash,
+                    file_type,
+                    container_paths: Vec::new(),
                 })
-            } else {
-                // 没有发现威胁 (verdict_value == 0) 或其他情况
-                // 检查返回码是否有错误
-                if ret != CL_CLEAN && ret != CL_SUCCESS {
-                    tracing::error!("cl_scanfile_ex failed for {}: error code {}", path, ret);
-                    Err(ClamAVError::ScanFailed(
-                        format!("cl_scanfile_ex failed with code: {}", ret)
-                    ))
-                } else {
-                    tracing::debug!("File clean: {}", path);
-                    Ok(ScanResult {
-                        filename: path.to_string(),
-                        virus_name: None,
-                        is_infected: false,
-                    })
-                }
             }
         }
     }
 }
 
+/// 使用 libclamav 校验单个数据库文件（cl_cvdhead + cl_cvdverify）
+///
+/// 返回拒绝原因；用作 database::verify_database_dir 的校验函数
+pub fn verify_database_file(path: &Path) -> Result<(), String> {
+    library().map_err(|e| e.to_string())?;
+    let path_cstr = CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|_| "invalid file name".to_string())?;
+
+    unsafe {
+        let head = cl_cvdhead(path_cstr.as_ptr());
+        if head.is_null() {
+            return Err("cl_cvdhead could not parse database header".to_string());
+        }
+        let flevel = (*head).fl;
+        cl_cvdfree(head);
+
+        if flevel > cl_retflevel() {
+            tracing::warn!("{} requires functionality level {} (libclamav has {})",
+                           path.display(), flevel, cl_retflevel());
+        }
+
+        let ret = cl_cvdverify(path_cstr.as_ptr());
+        if ret != CL_SUCCESS {
+            return Err(format!("cl_cvdverify failed: {} (code {})", strerror(ret), ret));
+        }
+    }
+
+    Ok(())
+}
+
+/// 校验病毒库目录中的单个签名文件（.ndb/.hdb 等）：在临时引擎中加载并编译
+///
+/// 返回拒绝原因；用作 database::verify_database_dir 的签名文件校验函数
+pub fn verify_signature_file(path: &Path) -> Result<(), String> {
+    ClamAVEngine::validate_signatures(&path.to_string_lossy())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// 病毒库目录变化检测（基于 cl_statinidir / cl_statchkdir）
+pub struct DatabaseStat {
+    stat: Box<cl_stat>,
+}
+
+impl DatabaseStat {
+    /// 记录目录当前状态
+    pub fn new(db_dir: &str) -> Result<Self, ClamAVError> {
+        library()?;
+        let dir_cstr = CString::new(db_dir).map_err(|_| {
+            ClamAVError::InvalidPath(db_dir.to_string())
+        })?;
+
+        let mut stat = Box::new(cl_stat {
+            dir: ptr::null_mut(),
+            stattab: ptr::null_mut(),
+            statdname: ptr::null_mut(),
+            entries: 0,
+        });
+
+        let ret = unsafe { cl_statinidir(dir_cstr.as_ptr(), &mut *stat) };
+        if ret != CL_SUCCESS {
+            unsafe { cl_statfree(&mut *stat) };
+            return Err(ClamAVError::DatabaseLoadFailed(
+                format!("cl_statinidir({}) failed with code: {}", db_dir, ret)
+            ));
+        }
+
+        Ok(Self { stat })
+    }
+
+    /// 自记录以来目录中的数据库文件是否发生变化
+    pub fn has_changed(&self) -> Result<bool, ClamAVError> {
+        let ret = unsafe { cl_statchkdir(&*self.stat) };
+        match ret {
+            0 => Ok(false),
+            1 => Ok(true),
+            code => Err(ClamAVError::DatabaseLoadFailed(
+                format!("cl_statchkdir failed with code: {}", code)
+            )),
+        }
+    }
+}
+
+impl Drop for DatabaseStat {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = cl_statfree(&mut *self.stat);
+        }
+    }
+}
+
+/// 自定义签名库支持的文件扩展名
+pub const CUSTOM_SIG_EXTENSIONS: &[&str] = &["hdb", "hsb", "ndb", "ldb", "yar", "ign2"];
+
+/// 判断文件名是否为受支持的自定义签名文件
+pub fn is_custom_signature_file(name: &str) -> bool {
+    std::path::Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| CUSTOM_SIG_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// 稳定的 64 位 FNV-1a 哈希（指纹保存在数据库中，不能使用每次构建可能变化的 DefaultHasher）
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// 在已有指纹的基础上继续计算
+    fn resume(fingerprint: &str) -> Self {
+        u64::from_str_radix(fingerprint, 16).map(Self).unwrap_or_else(|_| Self::new())
+    }
+
+    fn write(&mut self, data: &str) {
+        // 以 0 分隔字段，避免相邻字段拼接后相同
+        for byte in data.bytes().chain(std::iter::once(0)) {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// 计算引擎配置指纹：自定义签名文件（路径、大小、修改时间）和签名数、功能级别、加载选项、资源限制、引擎设置
+fn engine_fingerprint(
+    custom_files: &[String],
+    custom_signatures: u32,
+    functionality_level: u32,
+    db_options: &DatabaseOptions,
+    limits: &EngineLimits,
+    settings: &EngineSettings,
+) -> String {
+    let mut hasher = Fnv1a::new();
+    for file in custom_files {
+        let (size, mtime_ns) = std::fs::metadata(file)
+            .map(|m| {
+                use std::os::unix::fs::MetadataExt;
+                (m.size(), m.mtime() * 1_000_000_000 + m.mtime_nsec())
+            })
+            .unwrap_or_default();
+        hasher.write(&format!("{}:{}:{}", file, size, mtime_ns));
+    }
+    hasher.write(&custom_signatures.to_string());
+    hasher.write(&functionality_level.to_string());
+    hasher.write(&format!("{:x}:{:?}:{:?}", db_options.db_flags(), db_options.pua_categories(), db_options));
+    hasher.write(&format!("{:?}", limits));
+    hasher.write(&format!("{:?}", settings));
+    hasher.finish()
+}
+
+/// 列出目录中的自定义签名文件（按文件名排序，保证加载顺序稳定）
+fn custom_signature_files(dir: &str) -> Vec<String> {
+    let mut files: Vec<String> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+            .filter(|e| is_custom_signature_file(&e.file_name().to_string_lossy()))
+            .map(|e| e.path().to_string_lossy().to_string())
+            .collect(),
+        Err(e) => {
+            tracing::debug!("Custom signature directory {} not readable: {}", dir, e);
+            Vec::new()
+        }
+    };
+    files.sort();
+    files
+}
+
+/// 调用 cl_load 加载数据库文件或目录
+///
+/// # 返回
+/// - Ok(本次加载的签名数量) 或 Err(错误信息)
+unsafe fn load_database(engine: *mut cl_engine, path: &str, dboptions: c_uint) -> Result<u32, ClamAVError> {
+    let path_cstr = CString::new(path).map_err(|_| {
+        ClamAVError::InvalidPath(path.to_string())
+    })?;
+    let mut signo: c_uint = 0;
+    let ret = cl_load(path_cstr.as_ptr(), engine, &mut signo, dboptions);
+    if ret != CL_SUCCESS {
+        return Err(ClamAVError::DatabaseLoadFailed(
+            format!("cl_load({}) failed with code: {}", path, ret)
+        ));
+    }
+    Ok(signo)
+}
+
+/// 单次扫描调用的输入/输出参数
+struct ScanCall {
+    scan_opts: cl_scan_options,
+    verdict: cl_verdict_t,
+    last_alert: *const c_char,
+    scanned: u64,
+    /// 回调上下文：收集本次扫描的所有匹配签名
+    context: ScanContext,
+    /// 请求 libclamav 计算文件哈希并返回识别的文件类型
+    hash_alg: CString,
+    hash_out: *mut c_char,
+    file_type_out: *mut c_char,
+}
+
+impl ScanCall {
+    fn new(options: ScanOptions) -> Self {
+        Self {
+            // 构建扫描选项 - 根据 ScanOptions 生成解析器位掩码
+            scan_opts: options.to_cl_scan_options(),
+            verdict: cl_verdict_t::CL_VERDICT_NOTHING_FOUND,
+            last_alert: ptr::null(),
+            scanned: 0,
+            context: ScanContext::default(),
+            hash_alg: CString::new(SCAN_HASH_ALG).unwrap(),
+            hash_out: ptr::null_mut(),
+            file_type_out: ptr::null_mut(),
+        }
+    }
+}
+
 // 实现 Drop trait 确保引擎资源被正确释放
 impl Drop for ClamAVEngine {
     fn drop(&mut self) {
@@ -452,3 +1661,296 @@ impl Drop for ClamAVEngine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_fingerprint_tracks_detection_settings() {
+        let dir = std::env::temp_dir().join(format!("fingerprint-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sigs = dir.join("local.hdb").to_string_lossy().to_string();
+        std::fs::write(&sigs, "44d88612fea8a8f36de82e1278abb02f:68:Eicar-Test\n").unwrap();
+
+        let fingerprint = |files: &[String], limits: &EngineLimits, db_options: &DatabaseOptions| {
+            engine_fingerprint(files, 1, 200, db_options, limits, &EngineSettings::default())
+        };
+        let base = fingerprint(&[sigs.clone()], &EngineLimits::default(), &DatabaseOptions::default());
+        assert_eq!(base, fingerprint(&[sigs.clone()], &EngineLimits::default(), &DatabaseOptions::default()));
+
+        // 自定义签名列表、资源限制、加载选项变化后指纹变化
+        assert_ne!(base, fingerprint(&[], &EngineLimits::default(), &DatabaseOptions::default()));
+        let limits = EngineLimits { max_recursion: 5, ..Default::default() };
+        assert_ne!(base, fingerprint(&[sigs.clone()], &limits, &DatabaseOptions::default()));
+        let db_options = DatabaseOptions { detect_pua: true, ..Default::default() };
+        assert_ne!(base, fingerprint(&[sigs.clone()], &EngineLimits::default(), &db_options));
+
+        // 签名文件被替换（大小变化）
+        std::fs::write(&sigs, "44d88612fea8a8f36de82e1278abb02f:68:Eicar-Test\nffffffffffffffffffffffffffffffff:1:Other\n").unwrap();
+        assert_ne!(base, fingerprint(&[sigs.clone()], &EngineLimits::default(), &DatabaseOptions::default()));
+
+        // 启发式告警类别在指纹基础上继续计算
+        let mut hasher = Fnv1a::resume(&base);
+        hasher.write("alerts");
+        assert_ne!(hasher.finish(), base);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_flags_default_enables_all() {
+        let opts = ScanOptions::default();
+        assert_eq!(opts.parse_flags(), !0u32);
+    }
+
+    #[test]
+    fn test_parse_flags_disable_archive() {
+        let opts = ScanOptions {
+            scan_archive: false,
+            scan_pdf: false,
+            ..Default::default()
+        };
+        let parse = opts.parse_flags();
+        assert_eq!(parse & CL_SCAN_PARSE_ARCHIVE, 0);
+        assert_eq!(parse & CL_SCAN_PARSE_PDF, 0);
+        assert_eq!(parse & CL_SCAN_PARSE_PE, CL_SCAN_PARSE_PE);
+    }
+
+    #[test]
+    fn test_scan_context_dedup_alerts() {
+        let context = ScanContext {
+            alerts: vec![
+                "Eicar-Test-Signature".to_string(),
+                "Win.Trojan.Agent".to_string(),
+                "Eicar-Test-Signature".to_string(),
+            ],
+            ..Default::default()
+        };
+        let (alerts, containers) = context.into_alerts();
+        assert_eq!(
+            alerts,
+            vec!["Eicar-Test-Signature".to_string(), "Win.Trojan.Agent".to_string()]
+        );
+        assert_eq!(containers, vec![None, None]);
+    }
+
+    #[test]
+    fn test_file_inspection_records_container_chain() {
+        let mut context = ScanContext::default();
+        let ctx_ptr = &mut context as *mut ScanContext as *mut c_void;
+        let outer = CString::new("/backup/photos.zip").unwrap();
+        let inner = CString::new("/tmp/clamav-1234/inner.rar").unwrap();
+        let member = CString::new("evil.exe").unwrap();
+        let virname = CString::new("Win.Trojan.Agent").unwrap();
+
+        // 顶层文件命中：无成员路径
+        file_inspection_callback(-1, ptr::null(), ptr::null(), 0, outer.as_ptr(), 0, ptr::null(), 0, 0, ctx_ptr);
+        virus_found_callback(-1, virname.as_ptr(), ctx_ptr);
+
+        // photos.zip/inner.rar/evil.exe
+        let ancestors = [outer.as_ptr(), inner.as_ptr()];
+        file_inspection_callback(-1, ptr::null(), ancestors.as_ptr(), 0, member.as_ptr(), 0, ptr::null(), 2, 0, ctx_ptr);
+        let other = CString::new("Win.Test.Nested").unwrap();
+        virus_found_callback(-1, other.as_ptr(), ctx_ptr);
+        virus_found_callback(-1, other.as_ptr(), ctx_ptr);
+
+        // 同一签名命中另一个成员：photos.zip/inner.rar/dropper.exe
+        let sibling = CString::new("dropper.exe").unwrap();
+        file_inspection_callback(-1, ptr::null(), ancestors.as_ptr(), 0, sibling.as_ptr(), 0, ptr::null(), 2, 0, ctx_ptr);
+        virus_found_callback(-1, other.as_ptr(), ctx_ptr);
+
+        // 名称缺失的成员
+        let unnamed = [outer.as_ptr()];
+        file_inspection_callback(-1, ptr::null(), unnamed.as_ptr(), 0, ptr::null(), 0, ptr::null(), 1, 0, ctx_ptr);
+        assert_eq!(context.container_path().as_deref(), Some("<unnamed>"));
+
+        let (alerts, containers) = context.into_alerts();
+        assert_eq!(alerts, vec![
+            "Win.Trojan.Agent".to_string(),
+            "Win.Test.Nested".to_string(),
+            "Win.Test.Nested".to_string(),
+        ]);
+        assert_eq!(containers, vec![
+            None,
+            Some("inner.rar/evil.exe".to_string()),
+            Some("inner.rar/dropper.exe".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_container_alert_after_member_uses_container_path() {
+        let mut context = ScanContext::default();
+        let ctx_ptr = &mut context as *mut ScanContext as *mut c_void;
+        let outer = CString::new("/backup/photos.zip").unwrap();
+        let inner = CString::new("inner.rar").unwrap();
+        let member = CString::new("evil.exe").unwrap();
+        let ancestors = [outer.as_ptr(), inner.as_ptr()];
+
+        file_inspection_callback(3, ptr::null(), ptr::null(), 0, outer.as_ptr(), 0, ptr::null(), 0, 0, ctx_ptr);
+        file_inspection_callback(4, ptr::null(), ancestors.as_ptr(), 0, inner.as_ptr(), 0, ptr::null(), 1, 0, ctx_ptr);
+        file_inspection_callback(5, ptr::null(), ancestors.as_ptr(), 0, member.as_ptr(), 0, ptr::null(), 2, 0, ctx_ptr);
+        let nested = CString::new("Win.Trojan.Agent").unwrap();
+        virus_found_callback(5, nested.as_ptr(), ctx_ptr);
+
+        // 成员扫描完成后，内层压缩包本身命中
+        post_scan_callback(5, CL_CLEAN as c_int, ptr::null(), ctx_ptr);
+        let archive = CString::new("Heuristics.Encrypted.RAR").unwrap();
+        virus_found_callback(4, archive.as_ptr(), ctx_ptr);
+
+        // 内层压缩包扫描完成后，顶层文件命中
+        post_scan_callback(4, CL_CLEAN as c_int, ptr::null(), ctx_ptr);
+        let top = CString::new("Heuristics.Limits.Exceeded").unwrap();
+        virus_found_callback(3, top.as_ptr(), ctx_ptr);
+
+        // 不属于当前层的扫描后回调不弹出外层
+        post_scan_callback(9, CL_CLEAN as c_int, ptr::null(), ctx_ptr);
+        assert_eq!(context.layers.len(), 1);
+
+        let (_, containers) = context.into_alerts();
+        assert_eq!(containers, vec![
+            Some("inner.rar/evil.exe".to_string()),
+            Some("inner.rar".to_string()),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn test_virus_found_callback_collects() {
+        let mut context = ScanContext::default();
+        let name = CString::new("Eicar-Test-Signature").unwrap();
+        virus_found_callback(-1, name.as_ptr(), &mut context as *mut ScanContext as *mut c_void);
+        virus_found_callback(-1, ptr::null(), &mut context as *mut ScanContext as *mut c_void);
+        assert_eq!(context.alerts, vec!["Eicar-Test-Signature".to_string()]);
+    }
+
+    #[test]
+    fn test_scan_result_threats_carry_hash() {
+        let result = ScanResult {
+            filename: "/tmp/eicar.com".to_string(),
+            virus_names: vec!["Eicar-Test-Signature".to_string(), "Win.Test.Other".to_string()],
+            is_infected: true,
+            file_hash: Some("275a021bbfb6489e54d471899f7db9d1663fc695ec2fe2a2c4538aabf651fd0f".to_string()),
+            file_type: Some("CL_TYPE_TEXT_ASCII".to_string()),
+            container_paths: vec![None, Some("inner.zip/tool.exe".to_string())],
+        };
+        let threats = result.threats();
+        assert_eq!(threats.len(), 2);
+        assert_eq!(threats[1].virus_name, VirusName("Win.Test.Other".to_string()));
+        assert_eq!(threats[1].file_hash, result.file_hash);
+        assert_eq!(threats[0].file_type.as_deref(), Some("CL_TYPE_TEXT_ASCII"));
+        assert_eq!(threats[0].container_path, None);
+        assert_eq!(threats[1].container_path.as_deref(), Some("inner.zip/tool.exe"));
+    }
+
+    #[test]
+    fn test_scan_uninitialized_engine_fails() {
+        let engine = ClamAVEngine::uninitialized();
+        assert!(engine.scan_bytes(b"X5O!P%@AP", ScanOptions::default()).is_err());
+        assert!(engine.scan_fd(0, ScanOptions::default()).is_err());
+        assert!(engine.scan_file("/tmp/none", ScanOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_is_custom_signature_file() {
+        assert!(is_custom_signature_file("local.hdb"));
+        assert!(is_custom_signature_file("rules.YAR"));
+        assert!(is_custom_signature_file("allow.ign2"));
+        assert!(!is_custom_signature_file("daily.cvd"));
+        assert!(!is_custom_signature_file("notes.txt"));
+        assert!(!is_custom_signature_file("hdb"));
+    }
+
+    #[test]
+    fn test_database_options_default_matches_stdopt() {
+        let options = DatabaseOptions::default();
+        assert_eq!(options.db_flags(), CL_DB_STDOPT);
+        assert_eq!(options.pua_categories(), None);
+    }
+
+    #[test]
+    fn test_database_options_pua_categories() {
+        let mut options = DatabaseOptions {
+            detect_pua: true,
+            pua_include: vec!["Win".to_string(), "Packed".to_string()],
+            phishing_urls: false,
+            ..Default::default()
+        };
+        let flags = options.db_flags();
+        assert_ne!(flags & CL_DB_PUA, 0);
+        assert_ne!(flags & CL_DB_PUA_INCLUDE, 0);
+        assert_eq!(flags & CL_DB_PHISHING_URLS, 0);
+        assert_eq!(options.pua_categories().as_deref(), Some(".Win.Packed."));
+
+        // 排除列表优先于包含列表
+        options.pua_exclude = vec!["Tool".to_string()];
+        let flags = options.db_flags();
+        assert_ne!(flags & CL_DB_PUA_EXCLUDE, 0);
+        assert_eq!(flags & CL_DB_PUA_INCLUDE, 0);
+        assert_eq!(options.pua_categories().as_deref(), Some(".Tool."));
+
+        // 未启用 PUA 时忽略类别
+        options.detect_pua = false;
+        assert_eq!(options.db_flags() & (CL_DB_PUA | CL_DB_PUA_EXCLUDE), 0);
+        assert_eq!(options.pua_categories(), None);
+    }
+
+    #[test]
+    fn test_error_category_mapping() {
+        assert_eq!(error_category(CL_EACCES), ErrorCategory::Permission);
+        assert_eq!(error_category(CL_EREAD), ErrorCategory::Io);
+        assert_eq!(error_category(CL_EMAXSIZE), ErrorCategory::Limits);
+        assert_eq!(error_category(CL_EFORMAT), ErrorCategory::Parse);
+        assert_eq!(error_category(CL_ETIMEOUT), ErrorCategory::Timeout);
+        assert_eq!(error_category(31), ErrorCategory::Other);
+
+        let error = ClamAVError::scan_error(CL_EMAXREC, "limit".to_string());
+        assert_eq!(error.code(), Some(CL_EMAXREC));
+        assert_eq!(error.category(), ErrorCategory::Limits);
+        assert_eq!(ClamAVError::ScanFailed("x".to_string()).code(), None);
+    }
+
+    #[test]
+    fn test_refine_open_error_missing_file() {
+        let error = ClamAVError::scan_error(CL_EOPEN, "open".to_string());
+        let refined = refine_open_error(error, "/nonexistent/file");
+        assert_eq!(refined.category(), ErrorCategory::Io);
+        assert_eq!(refined.code(), Some(CL_EOPEN));
+    }
+
+    #[test]
+    fn test_to_cl_scan_options_heuristics() {
+        let opts = ScanOptions {
+            heuristics: false,
+            ..Default::default()
+        };
+        let cl_opts = opts.to_cl_scan_options();
+        assert_eq!(cl_opts.general & CL_SCAN_GENERAL_HEURISTICS, 0);
+        assert_eq!(cl_opts.general & CL_SCAN_GENERAL_ALLMATCHES, CL_SCAN_GENERAL_ALLMATCHES);
+    }
+
+    #[test]
+    fn test_engine_settings_default_matches_config() {
+        let settings = crate::models::config::ClamAVConfig::default().engine_settings();
+        assert_eq!(settings, EngineSettings::default());
+        assert!(settings.bytecode_trusted_only);
+        assert!(settings.cache_enabled);
+    }
+
+    #[test]
+    fn test_heuristic_alert_flags() {
+        assert_eq!(HeuristicAlerts::default().flags(), 0);
+
+        let alerts = HeuristicAlerts {
+            encrypted_archive: true,
+            structured_cc: true,
+            ..Default::default()
+        };
+        let flags = alerts.flags();
+        assert_eq!(flags & CL_SCAN_HEURISTIC_ENCRYPTED_ARCHIVE, CL_SCAN_HEURISTIC_ENCRYPTED_ARCHIVE);
+        assert_eq!(flags & CL_SCAN_HEURISTIC_ENCRYPTED_DOC, 0);
+        assert_eq!(flags & CL_SCAN_HEURISTIC_STRUCTURED, CL_SCAN_HEURISTIC_STRUCTURED);
+        assert_eq!(flags & CL_SCAN_HEURISTIC_STRUCTURED_CC, CL_SCAN_HEURISTIC_STRUCTURED_CC);
+        assert_eq!(flags & CL_SCAN_HEURISTIC_STRUCTURED_SSN_NORMAL, 0);
+    }
+}
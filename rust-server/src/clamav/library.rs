@@ -0,0 +1,253 @@
+// libclamav 运行时加载
+//
+// 不在构建时链接 libclamav，而是在启动时按 ClamAVConfig.lib_path 用 libloading 打开：
+// - 解析全部所需符号，缺少任何一个都视为加载失败并列出缺失的符号
+// - 加载成功后全局只保留一份函数表，供 ffi.rs 中的封装函数调用
+// - 加载失败不缓存，修复库文件后重新初始化引擎即可重试
+
+use std::os::raw::{c_char, c_int, c_longlong, c_uint, c_void};
+use std::sync::{Mutex, OnceLock};
+
+use super::ffi::{
+    cl_cvd, cl_engine, cl_engine_field, cl_error_t, cl_fmap_t, cl_scan_options, cl_stat, cl_verdict_t,
+    clcb_file_inspection, clcb_post_scan, clcb_virus_found, ClamAVError,
+};
+
+/// 未配置 lib_path 或配置的路径加载失败时依次尝试的库名（由动态链接器按系统路径查找）
+pub const DEFAULT_LIBRARY_NAMES: &[&str] = &["libclamav.so.12", "libclamav.so"];
+
+/// 已加载的 libclamav
+static LIBRARY: OnceLock<ClamAVLibrary> = OnceLock::new();
+
+/// 串行化加载过程
+static LOAD_LOCK: Mutex<()> = Mutex::new(());
+
+/// 声明 libclamav 函数表：生成 ClamAVLibrary 的字段、符号解析和同名封装函数
+macro_rules! clamav_api {
+    ($(
+        $(#[$meta:meta])*
+        fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;
+    )*) => {
+        /// 运行时加载的 libclamav 函数表
+        pub struct ClamAVLibrary {
+            path: String,
+            $( $name: unsafe extern "C" fn($($ty),*) $(-> $ret)?, )*
+            // 必须最后释放：函数指针依赖库保持加载
+            _library: libloading::Library,
+        }
+
+        impl ClamAVLibrary {
+            /// 解析全部所需符号，返回缺失的符号列表
+            unsafe fn resolve(path: String, library: libloading::Library) -> Result<Self, Vec<&'static str>> {
+                let mut missing = Vec::new();
+                $(
+                    let $name = library
+                        .get::<unsafe extern "C" fn($($ty),*) $(-> $ret)?>(concat!(stringify!($name), "\0").as_bytes())
+                        .map(|symbol| *symbol)
+                        .map_err(|_| missing.push(stringify!($name)))
+                        .ok();
+                )*
+
+                if !missing.is_empty() {
+                    return Err(missing);
+                }
+
+                Ok(Self {
+                    path,
+                    $( $name: $name.unwrap(), )*
+                    _library: library,
+                })
+            }
+        }
+
+        $(
+            $(#[$meta])*
+            #[allow(clippy::too_many_arguments)]
+            pub(super) unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+                (api().$name)($($arg),*)
+            }
+        )*
+    };
+}
+
+clamav_api! {
+    /// 初始化 ClamAV 库
+    fn cl_init(initoptions: c_uint) -> cl_error_t;
+
+    /// 创建新的扫描引擎
+    fn cl_engine_new() -> *mut cl_engine;
+
+    /// 设置引擎字符串选项
+    fn cl_engine_set_str(engine: *mut cl_engine, field: cl_engine_field, str: *const c_char) -> cl_error_t;
+
+    /// 设置引擎数值选项
+    fn cl_engine_set_num(engine: *mut cl_engine, field: cl_engine_field, num: c_longlong) -> cl_error_t;
+
+    /// 获取引擎数值选项
+    fn cl_engine_get_num(engine: *const cl_engine, field: cl_engine_field, err: *mut c_int) -> c_longlong;
+
+    /// 获取错误码的描述信息
+    fn cl_strerror(clerror: cl_error_t) -> *const c_char;
+
+    /// 获取 libclamav 版本字符串
+    fn cl_retver() -> *const c_char;
+
+    /// 获取 libclamav 功能级别
+    fn cl_retflevel() -> c_uint;
+
+    /// 编译扫描引擎
+    fn cl_engine_compile(engine: *mut cl_engine) -> cl_error_t;
+
+    /// 释放扫描引擎
+    fn cl_engine_free(engine: *mut cl_engine) -> cl_error_t;
+
+    /// 注册病毒发现回调
+    fn cl_engine_set_clcb_virus_found(engine: *mut cl_engine, callback: clcb_virus_found);
+
+    /// 注册文件检查回调
+    fn cl_engine_set_clcb_file_inspection(engine: *mut cl_engine, callback: clcb_file_inspection);
+
+    /// 注册扫描后回调
+    fn cl_engine_set_clcb_post_scan(engine: *mut cl_engine, callback: clcb_post_scan);
+
+    /// 加载病毒数据库
+    fn cl_load(path: *const c_char, engine: *mut cl_engine, signo: *mut c_uint, dboptions: c_uint) -> cl_error_t;
+
+    /// 扫描文件描述符（扩展版本）
+    fn cl_scandesc_ex(
+        desc: c_int,
+        filename: *const c_char,
+        verdict_out: *mut cl_verdict_t,
+        last_alert_out: *mut *const c_char,
+        scanned_out: *mut u64,
+        engine: *const cl_engine,
+        scanoptions: *const cl_scan_options,
+        context: *mut c_void,
+        hash_hint: *const c_char,
+        hash_out: *mut *mut c_char,
+        hash_alg: *const c_char,
+        file_type_hint: *const c_char,
+        file_type_out: *mut *mut c_char,
+    ) -> cl_error_t;
+
+    /// 将内存缓冲区映射为 fmap（不复制数据）
+    fn cl_fmap_open_memory(start: *const c_void, len: usize) -> *mut cl_fmap_t;
+
+    /// 关闭 fmap
+    fn cl_fmap_close(map: *mut cl_fmap_t);
+
+    /// 扫描 fmap（扩展版本）
+    fn cl_scanmap_ex(
+        map: *mut cl_fmap_t,
+        filename: *const c_char,
+        verdict_out: *mut cl_verdict_t,
+        last_alert_out: *mut *const c_char,
+        scanned_out: *mut u64,
+        engine: *const cl_engine,
+        scanoptions: *const cl_scan_options,
+        context: *mut c_void,
+        hash_hint: *const c_char,
+        hash_out: *mut *mut c_char,
+        hash_alg: *const c_char,
+        file_type_hint: *const c_char,
+        file_type_out: *mut *mut c_char,
+    ) -> cl_error_t;
+
+    /// 记录病毒库目录中数据库文件的 stat 信息
+    fn cl_statinidir(dirname: *const c_char, dbstat: *mut cl_stat) -> cl_error_t;
+
+    /// 检查病毒库目录是否发生变化（1 = 已变化，0 = 未变化）
+    fn cl_statchkdir(dbstat: *const cl_stat) -> c_int;
+
+    /// 释放 cl_statinidir 分配的资源
+    fn cl_statfree(dbstat: *mut cl_stat) -> cl_error_t;
+
+    /// 读取 CVD/CLD/CUD 文件头
+    fn cl_cvdhead(file: *const c_char) -> *mut cl_cvd;
+
+    /// 释放 cl_cvdhead 返回的结构体
+    fn cl_cvdfree(cvd: *mut cl_cvd);
+
+    /// 完整校验数据库文件（文件头、数字签名和内容）
+    fn cl_cvdverify(file: *const c_char) -> cl_error_t;
+}
+
+impl ClamAVLibrary {
+    /// 打开指定的库文件并解析所需符号
+    pub fn open(path: &str) -> Result<Self, String> {
+        unsafe {
+            let library = libloading::Library::new(path)
+                .map_err(|e| format!("{}: {}", path, e))?;
+            Self::resolve(path.to_string(), library)
+                .map_err(|missing| format!("{}: missing symbols: {}", path, missing.join(", ")))
+        }
+    }
+
+    /// 实际加载的库路径（或库名）
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// 加载 libclamav（已加载时直接返回）
+///
+/// 依次尝试 lib_path 和 DEFAULT_LIBRARY_NAMES，全部失败时返回每个候选的失败原因
+pub fn load_library(lib_path: Option<&str>) -> Result<&'static ClamAVLibrary, ClamAVError> {
+    let _guard = LOAD_LOCK.lock().unwrap();
+
+    if let Some(library) = LIBRARY.get() {
+        if lib_path.is_some_and(|p| p != library.path()) {
+            tracing::warn!("libclamav already loaded from {}, ignoring lib_path {:?}", library.path(), lib_path);
+        }
+        return Ok(library);
+    }
+
+    let mut errors = Vec::new();
+    for candidate in lib_path.into_iter().chain(DEFAULT_LIBRARY_NAMES.iter().copied()) {
+        match ClamAVLibrary::open(candidate) {
+            Ok(library) => {
+                if !errors.is_empty() {
+                    tracing::warn!("Loaded libclamav from {} after failures: {}", candidate, errors.join("; "));
+                }
+                tracing::info!("libclamav loaded from {}", candidate);
+                return Ok(LIBRARY.get_or_init(|| library));
+            }
+            Err(e) => {
+                tracing::debug!("Failed to load libclamav candidate {}", e);
+                errors.push(e);
+            }
+        }
+    }
+
+    Err(ClamAVError::LibraryUnavailable(errors.join("; ")))
+}
+
+/// 获取已加载的 libclamav
+pub fn library() -> Result<&'static ClamAVLibrary, ClamAVError> {
+    LIBRARY.get().ok_or_else(|| ClamAVError::LibraryUnavailable("libclamav not loaded".to_string()))
+}
+
+/// 封装函数使用的函数表；调用方需先通过 load_library/library 确认库已加载
+fn api() -> &'static ClamAVLibrary {
+    LIBRARY.get().expect("libclamav used before it was loaded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_missing_library() {
+        let err = ClamAVLibrary::open("/nonexistent/libclamav.so").err().unwrap();
+        assert!(err.starts_with("/nonexistent/libclamav.so"));
+    }
+
+    #[test]
+    fn test_open_library_without_symbols() {
+        // libc 可以打开，但不包含任何 libclamav 符号
+        let err = ClamAVLibrary::open("libc.so.6").err().unwrap();
+        assert!(err.contains("missing symbols"));
+        assert!(err.contains("cl_init"));
+        assert!(err.contains("cl_scandesc_ex"));
+    }
+}
@@ -3,7 +3,7 @@
 // 设计要点:
 // - 引擎单例模式，全局唯一实例
 // - 线程安全访问 (Arc<Mutex<Engine>>)
-// - 支持病毒库热重载（重新初始化）
+// - 支持病毒库热重载（后台编译新引擎后原子替换）
 // - 服务启动时初始化，关闭时释放
 // - 引擎异常时自动恢复机制
 //
@@ -12,11 +12,13 @@
 // - Initializing: 正在初始化
 // - Ready: 已就绪，可以执行扫描
 // - Error: 引擎错误状态
+// - Unavailable: libclamav 无法加载，HTTP API 正常运行但不能扫描
 // - Failed: 引擎失败，等待恢复
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 
-use super::ffi::{ClamAVEngine, ScanOptions, ScanResult, ClamAVError};
+use super::ffi::{ClamAVEngine, EngineInfo, ScanOptions, ScanResult, ClamAVError};
+use super::scanner::{Scanner, ScannerLoader};
 use crate::models::config::ClamAVConfig;
 
 /// 引擎状态
@@ -26,6 +28,8 @@ pub enum EngineState {
     Initializing,
     Ready,
     Error(String),
+    /// libclamav 无法加载（附带原因）
+    Unavailable(String),
     Failed,
 }
 
@@ -37,29 +41,116 @@ impl EngineState {
     pub fn is_operational(&self) -> bool {
         matches!(self, EngineState::Ready)
     }
+
+    /// 错误或不可用状态的原因
+    pub fn error_message(&self) -> Option<&str> {
+        match self {
+            EngineState::Error(msg) | EngineState::Unavailable(msg) => Some(msg),
+            _ => None,
+        }
+    }
+}
+
+/// 可原子替换的引擎句柄
+///
+/// 扫描时通过 current() 获取当前引擎的 Arc；重载时用 swap() 替换为新引擎，
+/// 正在进行的扫描持有旧引擎的 Arc 继续完成，最后一个引用释放时旧引擎被回收
+pub struct EngineHandle<S: Scanner = ClamAVEngine> {
+    slot: Arc<RwLock<Option<Arc<S>>>>,
+}
+
+impl<S: Scanner> Clone for EngineHandle<S> {
+    fn clone(&self) -> Self {
+        Self { slot: self.slot.clone() }
+    }
+}
+
+impl<S: Scanner> Default for EngineHandle<S> {
+    fn default() -> Self {
+        Self { slot: Arc::new(RwLock::new(None)) }
+    }
+}
+
+impl<S: Scanner> EngineHandle<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取当前引擎
+    pub fn current(&self) -> Result<Arc<S>, ClamAVError> {
+        self.slot.read().unwrap()
+            .as_ref()
+            .map(Arc::clone)
+            .ok_or_else(|| ClamAVError::ScanFailed("Engine not available".to_string()))
+    }
+
+    /// 替换当前引擎，返回旧引擎
+    pub fn swap(&self, engine: Arc<S>) -> Option<Arc<S>> {
+        self.slot.write().unwrap().replace(engine)
+    }
+
+    /// 移除当前引擎
+    pub fn take(&self) -> Option<Arc<S>> {
+        self.slot.write().unwrap().take()
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.slot.read().unwrap().is_some()
+    }
 }
 
 /// ClamAV 引擎单例管理器
-pub struct EngineManager {
-    engine: Arc<Mutex<Option<Arc<ClamAVEngine>>>>,
+pub struct EngineManager<S: Scanner = ClamAVEngine> {
+    engine: EngineHandle<S>,
     state: Arc<Mutex<EngineState>>,
-    config: ClamAVConfig,
+    config: Mutex<ClamAVConfig>,
+    /// 串行化重载，避免并发编译多个引擎
+    reload_lock: Mutex<()>,
+    /// 按配置创建引擎
+    loader: Box<ScannerLoader<S>>,
 }
 
-impl EngineManager {
-    /// 创建新的引擎管理器
+impl EngineManager<ClamAVEngine> {
+    /// 创建使用 libclamav 引擎的管理器
     pub fn new(config: ClamAVConfig) -> Self {
+        Self::with_loader(config, ClamAVEngine::load)
+    }
+}
+
+impl<S: Scanner> EngineManager<S> {
+    /// 创建使用自定义扫描后端的管理器
+    pub fn with_loader<F>(config: ClamAVConfig, loader: F) -> Self
+    where
+        F: Fn(&ClamAVConfig) -> Result<S, ClamAVError> + Send + Sync + 'static,
+    {
         Self {
-            engine: Arc::new(Mutex::new(None)),
+            engine: EngineHandle::new(),
             state: Arc::new(Mutex::new(EngineState::Uninitialized)),
-            config,
+            config: Mutex::new(config),
+            reload_lock: Mutex::new(()),
+            loader: Box::new(loader),
         }
     }
 
-    /// 初始化引擎
+    /// 获取当前引擎配置
+    pub fn get_config(&self) -> ClamAVConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// 更新引擎配置
     ///
-    /// # 参数
-    /// - db_dir: 病毒库目录路径
+    /// 新配置在下一次 initialize/reload 时生效
+    pub fn update_config(&self, config: ClamAVConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// 按当前配置创建并编译新引擎（耗时操作，不持有任何锁）
+    fn build_engine(&self) -> Result<S, ClamAVError> {
+        let config = self.get_config();
+        (self.loader)(&config)
+    }
+
+    /// 初始化引擎
     ///
     /// # 返回
     /// - Ok(()) 或 Err(错误信息)
@@ -76,28 +167,26 @@ impl EngineManager {
         *state = EngineState::Initializing;
         drop(state);
 
-        // 准备证书目录路径
-        let certs_dir = self.config.certs_dir.as_deref();
-        tracing::info!("Initializing ClamAV engine with db_dir={}, certs_dir={:?}",
-                       self.config.database_dir, certs_dir);
-
         // 创建新引擎
-        let engine = match ClamAVEngine::initialize(&self.config.database_dir, certs_dir) {
+        let engine = match self.build_engine() {
             Ok(e) => {
                 tracing::info!("ClamAV engine initialized successfully");
                 e
             }
             Err(e) => {
                 tracing::error!("Failed to initialize ClamAV engine: {}", e);
-                // 更新状态为错误
+                // 更新状态为错误；库本身无法加载时单独标记，便于前端提示
                 let mut state = self.state.lock().unwrap();
-                *state = EngineState::Error(e.to_string());
+                *state = match &e {
+                    ClamAVError::LibraryUnavailable(reason) => EngineState::Unavailable(reason.clone()),
+                    _ => EngineState::Error(e.to_string()),
+                };
                 return Err(e.to_string());
             }
         };
 
         // 保存引擎实例
-        *self.engine.lock().unwrap() = Some(Arc::new(engine));
+        self.engine.swap(Arc::new(engine));
 
         // 更新状态为就绪
         let mut state = self.state.lock().unwrap();
@@ -111,30 +200,34 @@ impl EngineManager {
     /// 获取引擎实例（用于执行扫描）
     ///
     /// 如果引擎未初始化或处于错误状态，返回错误
-    pub fn get_engine(&self) -> Result<Arc<ClamAVEngine>, String> {
+    pub fn get_engine(&self) -> Result<Arc<S>, String> {
         let state = self.state.lock().unwrap();
 
         if !state.is_operational() {
             Err(format!("Engine not operational: {:?}", state))
         } else {
-            Ok(self.engine.lock().unwrap().as_ref().unwrap().clone())
+            self.engine.current().map_err(|e| e.to_string())
         }
     }
 
-    /// 释放引擎资源
+    /// 获取当前引擎的加载信息
+    pub fn engine_info(&self) -> Option<EngineInfo> {
+        self.engine.current().ok().map(|e| e.info().clone())
+    }
+
+    /// 获取引擎句柄
     ///
-    /// 可以用于热重载病毒库后重新初始化
+    /// 扫描引擎持有句柄而不是固定的引擎实例，从而在重载后自动使用新引擎
+    pub fn engine_handle(&self) -> EngineHandle<S> {
+        self.engine.clone()
+    }
+
+    /// 释放引擎资源
     pub fn shutdown(&self) {
         tracing::info!("Shutting down ClamAV engine");
 
-        // 释放引擎
-        {
-            let mut engine = self.engine.lock().unwrap();
-            if let Some(e) = engine.take() {
-                // Arc 会自动处理引用计数
-                drop(e);
-            }
-        }
+        // 释放引擎（Arc 会自动处理引用计数，正在进行的扫描完成后才真正释放）
+        drop(self.engine.take());
 
         // 重置状态
         *self.state.lock().unwrap() = EngineState::Uninitialized;
@@ -157,17 +250,34 @@ impl EngineManager {
 
     /// 病毒库热重载
     ///
-    /// 重新加载病毒库，用于 freshclam 更新后调用
+    /// 在后台编译新引擎，成功后原子替换当前引擎；编译期间旧引擎继续提供扫描，
+    /// 正在进行的扫描在旧引擎上完成。新引擎编译失败时保留旧引擎
     pub fn reload(&self) -> Result<(), String> {
-        tracing::info!("Reloading ClamAV engine with new database");
+        let _guard = self.reload_lock.lock().unwrap();
 
-        // 先关闭当前引擎
-        self.shutdown();
+        // 当前没有可用引擎时直接初始化
+        if !self.get_state().is_ready() || !self.engine.is_loaded() {
+            tracing::info!("No engine loaded, initializing instead of reloading");
+            *self.state.lock().unwrap() = EngineState::Uninitialized;
+            return self.initialize();
+        }
 
-        // 重新初始化
-        self.initialize()?;
+        tracing::info!("Reloading ClamAV engine with new database");
+        let started = std::time::Instant::now();
+
+        let engine = self.build_engine().map_err(|e| {
+            tracing::error!("Reload failed, keeping current engine: {}", e);
+            format!("Reload failed: {}", e)
+        })?;
+
+        // 原子替换；旧引擎在最后一个扫描释放引用后回收
+        let old = self.engine.swap(Arc::new(engine));
+        if let Some(old) = old {
+            tracing::info!("Swapped engine, {} in-flight reference(s) to old engine",
+                           Arc::strong_count(&old) - 1);
+        }
 
-        tracing::info!("Engine reloaded successfully");
+        tracing::info!("Engine reloaded successfully in {:.1}s", started.elapsed().as_secs_f64());
         Ok(())
     }
 }
@@ -175,6 +285,7 @@ impl EngineManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clamav::mock::MockScanner;
 
     #[test]
     fn test_engine_manager_initialization() {
@@ -189,19 +300,22 @@ mod tests {
 
         // 测试状态
         assert_eq!(manager.get_state(), EngineState::Uninitialized);
+
+        // 未加载引擎时没有引擎信息
+        assert!(manager.engine_info().is_none());
     }
 
     #[test]
     fn test_engine_manager_shutdown() {
-        let manager = EngineManager::new(ClamAVConfig {
+        let manager = EngineManager::with_loader(ClamAVConfig {
             database_dir: "/test/db".to_string(),
             ..Default::default()
-        });
+        }, |_| Ok(MockScanner::new()));
 
         // 先初始化
-        // 注意：由于 ClamAV FFI 可能不存在，这个测试可能失败
-        // 实际实现时需要 mock 或条件编译
-        let _init_result = manager.initialize();
+        manager.initialize().unwrap();
+        assert_eq!(manager.get_state(), EngineState::Ready);
+        assert!(manager.engine.is_loaded());
 
         // 检查关闭
         manager.shutdown();
@@ -210,6 +324,79 @@ mod tests {
         assert_eq!(manager.get_state(), EngineState::Uninitialized);
 
         // 验证引擎已释放
-        assert!(manager.engine.lock().unwrap().is_none());
+        assert!(!manager.engine.is_loaded());
+    }
+
+    #[test]
+    fn test_engine_handle_swap_keeps_old_engine_alive() {
+        let handle = EngineHandle::new();
+        assert!(handle.current().is_err());
+
+        let first = Arc::new(ClamAVEngine::uninitialized());
+        assert!(handle.swap(first.clone()).is_none());
+
+        // 模拟正在进行的扫描持有旧引擎
+        let in_flight = handle.current().unwrap();
+        assert!(Arc::ptr_eq(&in_flight, &first));
+
+        let second = Arc::new(ClamAVEngine::uninitialized());
+        let old = handle.swap(second.clone()).unwrap();
+        assert!(Arc::ptr_eq(&old, &first));
+        assert!(Arc::ptr_eq(&handle.current().unwrap(), &second));
+
+        // 旧引擎仍可被在途扫描使用
+        assert!(Arc::ptr_eq(&in_flight, &first));
+    }
+
+    #[test]
+    fn test_engine_manager_with_mock_scanner() {
+        let manager = EngineManager::with_loader(ClamAVConfig::default(), |_| Ok(MockScanner::new()));
+
+        assert!(manager.initialize().is_ok());
+        assert_eq!(manager.get_state(), EngineState::Ready);
+        assert_eq!(manager.engine_info().unwrap().library_version, "mock");
+
+        let handle = manager.engine_handle();
+        let before = handle.current().unwrap();
+        assert!(manager.reload().is_ok());
+        assert!(!Arc::ptr_eq(&before, &handle.current().unwrap()));
+
+        manager.shutdown();
+        assert_eq!(manager.get_state(), EngineState::Uninitialized);
+        assert!(handle.current().is_err());
+    }
+
+    #[test]
+    fn test_reload_failure_keeps_current_engine() {
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let manager = EngineManager::with_loader(ClamAVConfig::default(), move |_| {
+            // 第一次加载成功，之后的重载失败
+            if counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                Ok(MockScanner::new())
+            } else {
+                Err(ClamAVError::DatabaseLoadFailed("corrupt database".to_string()))
+            }
+        });
+
+        manager.initialize().unwrap();
+        let before = manager.get_engine().unwrap();
+
+        assert!(manager.reload().is_err());
+        assert_eq!(manager.get_state(), EngineState::Ready);
+        assert!(Arc::ptr_eq(&before, &manager.get_engine().unwrap()));
+    }
+
+    #[test]
+    fn test_missing_library_marks_engine_unavailable() {
+        let manager: EngineManager<MockScanner> = EngineManager::with_loader(ClamAVConfig::default(), |_| {
+            Err(ClamAVError::LibraryUnavailable("/opt/lib/libclamav.so: cannot open shared object file".to_string()))
+        });
+
+        assert!(manager.initialize().is_err());
+        let state = manager.get_state();
+        assert!(matches!(state, EngineState::Unavailable(_)));
+        assert!(state.error_message().unwrap().contains("cannot open shared object file"));
+        assert!(manager.get_engine().is_err());
     }
 }
@@ -0,0 +1,279 @@
+// 模拟扫描引擎
+//
+// 进程内的 Scanner 实现，不依赖 libclamav：
+// - 按路径或内容规则返回指定结论（干净 / 感染 / 错误）
+// - 可为规则或所有文件注入扫描延迟
+// - 记录扫描次数和同时进行的扫描数峰值，便于测试断言
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::ffi::{ClamAVError, EngineInfo, ScanOptions, ScanResult};
+use super::scanner::Scanner;
+
+/// 规则匹配条件
+#[derive(Debug, Clone)]
+pub enum MockPattern {
+    /// 路径包含指定字符串
+    PathContains(String),
+    /// 文件名以指定后缀结尾
+    PathSuffix(String),
+    /// 文件内容包含指定字节序列
+    Content(Vec<u8>),
+}
+
+/// 模拟扫描结论
+#[derive(Debug, Clone)]
+pub enum MockVerdict {
+    Clean,
+    Infected(Vec<String>),
+    Error(ClamAVError),
+}
+
+/// 模拟规则：匹配条件 → 结论（可附加延迟）
+#[derive(Debug, Clone)]
+pub struct MockRule {
+    pub pattern: MockPattern,
+    pub verdict: MockVerdict,
+    pub delay: Option<Duration>,
+    /// 感染时报告的容器成员路径
+    pub container_path: Option<String>,
+}
+
+impl MockRule {
+    pub fn new(pattern: MockPattern) -> Self {
+        Self {
+            pattern,
+            verdict: MockVerdict::Clean,
+            delay: None,
+            container_path: None,
+        }
+    }
+
+    pub fn path_contains(s: &str) -> Self {
+        Self::new(MockPattern::PathContains(s.to_string()))
+    }
+
+    pub fn path_suffix(s: &str) -> Self {
+        Self::new(MockPattern::PathSuffix(s.to_string()))
+    }
+
+    pub fn content(bytes: &[u8]) -> Self {
+        Self::new(MockPattern::Content(bytes.to_vec()))
+    }
+
+    /// 匹配时报告感染
+    pub fn infected(mut self, virus_name: &str) -> Self {
+        self.verdict = MockVerdict::Infected(vec![virus_name.to_string()]);
+        self
+    }
+
+    /// 感染位于容器内的指定成员（如 "inner.rar/evil.exe"）
+    pub fn in_container(mut self, member_path: &str) -> Self {
+        self.container_path = Some(member_path.to_string());
+        self
+    }
+
+    /// 匹配时返回错误
+    pub fn error(mut self, error: ClamAVError) -> Self {
+        self.verdict = MockVerdict::Error(error);
+        self
+    }
+
+    /// 匹配时延迟返回
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    fn matches(&self, path: &str, content: Option<&[u8]>) -> bool {
+        match &self.pattern {
+            MockPattern::PathContains(s) => path.contains(s.as_str()),
+            MockPattern::PathSuffix(s) => path.ends_with(s.as_str()),
+            MockPattern::Content(needle) => content
+                .map(|data| !needle.is_empty() && data.windows(needle.len()).any(|w| w == needle.as_slice()))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// 模拟扫描引擎
+#[derive(Debug, Clone, Default)]
+pub struct MockScanner {
+    rules: Vec<MockRule>,
+    delay: Option<Duration>,
+    info: EngineInfo,
+    scan_count: Arc<AtomicUsize>,
+    /// 正在进行的扫描数
+    in_flight: Arc<AtomicUsize>,
+    /// 同时进行的扫描数峰值
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+impl MockScanner {
+    pub fn new() -> Self {
+        Self {
+            info: EngineInfo {
+                library_version: "mock".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// 添加规则，按添加顺序匹配，第一条匹配的规则生效
+    pub fn with_rule(mut self, rule: MockRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// 每个文件的默认扫描延迟
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// 设置引擎信息
+    pub fn with_info(mut self, info: EngineInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// 已执行的扫描次数
+    pub fn scan_count(&self) -> usize {
+        self.scan_count.load(Ordering::Relaxed)
+    }
+
+    /// 同时进行的扫描数峰值
+    pub fn max_concurrent(&self) -> usize {
+        self.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    fn evaluate(&self, name: &str, content: Option<&[u8]>) -> Result<ScanResult, ClamAVError> {
+        self.scan_count.fetch_add(1, Ordering::Relaxed);
+
+        let rule = self.rules.iter().find(|r| r.matches(name, content));
+
+        let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+        if let Some(delay) = rule.and_then(|r| r.delay).or(self.delay) {
+            std::thread::sleep(delay);
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        let virus_names = match rule.map(|r| &r.verdict) {
+            Some(MockVerdict::Infected(names)) => names.clone(),
+            Some(MockVerdict::Error(e)) => return Err(e.clone()),
+            Some(MockVerdict::Clean) | None => Vec::new(),
+        };
+
+        let container_paths = vec![rule.and_then(|r| r.container_path.clone()); virus_names.len()];
+
+        Ok(ScanResult {
+            filename: name.to_string(),
+            is_infected: !virus_names.is_empty(),
+            virus_names,
+            file_hash: None,
+            file_type: None,
+            container_paths,
+        })
+    }
+
+    fn needs_content(&self) -> bool {
+        self.rules.iter().any(|r| matches!(r.pattern, MockPattern::Content(_)))
+    }
+}
+
+impl Scanner for MockScanner {
+    fn scan_file(&self, path: &str, _options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        if !Path::new(path).exists() {
+            return Err(ClamAVError::InvalidPath(path.to_string()));
+        }
+
+        let content = if self.needs_content() {
+            std::fs::read(path).ok()
+        } else {
+            None
+        };
+
+        self.evaluate(path, content.as_deref())
+    }
+
+    fn scan_opened(&self, file: &File, path: &str, _options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        let content = if self.needs_content() {
+            let mut data = Vec::new();
+            (&*file).read_to_end(&mut data)
+                .map_err(|e| ClamAVError::from_io(path, &e))?;
+            Some(data)
+        } else {
+            None
+        };
+
+        self.evaluate(path, content.as_deref())
+    }
+
+    fn scan_bytes(&self, data: &[u8], _options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        self.evaluate(&format!("<memory:{} bytes>", data.len()), Some(data))
+    }
+
+    fn info(&self) -> &EngineInfo {
+        &self.info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_rules_first_match_wins() {
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::path_suffix(".exe").infected("Win.Test.Exe"))
+            .with_rule(MockRule::path_contains("broken").error(ClamAVError::ScanFailed("boom".to_string())))
+            .with_rule(MockRule::content(b"EICAR").infected("Eicar-Test-Signature"));
+
+        let result = scanner.scan_bytes(b"xxEICARxx", ScanOptions::default()).unwrap();
+        assert!(result.is_infected);
+        assert_eq!(result.virus_name(), Some("Eicar-Test-Signature"));
+
+        let clean = scanner.scan_bytes(b"hello", ScanOptions::default()).unwrap();
+        assert!(!clean.is_infected);
+
+        assert_eq!(scanner.scan_count(), 2);
+    }
+
+    #[test]
+    fn test_mock_scan_file() {
+        let dir = std::env::temp_dir().join(format!("mock-scanner-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bad = dir.join("tool.exe");
+        let broken = dir.join("broken.bin");
+        std::fs::write(&bad, b"MZ").unwrap();
+        std::fs::write(&broken, b"data").unwrap();
+
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::path_suffix(".exe").infected("Win.Test.Exe"))
+            .with_rule(MockRule::path_contains("broken").error(ClamAVError::ScanFailed("boom".to_string())));
+
+        let result = scanner.scan_file(&bad.to_string_lossy(), ScanOptions::default()).unwrap();
+        assert_eq!(result.virus_names, vec!["Win.Test.Exe".to_string()]);
+        assert!(scanner.scan_file(&broken.to_string_lossy(), ScanOptions::default()).is_err());
+        assert!(scanner.scan_file(&dir.join("missing").to_string_lossy(), ScanOptions::default()).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_mock_injected_delay() {
+        let scanner = MockScanner::new()
+            .with_rule(MockRule::content(b"slow").with_delay(Duration::from_millis(30)));
+
+        let started = std::time::Instant::now();
+        scanner.scan_bytes(b"slow data", ScanOptions::default()).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+}
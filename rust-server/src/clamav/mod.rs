@@ -4,13 +4,35 @@
 // - 引擎初始化和生命周期管理
 // - 文件扫描功能
 // - 引擎状态管理
+// - 病毒库文件检查
+// - 扫描后端抽象（Scanner trait）及模拟引擎
+// - libclamav 运行时加载
+// - 扫描排除规则
+// - 安全的目录遍历
+// - 扫描检查点（守护进程重启后继续扫描）
+// - 扫描结果缓存（增量扫描）
 
 pub mod ffi;
+pub mod library;
 pub mod manager;
 pub mod engine;
 pub mod types;
+pub mod database;
+pub mod scanner;
+pub mod mock;
+pub mod exclusion;
+pub mod traversal;
+pub mod checkpoint;
+pub mod verdict_cache;
 
 pub use ffi::*;
 pub use manager::*;
 pub use engine::*;
 pub use types::*;
+pub use scanner::*;
+pub use mock::*;
+pub use exclusion::*;
+pub use traversal::DiscoveryOptions;
+pub use checkpoint::{CheckpointCounters, CheckpointState, ResumeState, ScanCheckpoint};
+pub use verdict_cache::{CachedVerdict, FileIdentity, Verdict, VerdictCache, VerdictCachePolicy};
+pub use library::{load_library, ClamAVLibrary};
@@ -0,0 +1,91 @@
+// 扫描后端抽象
+//
+// Scanner trait 抽象了扫描管线所需的引擎能力：
+// - ClamAVEngine: 基于 libclamav FFI 的真实引擎
+// - MockScanner: 进程内模拟引擎（见 mock.rs），用于无 libclamav 环境下的测试
+
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+
+use super::database::{save_known_good, verify_database_dir};
+use super::ffi::{verify_database_file, verify_signature_file, ClamAVEngine, ClamAVError, EngineInfo, ScanOptions, ScanResult};
+use super::library::load_library;
+use crate::models::config::ClamAVConfig;
+
+/// 扫描后端
+///
+/// 实现需要线程安全：同一个实例会在多个 spawn_blocking 任务中并发使用
+pub trait Scanner: Send + Sync + 'static {
+    /// 扫描单个文件
+    fn scan_file(&self, path: &str, options: ScanOptions) -> Result<ScanResult, ClamAVError>;
+
+    /// 扫描已打开的文件（由调用方打开并确认身份），path 用于扫描结果中的文件名
+    fn scan_opened(&self, file: &File, path: &str, options: ScanOptions) -> Result<ScanResult, ClamAVError>;
+
+    /// 扫描内存缓冲区
+    fn scan_bytes(&self, data: &[u8], options: ScanOptions) -> Result<ScanResult, ClamAVError>;
+
+    /// 引擎加载信息
+    fn info(&self) -> &EngineInfo;
+}
+
+/// 扫描后端加载器：按配置创建并编译新引擎，由 EngineManager 在初始化和重载时调用
+pub type ScannerLoader<S> = dyn Fn(&ClamAVConfig) -> Result<S, ClamAVError> + Send + Sync;
+
+impl Scanner for ClamAVEngine {
+    fn scan_file(&self, path: &str, options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        ClamAVEngine::scan_file(self, path, options)
+    }
+
+    fn scan_opened(&self, file: &File, path: &str, options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        ClamAVEngine::scan_descriptor(self, file.as_raw_fd(), Some(path), options)
+    }
+
+    fn scan_bytes(&self, data: &[u8], options: ScanOptions) -> Result<ScanResult, ClamAVError> {
+        ClamAVEngine::scan_bytes(self, data, options)
+    }
+
+    fn info(&self) -> &EngineInfo {
+        ClamAVEngine::info(self)
+    }
+}
+
+impl ClamAVEngine {
+    /// 按 ClamAVConfig 加载 libclamav，校验病毒库后创建并编译引擎
+    ///
+    /// 损坏的数据库文件移入 rejected/ 并尽量从 known-good/ 恢复；
+    /// 加载成功后将本次使用的数据库记录为 known-good
+    pub fn load(config: &ClamAVConfig) -> Result<Self, ClamAVError> {
+        load_library(config.lib_path.as_deref())?;
+
+        let verification = verify_database_dir(&config.database_dir, verify_database_file, verify_signature_file);
+        if verification.accepted.is_empty() {
+            let reasons: Vec<String> = verification.rejected.iter()
+                .map(|r| format!("{}: {}", r.file, r.reason))
+                .collect();
+            return Err(ClamAVError::DatabaseLoadFailed(format!(
+                "No usable database files in {}{}",
+                config.database_dir,
+                if reasons.is_empty() { String::new() } else { format!(" (rejected: {})", reasons.join("; ")) }
+            )));
+        }
+
+        let certs_dir = config.certs_dir.as_deref();
+        let limits = config.engine_limits();
+        let db_options = config.database_options();
+        let settings = config.engine_settings();
+        let custom_sigs_dir = config.custom_sigs_dir.as_deref();
+        tracing::info!("Initializing ClamAV engine with db_dir={}, custom_sigs_dir={:?}, certs_dir={:?}",
+                       config.database_dir, custom_sigs_dir, certs_dir);
+
+        Self::initialize(&config.database_dir, custom_sigs_dir, certs_dir, &limits, &db_options, &settings)
+            .map(|engine| {
+                if let Err(e) = save_known_good(&config.database_dir, &verification.accepted) {
+                    tracing::warn!("Failed to record known-good database files: {}", e);
+                }
+                engine
+                    .with_heuristic_alerts(config.heuristic_alerts())
+                    .with_database_verification(&verification)
+            })
+    }
+}
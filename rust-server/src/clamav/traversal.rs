@@ -0,0 +1,414 @@
+// 安全的目录遍历
+//
+// 发现线程使用 symlink_metadata 判断条目类型，而不是会跟随符号链接的 is_dir()/is_file()：
+// - 符号链接：默认跳过；follow_symlinks 时跟随，已访问过的目录不会再次进入，符号链接循环可以终止
+// - 套接字、FIFO、设备文件直接跳过（交给 cl_scanfile 可能阻塞工作线程）
+// - one_filesystem：不跨越根路径所在的文件系统
+// - 按 (dev, inode) 去重：每个目录只遍历一次，硬链接文件只扫描一次
+// - 无法 stat 的条目记录为失败事件，而不是静默跳过
+// - 工作线程通过 open_discovered 打开文件：发现后被替换为符号链接、特殊文件或其他文件时拒绝扫描
+
+use std::collections::HashSet;
+use std::fs::{File, FileType, Metadata, ReadDir};
+use std::io;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::exclusion::ExclusionRules;
+use super::types::{ErrorCategory, FileEvent};
+
+/// 目录遍历选项
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryOptions {
+    /// 排除规则
+    pub exclusions: Arc<ExclusionRules>,
+    /// 跟随符号链接（默认跳过）
+    pub follow_symlinks: bool,
+    /// 不跨越根路径所在的文件系统
+    pub one_filesystem: bool,
+    /// 测试用：读取这些目录时返回权限错误（测试以 root 运行时无法构造无法读取的目录）
+    #[cfg(test)]
+    pub(crate) unreadable_dirs: Vec<PathBuf>,
+}
+
+impl DiscoveryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_exclusions(mut self, exclusions: ExclusionRules) -> Self {
+        self.exclusions = Arc::new(exclusions);
+        self
+    }
+
+    pub fn with_follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    pub fn with_one_filesystem(mut self, one_filesystem: bool) -> Self {
+        self.one_filesystem = one_filesystem;
+        self
+    }
+}
+
+/// 发现文件时的身份，扫描前用于确认打开的仍是同一个文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileId {
+    pub dev: u64,
+    pub ino: u64,
+    /// 经由符号链接发现（根路径或 follow_symlinks），打开时需要跟随链接
+    pub via_symlink: bool,
+}
+
+/// 遍历条目的处理方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    /// 需要继续遍历的目录
+    Directory(PathBuf),
+    /// 需要扫描的普通文件
+    File(PathBuf, FileId),
+    /// 跳过；需要记录原因时附带文件事件
+    Skipped(Option<FileEvent>),
+}
+
+/// 遍历状态：记录已访问的 (dev, inode) 和当前根路径所在的文件系统
+#[derive(Debug)]
+pub struct Traversal {
+    options: DiscoveryOptions,
+    visited_dirs: HashSet<(u64, u64)>,
+    visited_files: HashSet<(u64, u64)>,
+    root_dev: Option<u64>,
+}
+
+impl Traversal {
+    pub fn new(options: DiscoveryOptions) -> Self {
+        Self {
+            options,
+            visited_dirs: HashSet::new(),
+            visited_files: HashSet::new(),
+            root_dev: None,
+        }
+    }
+
+    /// 列出目录的条目
+    pub fn read_dir(&self, dir: &Path) -> io::Result<ReadDir> {
+        #[cfg(test)]
+        if self.options.unreadable_dirs.iter().any(|d| d == dir) {
+            return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+        }
+        std::fs::read_dir(dir)
+    }
+
+    /// 开始遍历一个根路径
+    ///
+    /// 根路径由用户显式指定，总是跟随符号链接，也不应用排除规则
+    pub fn enter_root(&mut self, root: &Path) -> Entry {
+        let metadata = match std::fs::metadata(root) {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::debug!("Cannot stat scan root {}: {}", root.display(), e);
+                return Entry::Skipped(Some(stat_failed(root, &e)));
+            }
+        };
+
+        self.root_dev = Some(metadata.dev());
+        self.classify_metadata(root.to_path_buf(), &metadata, true)
+    }
+
+    /// 判断目录中的一个条目如何处理
+    pub fn classify(&mut self, path: PathBuf) -> Entry {
+        let mut metadata = match std::fs::symlink_metadata(&path) {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::debug!("Cannot stat {}: {}", path.display(), e);
+                return Entry::Skipped(Some(stat_failed(&path, &e)));
+            }
+        };
+
+        let via_symlink = metadata.file_type().is_symlink();
+        if via_symlink {
+            if !self.options.follow_symlinks {
+                tracing::trace!("Skipping symlink {}", path.display());
+                return Entry::Skipped(None);
+            }
+            metadata = match std::fs::metadata(&path) {
+                Ok(m) => m,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    tracing::trace!("Skipping dangling symlink {}: {}", path.display(), e);
+                    return Entry::Skipped(Some(FileEvent::skipped(path.display().to_string(), "dangling_symlink".to_string())));
+                }
+                Err(e) => {
+                    tracing::debug!("Cannot stat symlink target {}: {}", path.display(), e);
+                    return Entry::Skipped(Some(stat_failed(&path, &e)));
+                }
+            };
+        }
+
+        if let Some(rule) = self.options.exclusions.explain(&path, metadata.is_dir()) {
+            tracing::trace!("Excluded {} by {:?} rule '{}'", path.display(), rule.kind, rule.rule);
+            return Entry::Skipped(Some(FileEvent::excluded(path.display().to_string(), rule.rule)));
+        }
+
+        if self.options.one_filesystem && self.root_dev.is_some_and(|dev| dev != metadata.dev()) {
+            tracing::trace!("Not crossing filesystem boundary at {}", path.display());
+            return Entry::Skipped(Some(FileEvent::excluded(path.display().to_string(), "one_filesystem".to_string())));
+        }
+
+        self.classify_metadata(path, &metadata, via_symlink)
+    }
+
+    fn classify_metadata(&mut self, path: PathBuf, metadata: &Metadata, via_symlink: bool) -> Entry {
+        let id = (metadata.dev(), metadata.ino());
+        let file_type = metadata.file_type();
+
+        if file_type.is_dir() {
+            // 已遍历过的目录（符号链接循环、重叠的根路径）
+            if !self.visited_dirs.insert(id) {
+                tracing::trace!("Directory already visited: {}", path.display());
+                return Entry::Skipped(None);
+            }
+            Entry::Directory(path)
+        } else if file_type.is_file() {
+            // 只有存在多个硬链接的文件需要记录
+            if metadata.nlink() > 1 && !self.visited_files.insert(id) {
+                tracing::trace!("Hardlink already scanned: {}", path.display());
+                return Entry::Skipped(None);
+            }
+            Entry::File(path, FileId { dev: id.0, ino: id.1, via_symlink })
+        } else {
+            let kind = special_file_kind(&file_type);
+            tracing::debug!("Skipping {} {}", kind, path.display());
+            Entry::Skipped(Some(FileEvent::skipped(path.display().to_string(), kind.to_string())))
+        }
+    }
+}
+
+/// 无法 stat 的条目
+fn stat_failed(path: &Path, error: &io::Error) -> FileEvent {
+    FileEvent::failed(path.display().to_string(), io_error_category(error), None, error.to_string())
+}
+
+/// I/O 错误的分类
+pub fn io_error_category(error: &io::Error) -> ErrorCategory {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => ErrorCategory::Permission,
+        _ => ErrorCategory::Io,
+    }
+}
+
+/// 打开待扫描的普通文件
+///
+/// 以 O_NONBLOCK 打开，文件被替换为 FIFO 或设备文件时不会阻塞；follow 为 false 时附加 O_NOFOLLOW，
+/// 路径是符号链接时打开失败。打开后 fstat 确认是普通文件，返回的元数据与实际扫描的内容对应
+pub fn open_regular(path: &Path, follow: bool) -> io::Result<(File, Metadata)> {
+    let mut flags = libc::O_NONBLOCK;
+    if !follow {
+        flags |= libc::O_NOFOLLOW;
+    }
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(flags)
+        .open(path)?;
+
+    let metadata = file.metadata()?;
+    if !metadata.file_type().is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a regular file ({})", path.display(), special_file_kind(&metadata.file_type())),
+        ));
+    }
+    Ok((file, metadata))
+}
+
+/// 打开发现的文件用于扫描
+///
+/// 发现与扫描之间文件可能被替换：只有经由符号链接发现的文件跟随链接，
+/// 并要求打开的文件 (dev, inode) 与发现时一致
+pub fn open_discovered(path: &Path, id: FileId) -> io::Result<(File, Metadata)> {
+    let (file, metadata) = open_regular(path, id.via_symlink)?;
+    if (metadata.dev(), metadata.ino()) != (id.dev, id.ino) {
+        return Err(io::Error::other(format!("{} was replaced after discovery", path.display())));
+    }
+    Ok((file, metadata))
+}
+
+/// 特殊文件类型名称
+fn special_file_kind(file_type: &FileType) -> &'static str {
+    if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() {
+        "block_device"
+    } else if file_type.is_char_device() {
+        "char_device"
+    } else {
+        "special_file"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clamav::types::FileEventReason;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("traversal-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 按 Traversal 完整遍历，返回需要扫描的文件和记录的事件
+    fn walk(root: &Path, options: DiscoveryOptions) -> (Vec<PathBuf>, Vec<FileEvent>) {
+        let mut traversal = Traversal::new(options);
+        let mut files = Vec::new();
+        let mut events = Vec::new();
+        let mut queue = Vec::new();
+
+        match traversal.enter_root(root) {
+            Entry::Directory(d) => queue.push(d),
+            Entry::File(f, _) => files.push(f),
+            Entry::Skipped(e) => events.extend(e),
+        }
+        while let Some(dir) = queue.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                match traversal.classify(entry.unwrap().path()) {
+                    Entry::Directory(d) => queue.push(d),
+                    Entry::File(f, _) => files.push(f),
+                    Entry::Skipped(e) => events.extend(e),
+                }
+            }
+        }
+        files.sort();
+        (files, events)
+    }
+
+    #[test]
+    fn test_symlink_loop_terminates() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::write(dir.join("a/b/file.txt"), b"data").unwrap();
+        std::os::unix::fs::symlink(dir.join("a"), dir.join("a/b/loop")).unwrap();
+
+        // 默认不跟随符号链接
+        let (files, _) = walk(&dir, DiscoveryOptions::new());
+        assert_eq!(files, vec![dir.join("a/b/file.txt")]);
+
+        // 跟随时循环目录只遍历一次
+        let (files, _) = walk(&dir, DiscoveryOptions::new().with_follow_symlinks(true));
+        assert_eq!(files, vec![dir.join("a/b/file.txt")]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_hardlinks_are_scanned_once() {
+        let dir = temp_dir();
+        std::fs::write(dir.join("original.bin"), b"data").unwrap();
+        std::fs::hard_link(dir.join("original.bin"), dir.join("backup.bin")).unwrap();
+        std::fs::write(dir.join("other.bin"), b"data").unwrap();
+
+        let (files, _) = walk(&dir, DiscoveryOptions::new());
+        assert_eq!(files.len(), 2);
+        assert!(files.contains(&dir.join("other.bin")));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_special_files_are_skipped() {
+        let dir = temp_dir();
+        std::fs::write(dir.join("file.txt"), b"data").unwrap();
+        let _listener = std::os::unix::net::UnixListener::bind(dir.join("daemon.sock")).unwrap();
+
+        let (files, events) = walk(&dir, DiscoveryOptions::new());
+        assert_eq!(files, vec![dir.join("file.txt")]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].reason, FileEventReason::Skipped);
+        assert_eq!(events[0].detail.as_deref(), Some("socket"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_one_filesystem_stays_on_root_device() {
+        // 同一文件系统内的目录照常遍历
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/file.txt"), b"data").unwrap();
+
+        let (files, events) = walk(&dir, DiscoveryOptions::new().with_one_filesystem(true));
+        assert_eq!(files, vec![dir.join("sub/file.txt")]);
+        assert!(events.is_empty());
+
+        // /proc 与临时目录不在同一文件系统
+        let mut traversal = Traversal::new(DiscoveryOptions::new().with_one_filesystem(true));
+        assert!(matches!(traversal.enter_root(&dir), Entry::Directory(_)));
+        if Path::new("/proc/self").exists() {
+            match traversal.classify(PathBuf::from("/proc")) {
+                Entry::Skipped(Some(event)) => assert_eq!(event.reason, FileEventReason::Excluded),
+                other => panic!("expected /proc to be skipped, got {:?}", other),
+            }
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stat_failures_are_recorded() {
+        let dir = temp_dir();
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("dangling")).unwrap();
+
+        let mut traversal = Traversal::new(DiscoveryOptions::new().with_follow_symlinks(true));
+        match traversal.classify(dir.join("vanished.txt")) {
+            Entry::Skipped(Some(event)) => {
+                assert_eq!(event.reason, FileEventReason::Error);
+                assert_eq!(event.category, Some(ErrorCategory::Io));
+            }
+            other => panic!("expected a failed event, got {:?}", other),
+        }
+        match traversal.classify(dir.join("dangling")) {
+            Entry::Skipped(Some(event)) => assert_eq!(event.detail.as_deref(), Some("dangling_symlink")),
+            other => panic!("expected a skipped event, got {:?}", other),
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_open_discovered_rejects_replaced_files() {
+        let dir = temp_dir();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, b"data").unwrap();
+        std::fs::write(dir.join("other.txt"), b"other").unwrap();
+
+        let mut traversal = Traversal::new(DiscoveryOptions::new());
+        let id = match traversal.classify(path.clone()) {
+            Entry::File(_, id) => id,
+            other => panic!("expected a file, got {:?}", other),
+        };
+        assert!(open_discovered(&path, id).is_ok());
+
+        // 替换为其他文件
+        std::fs::rename(dir.join("other.txt"), &path).unwrap();
+        assert!(open_discovered(&path, id).is_err());
+
+        // 替换为符号链接
+        std::fs::remove_file(&path).unwrap();
+        std::os::unix::fs::symlink("/etc/hostname", &path).unwrap();
+        assert!(open_discovered(&path, id).is_err());
+
+        // 替换为 FIFO：不阻塞，直接拒绝
+        std::fs::remove_file(&path).unwrap();
+        let fifo = std::ffi::CString::new(path.to_string_lossy().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+        let error = open_discovered(&path, id).unwrap_err();
+        assert!(error.to_string().contains("fifo"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
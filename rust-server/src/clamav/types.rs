@@ -3,6 +3,7 @@
 // 此文件定义了 ClamAV FFI 中使用的各种数据结构
 
 use std::fmt;
+use serde::{Deserialize, Serialize};
 
 /// 病毒名称
 #[derive(Debug, Clone, PartialEq)]
@@ -34,6 +35,21 @@ impl fmt::Display for ProgressPercent {
     }
 }
 
+/// 检测到的威胁
+///
+/// all-match 模式下同一文件的每个匹配签名各对应一条记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedThreat {
+    pub file_path: FilePath,
+    pub virus_name: VirusName,
+    /// 文件 SHA-256 哈希
+    pub file_hash: Option<String>,
+    /// ClamAV 识别的文件类型（如 CL_TYPE_PE）
+    pub file_type: Option<String>,
+    /// 命中成员在容器（压缩包等）内的路径，如 "inner.rar/evil.exe"
+    pub container_path: Option<String>,
+}
+
 /// 扫描的文件数量
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ScannedFiles(pub u32);
@@ -120,6 +136,21 @@ pub struct ScanProgress {
     pub discovered_files: DiscoveredFiles,
     /// 扫描速率（文件/秒，基于 EMA 计算）
     pub scan_rate: Option<ScanRate>,
+    /// 各扫描工作线程正在扫描的文件（按工作线程编号索引，空闲为 None）
+    pub worker_files: Vec<Option<FilePath>>,
+    /// 多根目录扫描时各根目录的进度（按提交顺序）
+    pub roots: Vec<RootProgress>,
+}
+
+/// 单个扫描根目录的进度
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootProgress {
+    pub path: FilePath,
+    pub discovered_files: DiscoveredFiles,
+    pub scanned_files: ScannedFiles,
+    pub threats_found: ThreatsFound,
+    /// 该根目录是否已遍历完成
+    pub discovery_complete: bool,
 }
 
 impl ScanProgress {
@@ -132,31 +163,286 @@ impl ScanProgress {
             current_file: None,
             discovered_files: DiscoveredFiles(0),
             scan_rate: None,
+            worker_files: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+}
+
+/// 文件未能完整扫描的原因分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// I/O 错误（打开、读取、映射失败等）
+    Io,
+    /// 权限不足
+    Permission,
+    /// 超出扫描限制（大小、嵌套深度、文件数）
+    Limits,
+    /// 加密内容
+    Encrypted,
+    /// 格式解析失败
+    Parse,
+    /// 扫描超时
+    Timeout,
+    /// 其他错误
+    Other,
+}
+
+impl ErrorCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Io => "io",
+            ErrorCategory::Permission => "permission",
+            ErrorCategory::Limits => "limits",
+            ErrorCategory::Encrypted => "encrypted",
+            ErrorCategory::Parse => "parse",
+            ErrorCategory::Timeout => "timeout",
+            ErrorCategory::Other => "other",
         }
     }
 }
 
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// 按分类统计的文件错误数量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorCounts {
+    pub io: u32,
+    pub permission: u32,
+    pub limits: u32,
+    pub encrypted: u32,
+    pub parse: u32,
+    pub timeout: u32,
+    pub other: u32,
+}
+
+impl ErrorCounts {
+    /// 记录一次错误
+    pub fn record(&mut self, category: ErrorCategory) {
+        let counter = match category {
+            ErrorCategory::Io => &mut self.io,
+            ErrorCategory::Permission => &mut self.permission,
+            ErrorCategory::Limits => &mut self.limits,
+            ErrorCategory::Encrypted => &mut self.encrypted,
+            ErrorCategory::Parse => &mut self.parse,
+            ErrorCategory::Timeout => &mut self.timeout,
+            ErrorCategory::Other => &mut self.other,
+        };
+        *counter += 1;
+    }
+
+    /// 累加另一组计数
+    pub fn add(&mut self, other: &ErrorCounts) {
+        self.io += other.io;
+        self.permission += other.permission;
+        self.limits += other.limits;
+        self.encrypted += other.encrypted;
+        self.parse += other.parse;
+        self.timeout += other.timeout;
+        self.other += other.other;
+    }
+
+    /// 错误总数
+    pub fn total(&self) -> u32 {
+        self.io + self.permission + self.limits + self.encrypted + self.parse + self.timeout + self.other
+    }
+}
+
 /// 扫描结果
 #[derive(Debug, Clone)]
 pub struct ScanOutcome {
     pub total_files: u32,
     pub scanned_files: u32,
-    pub threats: Vec<(FilePath, VirusName)>,
+    pub threats: Vec<DetectedThreat>,
     pub status: ScanStatus,
     pub error_message: Option<String>,
+    /// 未能完整扫描的文件，按原因分类计数
+    pub errors: ErrorCounts,
+    /// 各扫描根目录的结果（按提交顺序）
+    pub roots: Vec<RootOutcome>,
+    /// 因超出扫描限制（大小等）跳过的文件数
+    pub skipped_files: u32,
+    /// 被排除规则跳过的路径数（被排除的目录计为 1）
+    pub excluded_files: u32,
+    /// 扫描出错的文件数（I/O、权限、解析及其他错误）
+    pub errored_files: u32,
+    /// 扫描超时的文件数
+    pub timed_out_files: u32,
+    /// 未修改、使用缓存结果跳过的干净文件数（计入 scanned_files）
+    pub cached_files: u32,
+    /// 每个未被扫描（且非干净）的文件的记录
+    pub file_events: Vec<FileEvent>,
+}
+
+/// 文件未被扫描的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileEventReason {
+    /// 命中排除规则
+    Excluded,
+    /// 超出扫描限制
+    Skipped,
+    /// 扫描出错
+    Error,
+    /// 扫描超时
+    Timeout,
+}
+
+impl FileEventReason {
+    /// 由错误分类得到原因
+    pub fn from_category(category: ErrorCategory) -> Self {
+        match category {
+            ErrorCategory::Limits => FileEventReason::Skipped,
+            ErrorCategory::Timeout => FileEventReason::Timeout,
+            _ => FileEventReason::Error,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileEventReason::Excluded => "excluded",
+            FileEventReason::Skipped => "skipped",
+            FileEventReason::Error => "error",
+            FileEventReason::Timeout => "timeout",
+        }
+    }
+}
+
+/// 未被扫描的文件记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEvent {
+    pub path: String,
+    pub reason: FileEventReason,
+    /// 错误分类（排除时为 None）
+    pub category: Option<ErrorCategory>,
+    /// libclamav 错误码
+    pub error_code: Option<i32>,
+    /// 错误信息或命中的排除规则
+    pub detail: Option<String>,
+}
+
+impl FileEvent {
+    /// 被排除规则跳过的路径
+    pub fn excluded(path: String, rule: String) -> Self {
+        Self {
+            path,
+            reason: FileEventReason::Excluded,
+            category: None,
+            error_code: None,
+            detail: Some(rule),
+        }
+    }
+
+    /// 遍历时跳过的特殊文件（套接字、FIFO、设备文件）
+    pub fn skipped(path: String, detail: String) -> Self {
+        Self {
+            path,
+            reason: FileEventReason::Skipped,
+            category: None,
+            error_code: None,
+            detail: Some(detail),
+        }
+    }
+
+    /// 扫描失败的文件
+    pub fn failed(path: String, category: ErrorCategory, error_code: Option<i32>, detail: String) -> Self {
+        Self {
+            path,
+            reason: FileEventReason::from_category(category),
+            category: Some(category),
+            error_code,
+            detail: Some(detail),
+        }
+    }
+}
+
+/// 单个扫描根目录的结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RootOutcome {
+    pub path: String,
+    pub total_files: u32,
+    pub scanned_files: u32,
+    pub threats_found: u32,
+    pub errors: ErrorCounts,
+    /// 根目录无法扫描的原因（例如路径不存在）
+    pub error_message: Option<String>,
+}
+
+impl RootOutcome {
+    /// 无法扫描的根目录
+    pub fn failed(path: String, message: String) -> Self {
+        Self {
+            path,
+            total_files: 0,
+            scanned_files: 0,
+            threats_found: 0,
+            errors: ErrorCounts::default(),
+            error_message: Some(message),
+        }
+    }
 }
 
 impl ScanOutcome {
-    pub fn success(total_files: u32, scanned_files: u32, threats: Vec<(FilePath, VirusName)>) -> Self {
+    pub fn success(total_files: u32, scanned_files: u32, threats: Vec<DetectedThreat>) -> Self {
         Self {
             total_files,
             scanned_files,
             threats,
             status: ScanStatus::Completed,
             error_message: None,
+            errors: ErrorCounts::default(),
+            roots: Vec::new(),
+            skipped_files: 0,
+            excluded_files: 0,
+            errored_files: 0,
+            timed_out_files: 0,
+            cached_files: 0,
+            file_events: Vec::new(),
         }
     }
 
+    /// 附加文件错误统计，并据此计算跳过、出错和超时的文件数
+    ///
+    /// 加密内容的文件已被扫描，不计入出错
+    pub fn with_errors(mut self, errors: ErrorCounts) -> Self {
+        self.errors = errors;
+        self.skipped_files = errors.limits;
+        self.timed_out_files = errors.timeout;
+        self.errored_files = errors.io + errors.permission + errors.parse + errors.other;
+        self
+    }
+
+    /// 附加未被扫描文件的记录，并统计被排除的路径数
+    ///
+    /// 遍历时跳过的特殊文件和超过 max_file_size 的文件计入 skipped_files（需在 with_errors 之后调用）
+    pub fn with_file_events(mut self, events: Vec<FileEvent>) -> Self {
+        self.excluded_files = events.iter()
+            .filter(|e| e.reason == FileEventReason::Excluded)
+            .count() as u32;
+        self.skipped_files = self.errors.limits + events.iter()
+            .filter(|e| e.reason == FileEventReason::Skipped && e.category.is_none())
+            .count() as u32;
+        self.file_events = events;
+        self
+    }
+
+    /// 附加使用缓存结果跳过的文件数
+    pub fn with_cached_files(mut self, cached_files: u32) -> Self {
+        self.cached_files = cached_files;
+        self
+    }
+
+    /// 附加各根目录的结果
+    pub fn with_roots(mut self, roots: Vec<RootOutcome>) -> Self {
+        self.roots = roots;
+        self
+    }
+
     pub fn failed(message: String) -> Self {
         Self {
             total_files: 0,
@@ -164,6 +450,14 @@ impl ScanOutcome {
             threats: vec![],
             status: ScanStatus::Failed(message.clone()),
             error_message: Some(message),
+            errors: ErrorCounts::default(),
+            roots: Vec::new(),
+            skipped_files: 0,
+            excluded_files: 0,
+            errored_files: 0,
+            timed_out_files: 0,
+            cached_files: 0,
+            file_events: Vec::new(),
         }
     }
 }
@@ -215,4 +509,15 @@ mod tests {
         let name = VirusName("Eicar-Test-Signature".to_string());
         assert_eq!(format!("{}", name), "Eicar-Test-Signature");
     }
+
+    #[test]
+    fn test_error_counts_record() {
+        let mut counts = ErrorCounts::default();
+        counts.record(ErrorCategory::Permission);
+        counts.record(ErrorCategory::Permission);
+        counts.record(ErrorCategory::Timeout);
+        assert_eq!(counts.permission, 2);
+        assert_eq!(counts.timeout, 1);
+        assert_eq!(counts.total(), 3);
+    }
 }
@@ -0,0 +1,271 @@
+// 文件扫描结果缓存（增量扫描）
+//
+// NAS 上的大部分内容不会变化，目录扫描跳过未修改的干净文件：
+// - 以 (dev, inode) 为键，记录 size、mtime、ctime、扫描结果和扫描时的病毒库版本
+// - size、mtime、ctime 任一变化即视为已修改（ctime 由内核维护，touch 无法回退）
+// - 病毒库版本前进超过阈值后重新扫描，新签名可能识别出旧文件
+// - 引擎指纹（自定义签名、PUA 类别、资源限制、启发式和字节码设置）必须完全一致，
+//   这些变化不会改变病毒库版本
+// - 只跳过干净的结果；加密等未完整扫描的文件不写入缓存
+// - force_rescan 时不跳过任何文件，但仍然刷新缓存
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::ffi::EngineInfo;
+
+/// 默认阈值：病毒库版本前进超过该值后，缓存的干净结果失效
+pub const DEFAULT_VERSION_THRESHOLD: u32 = 10;
+
+/// 缓存的扫描结论
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    Infected,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Clean => "clean",
+            Verdict::Infected => "infected",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "clean" => Some(Verdict::Clean),
+            "infected" => Some(Verdict::Infected),
+            _ => None,
+        }
+    }
+}
+
+/// 判断文件是否修改所需的元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIdentity {
+    pub dev: u64,
+    pub ino: u64,
+    pub size: u64,
+    /// 修改时间（纳秒）
+    pub mtime_ns: i64,
+    /// 状态变化时间（纳秒）
+    pub ctime_ns: i64,
+}
+
+impl FileIdentity {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            size: metadata.size(),
+            mtime_ns: metadata.mtime() * 1_000_000_000 + metadata.mtime_nsec(),
+            ctime_ns: metadata.ctime() * 1_000_000_000 + metadata.ctime_nsec(),
+        }
+    }
+
+    pub fn of(path: &Path) -> Option<Self> {
+        std::fs::metadata(path).ok().map(|m| Self::from_metadata(&m))
+    }
+
+    /// 内容未变化（同一文件且大小、时间戳都相同）
+    pub fn is_unchanged(&self, other: &FileIdentity) -> bool {
+        self == other
+    }
+}
+
+/// 一条缓存记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedVerdict {
+    pub identity: FileIdentity,
+    pub verdict: Verdict,
+    /// 扫描时的病毒库版本（CL_ENGINE_DB_VERSION）
+    pub db_version: u32,
+    /// 扫描时的引擎指纹（EngineInfo.fingerprint）
+    pub engine_fingerprint: String,
+}
+
+/// 扫描结果缓存的存储后端
+pub trait VerdictCache: Send + Sync {
+    /// 按 (dev, inode) 查询
+    fn get(&self, dev: u64, ino: u64) -> Option<CachedVerdict>;
+
+    /// 写入或覆盖一条记录（可以缓冲，由 flush 落盘）
+    fn put(&self, entry: CachedVerdict);
+
+    /// 写入缓冲的记录
+    fn flush(&self) {}
+}
+
+/// 单次扫描使用缓存的方式
+#[derive(Clone)]
+pub struct VerdictCachePolicy {
+    store: Arc<dyn VerdictCache>,
+    version_threshold: u32,
+    force_rescan: bool,
+}
+
+impl fmt::Debug for VerdictCachePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerdictCachePolicy")
+            .field("version_threshold", &self.version_threshold)
+            .field("force_rescan", &self.force_rescan)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VerdictCachePolicy {
+    pub fn new(store: Arc<dyn VerdictCache>) -> Self {
+        Self {
+            store,
+            version_threshold: DEFAULT_VERSION_THRESHOLD,
+            force_rescan: false,
+        }
+    }
+
+    pub fn with_version_threshold(mut self, threshold: u32) -> Self {
+        self.version_threshold = threshold;
+        self
+    }
+
+    /// 强制完整重新扫描：不跳过任何文件，扫描结果仍然写入缓存
+    pub fn with_force_rescan(mut self, force: bool) -> Self {
+        self.force_rescan = force;
+        self
+    }
+
+    /// 文件未修改、上次扫描干净、引擎指纹相同且病毒库版本未超出阈值时可以跳过
+    pub fn can_skip(&self, identity: &FileIdentity, engine: &EngineInfo) -> bool {
+        if self.force_rescan {
+            return false;
+        }
+        match self.store.get(identity.dev, identity.ino) {
+            Some(cached) => {
+                cached.verdict == Verdict::Clean
+                    && cached.identity.is_unchanged(identity)
+                    && cached.engine_fingerprint == engine.fingerprint
+                    && engine.db_version.saturating_sub(cached.db_version) <= self.version_threshold
+            }
+            None => false,
+        }
+    }
+
+    /// 记录扫描结果
+    pub fn record(&self, identity: FileIdentity, verdict: Verdict, engine: &EngineInfo) {
+        self.store.put(CachedVerdict {
+            identity,
+            verdict,
+            db_version: engine.db_version,
+            engine_fingerprint: engine.fingerprint.clone(),
+        });
+    }
+
+    pub fn flush(&self) {
+        self.store.flush();
+    }
+}
+
+/// 内存中的缓存（测试和不需要持久化的场景）
+#[derive(Debug, Default)]
+pub struct MemoryVerdictCache {
+    entries: std::sync::Mutex<HashMap<(u64, u64), CachedVerdict>>,
+}
+
+impl MemoryVerdictCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl VerdictCache for MemoryVerdictCache {
+    fn get(&self, dev: u64, ino: u64) -> Option<CachedVerdict> {
+        self.entries.lock().unwrap().get(&(dev, ino)).cloned()
+    }
+
+    fn put(&self, entry: CachedVerdict) {
+        self.entries.lock().unwrap().insert((entry.identity.dev, entry.identity.ino), entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> FileIdentity {
+        FileIdentity { dev: 1, ino: 42, size: 100, mtime_ns: 1_000, ctime_ns: 2_000 }
+    }
+
+    fn engine(db_version: u32) -> EngineInfo {
+        EngineInfo { db_version, fingerprint: "0123456789abcdef".to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_unchanged_clean_file_is_skipped() {
+        let policy = VerdictCachePolicy::new(Arc::new(MemoryVerdictCache::new())).with_version_threshold(5);
+        assert!(!policy.can_skip(&identity(), &engine(100)));
+
+        policy.record(identity(), Verdict::Clean, &engine(100));
+        assert!(policy.can_skip(&identity(), &engine(100)));
+        assert!(policy.can_skip(&identity(), &engine(105)));
+
+        // 病毒库版本超出阈值
+        assert!(!policy.can_skip(&identity(), &engine(106)));
+
+        // 强制完整扫描
+        assert!(!policy.clone().with_force_rescan(true).can_skip(&identity(), &engine(100)));
+    }
+
+    #[test]
+    fn test_engine_fingerprint_change_invalidates_verdicts() {
+        let policy = VerdictCachePolicy::new(Arc::new(MemoryVerdictCache::new()));
+        policy.record(identity(), Verdict::Clean, &engine(100));
+
+        // 上传自定义签名等变化不改变病毒库版本，只改变指纹
+        let reloaded = EngineInfo { fingerprint: "fedcba9876543210".to_string(), ..engine(100) };
+        assert!(!policy.can_skip(&identity(), &reloaded));
+    }
+
+    #[test]
+    fn test_modified_or_infected_file_is_rescanned() {
+        let policy = VerdictCachePolicy::new(Arc::new(MemoryVerdictCache::new()));
+        policy.record(identity(), Verdict::Clean, &engine(100));
+
+        let mut changed = identity();
+        changed.ctime_ns += 1;
+        assert!(!policy.can_skip(&changed, &engine(100)));
+
+        let mut resized = identity();
+        resized.size += 1;
+        assert!(!policy.can_skip(&resized, &engine(100)));
+
+        policy.record(identity(), Verdict::Infected, &engine(100));
+        assert!(!policy.can_skip(&identity(), &engine(100)));
+    }
+
+    #[test]
+    fn test_identity_from_file() {
+        let path = std::env::temp_dir().join(format!("verdict-cache-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"data").unwrap();
+
+        let id = FileIdentity::of(&path).unwrap();
+        assert_eq!(id.size, 4);
+        assert_eq!(FileIdentity::of(&path), Some(id));
+
+        std::fs::write(&path, b"changed").unwrap();
+        assert!(!FileIdentity::of(&path).unwrap().is_unchanged(&id));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -67,6 +67,11 @@ impl FnosEnv {
         format!("{}/clamav", self.data_dir())
     }
 
+    /// 自定义签名库目录
+    pub fn custom_sigs_dir(&self) -> String {
+        format!("{}/custom-sigs", self.data_dir())
+    }
+
     /// 隔离区目录
     pub fn quarantine_dir(&self) -> String {
         format!("{}/quarantine", self.data_dir())
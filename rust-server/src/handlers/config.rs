@@ -1,10 +1,11 @@
 use axum::{extract::State, response::Json};
 use serde_json::json;
+use crate::clamav::Scanner;
 use crate::services::AppState;
 use crate::models::config::*;
 
-pub async fn get_config(
-    State(state): State<AppState>,
+pub async fn get_config<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<serde_json::Value> {
     // 尝试从文件读取配置
     let settings_file = state.env.settings_file();
@@ -26,12 +27,20 @@ pub async fn get_config(
         "scan_paths": config.scan.exclude_paths,
         "auto_update": config.update.auto_check,
         "quarantine_enabled": config.threat.auto_action,
-        "threat_action": config.threat.action
+        "threat_action": config.threat.action,
+        "max_file_size_mb": config.scan.max_file_size_mb,
+        "exclude_extensions": config.scan.exclude_extensions,
+        "follow_symlinks": config.scan.follow_symlinks,
+        "one_filesystem": config.scan.one_filesystem,
+        "verdict_cache": config.scan.verdict_cache,
+        "verdict_cache_version_threshold": config.scan.verdict_cache_version_threshold,
+        "verdict_cache_max_age_days": config.scan.verdict_cache_max_age_days,
+        "engine": config.engine
     }))
 }
 
-pub async fn update_config(
-    State(state): State<AppState>,
+pub async fn update_config<S: Scanner>(
+    State(state): State<AppState<S>>,
     Json(partial): Json<serde_json::Value>,
 ) -> Json<serde_json::Value> {
     // 读取现有配置
@@ -48,6 +57,10 @@ pub async fn update_config(
         AppConfig::default()
     };
 
+    // 记录修改前的引擎相关设置，用于判断是否需要重新加载引擎
+    let old_engine = config.engine.clone();
+    let old_max_file_size = config.scan.max_file_size_mb;
+
     // 支持前端发送的简化格式
     // 前端格式: { scan_paths, auto_update, quarantine_enabled, threat_action }
     if let Some(paths) = partial.get("scan_paths") {
@@ -89,12 +102,33 @@ pub async fn update_config(
                 .map(|s| s.to_string())
                 .collect();
         }
+        if let Some(exts) = scan.get("exclude_extensions").and_then(|v| v.as_array()) {
+            config.scan.exclude_extensions = exts.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect();
+        }
+        if let Some(v) = scan.get("follow_symlinks").and_then(|v| v.as_bool()) {
+            config.scan.follow_symlinks = v;
+        }
+        if let Some(v) = scan.get("one_filesystem").and_then(|v| v.as_bool()) {
+            config.scan.one_filesystem = v;
+        }
         if let Some(max_size) = scan.get("max_file_size_mb").and_then(|v| v.as_u64()) {
             config.scan.max_file_size_mb = max_size as u32;
         }
         if let Some(archives) = scan.get("scan_archives").and_then(|v| v.as_bool()) {
             config.scan.scan_archives = archives;
         }
+        if let Some(v) = scan.get("verdict_cache").and_then(|v| v.as_bool()) {
+            config.scan.verdict_cache = v;
+        }
+        if let Some(v) = scan.get("verdict_cache_version_threshold").and_then(|v| v.as_u64()) {
+            config.scan.verdict_cache_version_threshold = v as u32;
+        }
+        if let Some(v) = scan.get("verdict_cache_max_age_days").and_then(|v| v.as_u64()) {
+            config.scan.verdict_cache_max_age_days = v as u32;
+        }
     }
 
     if let Some(threat) = partial.get("threat").and_then(|v| v.as_object()) {
@@ -121,6 +155,97 @@ pub async fn update_config(
         }
     }
 
+    if let Some(max_size) = partial.get("max_file_size_mb").and_then(|v| v.as_u64()) {
+        config.scan.max_file_size_mb = max_size as u32;
+    }
+
+    if let Some(engine) = partial.get("engine").and_then(|v| v.as_object()) {
+        if let Some(v) = engine.get("max_scan_size_mb").and_then(|v| v.as_u64()) {
+            config.engine.max_scan_size_mb = v as u32;
+        }
+        if let Some(v) = engine.get("max_recursion").and_then(|v| v.as_u64()) {
+            config.engine.max_recursion = v as u32;
+        }
+        if let Some(v) = engine.get("max_files").and_then(|v| v.as_u64()) {
+            config.engine.max_files = v as u32;
+        }
+        if let Some(v) = engine.get("max_embedded_pe_mb").and_then(|v| v.as_u64()) {
+            config.engine.max_embedded_pe_mb = v as u32;
+        }
+        if let Some(v) = engine.get("scan_timeout").and_then(|v| v.as_u64()) {
+            config.engine.scan_timeout = v as u32;
+        }
+        if let Some(v) = engine.get("detect_pua").and_then(|v| v.as_bool()) {
+            config.engine.detect_pua = v;
+        }
+        if let Some(v) = engine.get("pua_include_categories").and_then(|v| v.as_array()) {
+            config.engine.pua_include_categories = v.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect();
+        }
+        if let Some(v) = engine.get("pua_exclude_categories").and_then(|v| v.as_array()) {
+            config.engine.pua_exclude_categories = v.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect();
+        }
+        if let Some(v) = engine.get("official_db_only").and_then(|v| v.as_bool()) {
+            config.engine.official_db_only = v;
+        }
+        if let Some(v) = engine.get("phishing_signatures").and_then(|v| v.as_bool()) {
+            config.engine.phishing_signatures = v;
+        }
+        if let Some(v) = engine.get("phishing_scan_urls").and_then(|v| v.as_bool()) {
+            config.engine.phishing_scan_urls = v;
+        }
+        if let Some(v) = engine.get("alert_encrypted_archive").and_then(|v| v.as_bool()) {
+            config.engine.alert_encrypted_archive = v;
+        }
+        if let Some(v) = engine.get("alert_encrypted_doc").and_then(|v| v.as_bool()) {
+            config.engine.alert_encrypted_doc = v;
+        }
+        if let Some(v) = engine.get("alert_broken_executables").and_then(|v| v.as_bool()) {
+            config.engine.alert_broken_executables = v;
+        }
+        if let Some(v) = engine.get("alert_phishing_ssl_mismatch").and_then(|v| v.as_bool()) {
+            config.engine.alert_phishing_ssl_mismatch = v;
+        }
+        if let Some(v) = engine.get("alert_phishing_cloak").and_then(|v| v.as_bool()) {
+            config.engine.alert_phishing_cloak = v;
+        }
+        if let Some(v) = engine.get("alert_ole2_macros").and_then(|v| v.as_bool()) {
+            config.engine.alert_ole2_macros = v;
+        }
+        if let Some(v) = engine.get("alert_partition_intersection").and_then(|v| v.as_bool()) {
+            config.engine.alert_partition_intersection = v;
+        }
+        if let Some(v) = engine.get("detect_structured_cc").and_then(|v| v.as_bool()) {
+            config.engine.detect_structured_cc = v;
+        }
+        if let Some(v) = engine.get("detect_structured_ssn").and_then(|v| v.as_bool()) {
+            config.engine.detect_structured_ssn = v;
+        }
+        if let Some(v) = engine.get("alert_exceeds_max").and_then(|v| v.as_bool()) {
+            config.engine.alert_exceeds_max = v;
+        }
+        if let Some(v) = engine.get("heuristic_scan").and_then(|v| v.as_bool()) {
+            config.engine.heuristic_scan = v;
+        }
+        if let Some(v) = engine.get("bytecode_trusted_only").and_then(|v| v.as_bool()) {
+            config.engine.bytecode_trusted_only = v;
+        }
+        if let Some(v) = engine.get("bytecode_timeout_ms").and_then(|v| v.as_u64()) {
+            config.engine.bytecode_timeout_ms = v;
+        }
+        if let Some(v) = engine.get("engine_cache").and_then(|v| v.as_bool()) {
+            config.engine.engine_cache = v;
+        }
+        if let Some(v) = engine.get("cache_size").and_then(|v| v.as_u64()) {
+            config.engine.cache_size = v as u32;
+        }
+    }
+
     if let Some(history) = partial.get("history").and_then(|v| v.as_object()) {
         if let Some(days) = history.get("retention_days").and_then(|v| v.as_u64()) {
             config.history.retention_days = days as u32;
@@ -130,11 +255,40 @@ pub async fn update_config(
         }
     }
 
+    if let Some(exts) = partial.get("exclude_extensions").and_then(|v| v.as_array()) {
+        config.scan.exclude_extensions = exts.iter()
+            .filter_map(|v| v.as_str())
+            .map(|s| s.to_string())
+            .collect();
+    }
+
+    // 排除规则无效时不保存
+    if let Err(e) = config.scan.validate_exclusions() {
+        return Json(json!({
+            "success": false,
+            "error": e.to_string()
+        }));
+    }
+
     // 保存配置
     let config_json = serde_json::to_string_pretty(&config).unwrap();
     match std::fs::write(&settings_file, config_json) {
         Ok(_) => {
             tracing::info!("Configuration saved to {}", settings_file);
+
+            // 引擎设置变化时在后台重新加载引擎
+            if config.engine != old_engine || config.scan.max_file_size_mb != old_max_file_size {
+                let clamav = state.clamav.clone();
+                let mut clamav_config = clamav.get_config();
+                clamav_config.apply_settings(&config);
+                tokio::spawn(async move {
+                    tracing::info!("Engine settings changed, reloading engine");
+                    if let Err(e) = clamav.apply_config(clamav_config).await {
+                        tracing::error!("Failed to apply engine settings: {}", e);
+                    }
+                });
+            }
+
             Json(json!({
                 "success": true,
                 "message": "配置已保存"
@@ -156,5 +310,6 @@ fn get_default_config() -> ConfigResponse {
         threat: crate::models::config::ThreatConfig::default(),
         update: crate::models::config::UpdateConfig::default(),
         history: crate::models::config::HistoryConfig::default(),
+        engine: crate::models::config::EngineConfig::default(),
     }
 }
@@ -0,0 +1,21 @@
+use axum::{response::Json, extract::State};
+use serde_json::json;
+use crate::clamav::Scanner;
+use crate::services::AppState;
+
+pub async fn engine_info<S: Scanner>(State(state): State<AppState<S>>) -> Json<serde_json::Value> {
+    let engine_state = state.clamav.get_engine_state().await;
+
+    match state.clamav.engine_info() {
+        Some(info) => Json(json!({
+            "success": true,
+            "state": format!("{:?}", engine_state),
+            "engine": info
+        })),
+        None => Json(json!({
+            "success": false,
+            "state": format!("{:?}", engine_state),
+            "error": engine_state.error_message().unwrap_or("Engine not loaded")
+        })),
+    }
+}
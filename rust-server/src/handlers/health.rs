@@ -1,5 +1,6 @@
 use axum::{response::Json, extract::State};
 use serde_json::json;
+use crate::clamav::Scanner;
 use crate::services::AppState;
 
 pub async fn health_check() -> Json<serde_json::Value> {
@@ -10,7 +11,7 @@ pub async fn health_check() -> Json<serde_json::Value> {
     }))
 }
 
-pub async fn status(State(state): State<AppState>) -> Json<serde_json::Value> {
+pub async fn status<S: Scanner>(State(state): State<AppState<S>>) -> Json<serde_json::Value> {
     let scan_service = state.scan_service.read().await;
     let clamav_service = &state.clamav;
 
@@ -36,17 +37,25 @@ pub async fn status(State(state): State<AppState>) -> Json<serde_json::Value> {
         match &engine_state {
             crate::clamav::EngineState::Initializing => "initializing",
             crate::clamav::EngineState::Error(_) => "error",
+            crate::clamav::EngineState::Unavailable(_) => "engine_unavailable",
             crate::clamav::EngineState::Failed => "failed",
             _ => "starting",
         }
     };
 
+    // 已加载引擎实际应用的设置，引擎未就绪时返回待应用的配置
+    let engine_settings = clamav_service.engine_info()
+        .map(|info| info.settings)
+        .unwrap_or_else(|| clamav_service.get_config().engine_settings());
+
     Json(json!({
         "status": service_status,
         "version": "1.0.0",
         "service": "clamav-daemon",
         "scan_in_progress": is_scanning,
         "current_scan_id": scan_id,
-        "engine_ready": is_engine_ready
+        "engine_ready": is_engine_ready,
+        "engine_error": engine_state.error_message(),
+        "engine_settings": engine_settings
     }))
 }
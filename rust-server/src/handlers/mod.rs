@@ -4,6 +4,8 @@ pub mod update;
 pub mod config;
 pub mod threat;
 pub mod quarantine;
+pub mod signature;
+pub mod engine;
 
 pub use health::*;
 pub use scan::*;
@@ -11,3 +13,5 @@ pub use update::*;
 pub use config::*;
 pub use threat::*;
 pub use quarantine::*;
+pub use signature::*;
+pub use engine::*;
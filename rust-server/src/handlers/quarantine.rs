@@ -1,10 +1,11 @@
 use axum::{extract::{Path, State}, response::Json};
 use serde_json::json;
+use crate::clamav::Scanner;
 use crate::services::{AppState, QuarantineService};
 use crate::models::quarantine::*;
 
-pub async fn list_quarantine(
-    State(state): State<AppState>,
+pub async fn list_quarantine<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<QuarantineListResponse> {
     let service = QuarantineService::new(state.env.clone());
 
@@ -26,8 +27,8 @@ pub async fn list_quarantine(
     }
 }
 
-pub async fn restore_quarantine(
-    State(state): State<AppState>,
+pub async fn restore_quarantine<S: Scanner>(
+    State(state): State<AppState<S>>,
     Path(uuid): Path<String>,
 ) -> Json<QuarantineRestoreResponse> {
     let service = QuarantineService::new(state.env.clone());
@@ -51,8 +52,8 @@ pub async fn restore_quarantine(
     }
 }
 
-pub async fn delete_quarantine(
-    State(state): State<AppState>,
+pub async fn delete_quarantine<S: Scanner>(
+    State(state): State<AppState<S>>,
     Path(uuid): Path<String>,
 ) -> Json<serde_json::Value> {
     let service = QuarantineService::new(state.env.clone());
@@ -74,8 +75,8 @@ pub async fn delete_quarantine(
     }
 }
 
-pub async fn cleanup_quarantine(
-    State(state): State<AppState>,
+pub async fn cleanup_quarantine<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<QuarantineCleanupResponse> {
     let service = QuarantineService::new(state.env.clone());
 
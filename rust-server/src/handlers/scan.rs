@@ -1,16 +1,34 @@
-use axum::{extract::State, response::Json};
+use axum::{extract::{Query, State}, response::Json};
 use serde_json::json;
+use crate::clamav::Scanner;
 use crate::services::AppState;
 use crate::models::scan::*;
+use crate::models::config::AppConfig;
 use crate::clamav::engine::TaskPriority;
-use crate::clamav::ScanOptions;
 
-pub async fn start_scan(
-    State(state): State<AppState>,
+pub async fn start_scan<S: Scanner>(
+    State(state): State<AppState<S>>,
     Json(req): Json<ScanRequest>,
 ) -> Json<ScanResponse> {
+    // 引擎未就绪（例如 libclamav 无法加载）时拒绝扫描
+    let engine_state = state.clamav.get_engine_state().await;
+    if !engine_state.is_ready() {
+        return Json(ScanResponse {
+            success: false,
+            scan_id: None,
+            status: None,
+            error: Some(format!(
+                "ClamAV engine not ready: {}",
+                engine_state.error_message().unwrap_or("initializing")
+            )),
+        });
+    }
+
     let scan_id = format!("scan_{:}", chrono::Utc::now().format("%Y%m%d_%H%M%S"));
 
+    // 原始请求随检查点保存，服务重启后按相同参数继续扫描
+    let request_json = serde_json::to_string(&req).unwrap_or_default();
+
     // 确定扫描路径
     let paths = if req.scan_type == ScanType::Full {
         // 全盘扫描：从 /proc/mounts 获取挂载点
@@ -28,6 +46,20 @@ pub async fn start_scan(
         });
     }
 
+    // 构建遍历选项和排除规则（全局配置 + 本次扫描），规则无效时拒绝扫描
+    let app_config = AppConfig::load_or_default(&state.env.settings_file());
+    let discovery = match app_config.scan.discovery_options(&req.exclude, req.follow_symlinks, req.one_filesystem) {
+        Ok(discovery) => discovery,
+        Err(e) => {
+            return Json(ScanResponse {
+                success: false,
+                scan_id: None,
+                status: None,
+                error: Some(e.to_string()),
+            });
+        }
+    };
+
     // 创建数据库记录
     let scan_type_str = match req.scan_type {
         ScanType::Full => "full",
@@ -43,13 +75,28 @@ pub async fn start_scan(
         });
     }
 
-    // 启动后台扫描
-    let result = state.scan_service.write().await
+    if let Err(e) = state.db.create_scan_checkpoint(&scan_id, &request_json) {
+        tracing::warn!("Failed to create checkpoint for scan {}, it will not be resumable: {}", scan_id, e);
+    }
+
+    // 解析本次扫描的解析器选项
+    let options = req.options
+        .as_ref()
+        .map(|o| o.to_scan_options())
+        .unwrap_or_default();
+
+    // 启动后台扫描（增量扫描时跳过未修改的干净文件）
+    let scan_service = state.scan_service.write().await;
+    scan_service.prune_verdict_cache(app_config.scan.verdict_cache_max_age_days);
+    let cache = app_config.scan.verdict_cache_policy(scan_service.verdict_cache(), req.force_full_rescan);
+    let result = scan_service
         .start_scan(
             scan_id.clone(),
             paths.clone(),
             TaskPriority::Normal,
-            ScanOptions::default(),
+            options,
+            discovery,
+            cache,
         ).await;
 
     match result {
@@ -68,8 +115,8 @@ pub async fn start_scan(
     }
 }
 
-pub async fn stop_scan(
-    State(state): State<AppState>,
+pub async fn stop_scan<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<serde_json::Value> {
     let scan_service = state.scan_service.read().await;
     let scan_id = scan_service.get_current_scan_id().await;
@@ -96,8 +143,129 @@ pub async fn stop_scan(
     }
 }
 
-pub async fn scan_status(
-    State(state): State<AppState>,
+pub async fn pause_scan<S: Scanner>(
+    State(state): State<AppState<S>>,
+) -> Json<serde_json::Value> {
+    let scan_service = state.scan_service.read().await;
+    let scan_id = scan_service.get_current_scan_id().await;
+
+    match scan_id {
+        Some(sid) => match scan_service.pause_scan(&sid).await {
+            Ok(()) => Json(json!({
+                "success": true,
+                "scan_id": sid,
+                "status": "paused"
+            })),
+            Err(e) => Json(json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        },
+        None => Json(json!({
+            "success": false,
+            "error": "No scan in progress"
+        })),
+    }
+}
+
+pub async fn resume_scan<S: Scanner>(
+    State(state): State<AppState<S>>,
+) -> Json<serde_json::Value> {
+    let scan_service = state.scan_service.read().await;
+    let scan_id = scan_service.get_current_scan_id().await;
+
+    match scan_id {
+        Some(sid) => match scan_service.resume_scan(&sid).await {
+            Ok(()) => Json(json!({
+                "success": true,
+                "scan_id": sid,
+                "status": "scanning"
+            })),
+            Err(e) => Json(json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        },
+        None => Json(json!({
+            "success": false,
+            "error": "No scan in progress"
+        })),
+    }
+}
+
+/// 列出被服务重启中断、可以从检查点继续的扫描
+pub async fn resumable_scans<S: Scanner>(
+    State(state): State<AppState<S>>,
+) -> Json<serde_json::Value> {
+    match state.db.get_interrupted_scans() {
+        Ok(records) => {
+            let scans: Vec<serde_json::Value> = records.into_iter()
+                .map(|r| {
+                    let checkpoint = state.db.get_scan_checkpoint(&r.scan_id).ok().flatten();
+                    let paths: Vec<String> = serde_json::from_str(&r.paths).unwrap_or_default();
+                    json!({
+                        "scan_id": r.scan_id,
+                        "scan_type": r.scan_type,
+                        "paths": paths,
+                        "start_time": r.start_time,
+                        "scanned_files": r.scanned_files,
+                        "total_files": r.total_files,
+                        "checkpoint_time": checkpoint.map(|c| c.updated_at),
+                    })
+                })
+                .collect();
+            Json(json!({
+                "success": true,
+                "scans": scans
+            }))
+        }
+        Err(e) => Json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// 从检查点继续被中断的扫描，沿用原来的 scan_id
+pub async fn resume_interrupted_scan<S: Scanner>(
+    State(state): State<AppState<S>>,
+    axum::extract::Path(scan_id): axum::extract::Path<String>,
+) -> Json<serde_json::Value> {
+    let engine_state = state.clamav.get_engine_state().await;
+    if !engine_state.is_ready() {
+        return Json(json!({
+            "success": false,
+            "error": format!(
+                "ClamAV engine not ready: {}",
+                engine_state.error_message().unwrap_or("initializing")
+            )
+        }));
+    }
+
+    let scan_service = state.scan_service.read().await;
+    if scan_service.is_scanning().await {
+        return Json(json!({
+            "success": false,
+            "error": "Another scan is in progress"
+        }));
+    }
+
+    let app_config = AppConfig::load_or_default(&state.env.settings_file());
+    match scan_service.resume_interrupted_scan(&scan_id, &app_config).await {
+        Ok(_) => Json(json!({
+            "success": true,
+            "scan_id": scan_id,
+            "status": "scanning"
+        })),
+        Err(e) => Json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+    }
+}
+
+pub async fn scan_status<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<ScanStatusResponse> {
     // 首先检查是否有活跃的扫描（从内存中获取实时数据）
     let scan_service = state.scan_service.read().await;
@@ -111,7 +279,7 @@ pub async fn scan_status(
     if let (Some(scan_id), Some(progress)) = (current_scan_id, realtime_progress) {
         let elapsed = chrono::Utc::now().timestamp() - progress.created_at.timestamp();
         let scan_status = progress.status.clone();
-        let is_scanning = scan_status == "scanning";
+        let is_scanning = scan_status == "scanning" || scan_status == "paused";
 
         // 使用 discovered_files 计算进度（更准确的实时总数）
         let effective_total = if progress.discovered_files > 0 {
@@ -143,6 +311,8 @@ pub async fn scan_status(
                     } else {
                         None
                     },
+                    worker_files: progress.worker_files.clone(),
+                    roots: progress.roots.clone(),
                 })
             } else {
                 None
@@ -161,7 +331,7 @@ pub async fn scan_status(
         Ok(Some(scan)) => {
             let elapsed = chrono::Utc::now().timestamp() - scan.start_time;
             let scan_status = scan.status.clone();
-            let is_scanning = scan_status == "scanning";
+            let is_scanning = scan_status == "scanning" || scan_status == "paused";
 
             Json(ScanStatusResponse {
                 scan_id: Some(scan.scan_id),
@@ -178,6 +348,8 @@ pub async fn scan_status(
                         current_file: scan.current_file.unwrap_or_default(),
                         discovered: None,
                         scan_rate: None,
+                        worker_files: Vec::new(),
+                        roots: Vec::new(),
                     })
                 } else {
                     None
@@ -203,8 +375,8 @@ pub async fn scan_status(
     }
 }
 
-pub async fn scan_history(
-    State(state): State<AppState>,
+pub async fn scan_history<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<serde_json::Value> {
     match state.db.get_scan_history(50) {
         Ok(history) => {
@@ -220,7 +392,23 @@ pub async fn scan_history(
                     "total_files": h.total_files,
                     "scanned_files": h.scanned_files,
                     "threats_found": h.threats_found,
-                    "error_message": h.error_message
+                    "error_message": h.error_message,
+                    "skipped_files": h.file_counts.skipped_files,
+                    "excluded_files": h.file_counts.excluded_files,
+                    "errored_files": h.file_counts.errored_files,
+                    "timed_out_files": h.file_counts.timed_out_files,
+                    "cached_files": h.file_counts.cached_files,
+                    "roots": state.db.get_scan_roots(&h.scan_id).unwrap_or_default()
+                        .into_iter()
+                        .map(|r| json!({
+                            "path": r.root_path,
+                            "total_files": r.total_files,
+                            "scanned_files": r.scanned_files,
+                            "threats_found": r.threats_found,
+                            "error_count": r.error_count,
+                            "error_message": r.error_message
+                        }))
+                        .collect::<Vec<_>>()
                 })
             }).collect();
 
@@ -238,8 +426,8 @@ pub async fn scan_history(
 }
 
 /// 删除单条扫描历史记录
-pub async fn delete_scan_history(
-    State(state): State<AppState>,
+pub async fn delete_scan_history<S: Scanner>(
+    State(state): State<AppState<S>>,
     axum::extract::Path(id): axum::extract::Path<i64>,
 ) -> Json<serde_json::Value> {
     match state.db.delete_scan_history(id) {
@@ -254,8 +442,8 @@ pub async fn delete_scan_history(
 }
 
 /// 清空所有扫描历史记录
-pub async fn clear_scan_history(
-    State(state): State<AppState>,
+pub async fn clear_scan_history<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<serde_json::Value> {
     match state.db.clear_scan_history() {
         Ok(()) => Json(json!({
@@ -268,6 +456,173 @@ pub async fn clear_scan_history(
     }
 }
 
+/// 扫描结果缓存统计
+pub async fn verdict_cache_stats<S: Scanner>(
+    State(state): State<AppState<S>>,
+) -> Json<serde_json::Value> {
+    let Some(store) = state.scan_service.read().await.verdict_store() else {
+        return Json(json!({
+            "success": false,
+            "error": "Verdict cache unavailable"
+        }));
+    };
+
+    match tokio::task::spawn_blocking(move || store.stats()).await {
+        Ok(Ok(stats)) => Json(json!({
+            "success": true,
+            "stats": stats
+        })),
+        Ok(Err(e)) => Json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+        Err(e) => Json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// 清空扫描结果缓存（下次扫描时所有文件完整扫描）
+pub async fn clear_verdict_cache<S: Scanner>(
+    State(state): State<AppState<S>>,
+) -> Json<serde_json::Value> {
+    let Some(store) = state.scan_service.read().await.verdict_store() else {
+        return Json(json!({
+            "success": false,
+            "error": "Verdict cache unavailable"
+        }));
+    };
+
+    match tokio::task::spawn_blocking(move || store.clear()).await {
+        Ok(Ok(removed)) => Json(json!({
+            "success": true,
+            "removed": removed
+        })),
+        Ok(Err(e)) => Json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+        Err(e) => Json(json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// 扫描文件事件分页参数
+#[derive(Debug, serde::Deserialize)]
+pub struct FileEventQuery {
+    #[serde(default = "default_page")]
+    pub page: i64,
+    #[serde(default = "default_page_size")]
+    pub page_size: i64,
+    /// 按原因过滤：excluded / skipped / error / timeout
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+fn default_page_size() -> i64 {
+    100
+}
+
+/// 分页获取某次扫描中未被扫描的文件及原因
+pub async fn scan_file_events<S: Scanner>(
+    State(state): State<AppState<S>>,
+    axum::extract::Path(scan_id): axum::extract::Path<String>,
+    Query(query): Query<FileEventQuery>,
+) -> Json<serde_json::Value> {
+    let page = query.page.max(1);
+    let page_size = query.page_size.clamp(1, 1000);
+
+    match state.db.get_scan_file_events(&scan_id, query.reason.as_deref(), (page - 1) * page_size, page_size) {
+        Ok((events, total)) => {
+            let items: Vec<serde_json::Value> = events.into_iter().map(|e| {
+                json!({
+                    "id": e.id,
+                    "file_path": e.file_path,
+                    "reason": e.reason,
+                    "category": e.category,
+                    "error_code": e.error_code,
+                    "detail": e.detail
+                })
+            }).collect();
+
+            Json(json!({
+                "success": true,
+                "scan_id": scan_id,
+                "items": items,
+                "page": page,
+                "page_size": page_size,
+                "total": total
+            }))
+        }
+        Err(e) => Json(json!({
+            "success": false,
+            "error": e.to_string()
+        }))
+    }
+}
+
+/// 排除规则查询参数
+#[derive(Debug, serde::Deserialize)]
+pub struct ExclusionQuery {
+    pub path: String,
+    /// 额外的本次扫描排除规则（逗号分隔）
+    #[serde(default)]
+    pub exclude: Option<String>,
+}
+
+/// 说明指定路径是否会被排除，以及命中的规则
+pub async fn explain_exclusion<S: Scanner>(
+    State(state): State<AppState<S>>,
+    Query(query): Query<ExclusionQuery>,
+) -> Json<serde_json::Value> {
+    let scan_excludes: Vec<String> = query.exclude
+        .as_deref()
+        .map(|s| s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default();
+
+    let app_config = AppConfig::load_or_default(&state.env.settings_file());
+    let rules = match app_config.scan.exclusion_rules(&scan_excludes) {
+        Ok(rules) => rules,
+        Err(e) => {
+            return Json(json!({
+                "success": false,
+                "error": e.to_string()
+            }));
+        }
+    };
+
+    let path = std::path::Path::new(&query.path);
+    let is_dir = path.is_dir();
+
+    // 检查路径本身及其各级父目录：父目录被排除时整棵子树都不会遍历
+    let matched = path.ancestors()
+        .enumerate()
+        .find_map(|(depth, p)| rules.explain(p, depth > 0 || is_dir).map(|m| (p, m)));
+
+    match matched {
+        Some((excluded_path, rule)) => Json(json!({
+            "success": true,
+            "path": query.path,
+            "excluded": true,
+            "excluded_path": excluded_path.display().to_string(),
+            "rule": rule
+        })),
+        None => Json(json!({
+            "success": true,
+            "path": query.path,
+            "excluded": false,
+            "rule_count": rules.len()
+        })),
+    }
+}
+
 // 获取全盘扫描路径
 fn get_full_scan_paths() -> Vec<String> {
     let mut paths = Vec::new();
@@ -0,0 +1,123 @@
+use axum::{extract::{Path, State}, response::Json};
+use crate::clamav::Scanner;
+use crate::services::{AppState, SignatureService};
+use crate::models::signature::*;
+
+pub async fn list_signatures<S: Scanner>(
+    State(state): State<AppState<S>>,
+) -> Json<SignatureListResponse> {
+    let service = SignatureService::new(state.env.clone());
+
+    let items = service.list_files().unwrap_or_else(|e| {
+        tracing::warn!("Failed to list custom signatures: {}", e);
+        vec![]
+    });
+
+    Json(SignatureListResponse {
+        directory: service.dir().to_string(),
+        total: items.len() as u32,
+        items,
+    })
+}
+
+pub async fn upload_signature<S: Scanner>(
+    State(state): State<AppState<S>>,
+    Json(req): Json<SignatureUploadRequest>,
+) -> Json<SignatureResponse> {
+    let service = SignatureService::new(state.env.clone());
+    let name = req.name.clone();
+
+    // 试编译需要加载签名，放到阻塞线程执行
+    let result = tokio::task::spawn_blocking(move || service.install(&req.name, &req.content))
+        .await
+        .unwrap_or_else(|e| Err(format!("Validation task failed: {}", e)));
+
+    match result {
+        Ok(signatures) => {
+            tracing::info!("Installed custom signature file {} ({} signatures)", name, signatures);
+            let (reloaded, error) = reload_engine(&state).await;
+            Json(SignatureResponse {
+                success: true,
+                name,
+                signatures: Some(signatures),
+                reloaded,
+                error,
+            })
+        }
+        Err(e) => Json(SignatureResponse {
+            success: false,
+            name,
+            signatures: None,
+            reloaded: false,
+            error: Some(e),
+        }),
+    }
+}
+
+pub async fn validate_signature<S: Scanner>(
+    State(state): State<AppState<S>>,
+    Json(req): Json<SignatureUploadRequest>,
+) -> Json<SignatureResponse> {
+    let service = SignatureService::new(state.env.clone());
+    let name = req.name.clone();
+
+    let result = tokio::task::spawn_blocking(move || service.validate(&req.name, &req.content))
+        .await
+        .unwrap_or_else(|e| Err(format!("Validation task failed: {}", e)));
+
+    match result {
+        Ok(signatures) => Json(SignatureResponse {
+            success: true,
+            name,
+            signatures: Some(signatures),
+            reloaded: false,
+            error: None,
+        }),
+        Err(e) => Json(SignatureResponse {
+            success: false,
+            name,
+            signatures: None,
+            reloaded: false,
+            error: Some(e),
+        }),
+    }
+}
+
+pub async fn delete_signature<S: Scanner>(
+    State(state): State<AppState<S>>,
+    Path(name): Path<String>,
+) -> Json<SignatureResponse> {
+    let service = SignatureService::new(state.env.clone());
+
+    match service.delete_file(&name) {
+        Ok(()) => {
+            tracing::info!("Deleted custom signature file {}", name);
+            let (reloaded, error) = reload_engine(&state).await;
+            Json(SignatureResponse {
+                success: true,
+                name,
+                signatures: None,
+                reloaded,
+                error,
+            })
+        }
+        Err(e) => Json(SignatureResponse {
+            success: false,
+            name,
+            signatures: None,
+            reloaded: false,
+            error: Some(e),
+        }),
+    }
+}
+
+/// 签名文件变更后重新加载引擎，返回 (是否成功, 错误信息)
+async fn reload_engine<S: Scanner>(state: &AppState<S>) -> (bool, Option<String>) {
+    match state.clamav.reload_engine().await {
+        Ok(()) => (true, None),
+        Err(e) => {
+            tracing::error!("Engine reload after signature change failed: {}", e);
+            (false, Some(e.to_string()))
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use axum::{extract::{Path, State}, response::Json};
 use serde_json::json;
+use crate::clamav::Scanner;
 use crate::services::AppState;
 use crate::models::threat::*;
 
-pub async fn list_threats(
-    State(state): State<AppState>,
+pub async fn list_threats<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<ThreatsListResponse> {
     match state.db.get_threats(None, 100) {
         Ok(threats) => {
@@ -20,6 +21,9 @@ pub async fn list_threats(
                     action_taken: t.action_taken,
                     quarantine_uuid: t.original_location,
                     action_time: t.action_time,
+                    file_hash: t.file_hash,
+                    file_type: t.file_type,
+                    container_path: t.container_path,
                 }
             }).collect();
 
@@ -35,8 +39,8 @@ pub async fn list_threats(
     }
 }
 
-pub async fn handle_threat(
-    State(state): State<AppState>,
+pub async fn handle_threat<S: Scanner>(
+    State(state): State<AppState<S>>,
     Path(id): Path<i64>,
     Json(req): Json<ThreatHandleRequest>,
 ) -> Json<ThreatHandleResponse> {
@@ -69,6 +73,7 @@ pub async fn handle_threat(
                 &threat.virus_name,
                 &threat.scan_id,
                 0,  // file_size - TODO: 获取实际文件大小
+                threat.file_hash.as_deref(),
             ) {
                 Ok(uuid) => {
                     // 更新威胁记录
@@ -86,6 +91,9 @@ pub async fn handle_threat(
                             action_taken: Some("quarantined".to_string()),
                             quarantine_uuid: Some(uuid),
                             action_time: Some(now),
+                            file_hash: threat.file_hash.clone(),
+                            file_type: threat.file_type.clone(),
+                            container_path: threat.container_path.clone(),
                         }),
                         error: None,
                     })
@@ -116,6 +124,9 @@ pub async fn handle_threat(
                             action_taken: Some("deleted".to_string()),
                             quarantine_uuid: None,
                             action_time: Some(now),
+                            file_hash: threat.file_hash.clone(),
+                            file_type: threat.file_type.clone(),
+                            container_path: threat.container_path.clone(),
                         }),
                         error: None,
                     })
@@ -143,6 +154,9 @@ pub async fn handle_threat(
                     action_taken: Some("ignored".to_string()),
                     quarantine_uuid: None,
                     action_time: Some(now),
+                    file_hash: threat.file_hash.clone(),
+                    file_type: threat.file_type.clone(),
+                    container_path: threat.container_path.clone(),
                 }),
                 error: None,
             })
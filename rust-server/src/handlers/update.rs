@@ -1,10 +1,11 @@
 use axum::{extract::State, response::Json};
 use serde_json::json;
+use crate::clamav::Scanner;
 use crate::services::AppState;
 use crate::models::update::*;
 
-pub async fn start_update(
-    State(state): State<AppState>,
+pub async fn start_update<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<UpdateResponse> {
     let result = state.update_service.write().await
         .start_update().await;
@@ -27,8 +28,8 @@ pub async fn start_update(
     }
 }
 
-pub async fn update_status(
-    State(state): State<AppState>,
+pub async fn update_status<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<UpdateStatusResponse> {
     let status = state.update_service.read().await.get_status().await;
 
@@ -61,8 +62,8 @@ pub async fn update_status(
     })
 }
 
-pub async fn update_version(
-    State(_state): State<AppState>,
+pub async fn update_version<S: Scanner>(
+    State(_state): State<AppState<S>>,
 ) -> Json<serde_json::Value> {
     // 读取病毒库目录获取版本信息
     let db_dir = std::env::var("TRIM_DATA_SHARE_PATHS")
@@ -126,8 +127,8 @@ pub async fn update_version(
     }))
 }
 
-pub async fn update_history(
-    State(state): State<AppState>,
+pub async fn update_history<S: Scanner>(
+    State(state): State<AppState<S>>,
 ) -> Json<serde_json::Value> {
     match state.db.get_update_history(50) {
         Ok(history) => {
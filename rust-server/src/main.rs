@@ -16,8 +16,9 @@ use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use models::config::ConfigResponse;
+use clamav::Scanner;
 
-use handlers::{scan, update, config, threat, quarantine, health};
+use handlers::{scan, update, config, threat, quarantine, health, signature, engine};
 
 // 服务器端口
 const SERVER_PORT: u16 = 8899;
@@ -42,6 +43,7 @@ async fn main() -> anyhow::Result<()> {
     // 确保必要的目录存在
     std::fs::create_dir_all(env.data_dir())?;
     std::fs::create_dir_all(env.clamav_db_dir())?;
+    std::fs::create_dir_all(env.custom_sigs_dir())?;
     std::fs::create_dir_all(env.quarantine_dir())?;
     std::fs::create_dir_all(&format!("{}/metadata", env.quarantine_dir()))?;
     std::fs::create_dir_all(&format!("{}/files", env.quarantine_dir()))?;
@@ -60,14 +62,16 @@ async fn main() -> anyhow::Result<()> {
     // 初始化 ClamAV 引擎（FFI 版本）
     tracing::info!("Initializing ClamAV engine...");
 
-    // 初始化引擎
+    // 初始化引擎；失败时不退出，HTTP API 以引擎不可用状态运行并通过 /api/status 报告原因
     {
         let scan_service = app_state.scan_service.read().await;
-        if let Err(e) = scan_service.clamav.initialize().await {
-            tracing::error!("Failed to initialize ClamAV engine: {}", e);
-            return Err(anyhow::anyhow!("ClamAV engine initialization failed: {}", e));
+        match scan_service.clamav.initialize().await {
+            Ok(()) => tracing::info!("ClamAV engine initialized successfully"),
+            Err(e) => {
+                tracing::error!("Failed to initialize ClamAV engine: {}", e);
+                tracing::warn!("Starting HTTP API without a usable engine; scans are disabled");
+            }
         }
-        tracing::info!("ClamAV engine initialized successfully");
 
         // 启动扫描引擎
         if let Err(e) = scan_service.clamav.start_scan_engine().await {
@@ -77,6 +81,14 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Scan engine started successfully");
     }
 
+    // 监视病毒库目录，变化后自动重载引擎（停止标志在服务退出时设置）
+    let db_watcher_stop = services::DbWatcher::new(
+        env.clamav_db_dir(),
+        app_state.clamav.engine_manager(),
+        app_state.db.clone(),
+    ).spawn();
+    tracing::info!("Virus database watcher started");
+
     // 初始化扫描服务回调（只设置一次，避免覆盖）
     {
         let scan_service = app_state.scan_service.read().await;
@@ -85,58 +97,108 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // 构建路由
-    let app = Router::new()
+    let app = build_router(app_state);
+
+    // 绑定地址
+    let addr = SocketAddr::from(([127, 0, 0, 1], SERVER_PORT));
+    tracing::info!("Server listening on http://{}", addr);
+
+    // 写入 PID 文件
+    // let pid = std::process::id();
+    // std::fs::write(&format!("{}/daemon.pid", env.pkg_var()), pid.to_string())?;
+
+    // 启动服务器
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    db_watcher_stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    tracing::info!("Server stopped");
+
+    Ok(())
+}
+
+/// 等待 Ctrl+C 或 SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("Shutdown signal received");
+}
+
+/// 构建 HTTP 路由
+fn build_router<S: Scanner>(app_state: services::AppState<S>) -> Router {
+    Router::new()
         // 健康检查
         .route("/health", get(health::health_check))
-        .route("/api/status", get(health::status))
+        .route("/api/status", get(health::status::<S>))
+        .route("/api/engine/info", get(engine::engine_info::<S>))
 
         // 扫描相关
-        .route("/api/scan/start", post(scan::start_scan))
-        .route("/api/scan/stop", post(scan::stop_scan))
-        .route("/api/scan/status", get(scan::scan_status))
-        .route("/api/scan/history", get(scan::scan_history))
-        .route("/api/scan/history/:id", axum::routing::delete(scan::delete_scan_history))
-        .route("/api/scan/history/clear", post(scan::clear_scan_history))
+        .route("/api/scan/start", post(scan::start_scan::<S>))
+        .route("/api/scan/stop", post(scan::stop_scan::<S>))
+        .route("/api/scan/pause", post(scan::pause_scan::<S>))
+        .route("/api/scan/resume", post(scan::resume_scan::<S>))
+        .route("/api/scan/resumable", get(scan::resumable_scans::<S>))
+        .route("/api/scan/resume/:scan_id", post(scan::resume_interrupted_scan::<S>))
+        .route("/api/scan/status", get(scan::scan_status::<S>))
+        .route("/api/scan/history", get(scan::scan_history::<S>))
+        .route("/api/scan/history/:id", axum::routing::delete(scan::delete_scan_history::<S>))
+        .route("/api/scan/history/clear", post(scan::clear_scan_history::<S>))
+        .route("/api/scan/exclusions/explain", get(scan::explain_exclusion::<S>))
+        .route("/api/scan/events/:scan_id", get(scan::scan_file_events::<S>))
+        .route("/api/scan/verdict-cache", get(scan::verdict_cache_stats::<S>).delete(scan::clear_verdict_cache::<S>))
 
         // 更新相关
-        .route("/api/update/start", post(update::start_update))
-        .route("/api/update/status", get(update::update_status))
-        .route("/api/update/version", get(update::update_version))
-        .route("/api/update/history", get(update::update_history))
+        .route("/api/update/start", post(update::start_update::<S>))
+        .route("/api/update/status", get(update::update_status::<S>))
+        .route("/api/update/version", get(update::update_version::<S>))
+        .route("/api/update/history", get(update::update_history::<S>))
 
         // 威胁处理
-        .route("/api/threats", get(threat::list_threats))
-        .route("/api/threats/:id/handle", post(threat::handle_threat))
+        .route("/api/threats", get(threat::list_threats::<S>))
+        .route("/api/threats/:id/handle", post(threat::handle_threat::<S>))
 
         // 隔离区管理
-        .route("/api/quarantine", get(quarantine::list_quarantine))
-        .route("/api/quarantine/:uuid/restore", post(quarantine::restore_quarantine))
-        .route("/api/quarantine/:uuid", axum::routing::delete(quarantine::delete_quarantine))
-        .route("/api/quarantine/cleanup", post(quarantine::cleanup_quarantine))
+        .route("/api/quarantine", get(quarantine::list_quarantine::<S>))
+        .route("/api/quarantine/:uuid/restore", post(quarantine::restore_quarantine::<S>))
+        .route("/api/quarantine/:uuid", axum::routing::delete(quarantine::delete_quarantine::<S>))
+        .route("/api/quarantine/cleanup", post(quarantine::cleanup_quarantine::<S>))
+
+        // 自定义签名库
+        .route("/api/signatures", get(signature::list_signatures::<S>).post(signature::upload_signature::<S>))
+        .route("/api/signatures/validate", post(signature::validate_signature::<S>))
+        .route("/api/signatures/:name", axum::routing::delete(signature::delete_signature::<S>))
 
         // 配置管理
-        .route("/api/config", get(config::get_config).put(config::update_config))
+        .route("/api/config", get(config::get_config::<S>).put(config::update_config::<S>))
 
         // CORS 支持
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
 
         // 注入应用状态
-       .with_state(app_state);
-
-    // 绑定地址
-    let addr = SocketAddr::from(([127, 0, 0, 1], SERVER_PORT));
-    tracing::info!("Server listening on http://{}", addr);
-
-    // 写入 PID 文件
-    // let pid = std::process::id();
-    // std::fs::write(&format!("{}/daemon.pid", env.pkg_var()), pid.to_string())?;
-
-    // 启动服务器
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+        .with_state(app_state)
 }
 
 /// 初始化病毒数据库
@@ -174,6 +236,16 @@ fn initialize_virus_db(env: &env::FnosEnv) -> anyhow::Result<()> {
                     let name_str = name.to_string_lossy();
                     name_str.ends_with(".cvd") || name_str.ends_with(".cld") || name_str == "freshclam.dat"
                 })
+                // 跳过文件头损坏的数据库文件
+                .filter(|entry| {
+                    let path = entry.path();
+                    let is_db = path.extension().is_some_and(|ext| ext == "cvd" || ext == "cld");
+                    if is_db && clamav::database::CvdHeader::read(&path).is_none() {
+                        tracing::warn!("Skipping invalid pre-installed database {:?}", path);
+                        return false;
+                    }
+                    true
+                })
                 .collect();
 
             if !db_files.is_empty() {
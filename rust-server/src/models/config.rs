@@ -7,6 +7,8 @@ pub struct AppConfig {
     pub threat: ThreatConfig,
     pub update: UpdateConfig,
     pub history: HistoryConfig,
+    #[serde(default)]
+    pub engine: EngineConfig,
 }
 
 impl Default for AppConfig {
@@ -16,17 +18,47 @@ impl Default for AppConfig {
             threat: ThreatConfig::default(),
             update: UpdateConfig::default(),
             history: HistoryConfig::default(),
+            engine: EngineConfig::default(),
         }
     }
 }
 
+impl AppConfig {
+    /// 从配置文件读取，文件不存在或解析失败时返回默认配置
+    pub fn load_or_default(settings_file: &str) -> Self {
+        std::fs::read_to_string(settings_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+}
+
 /// 扫描配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanConfig {
     pub default_scan_type: String,
+    /// 排除规则：绝对路径前缀、glob 模式（*.iso、**/node_modules）或 re:<正则>
     pub exclude_paths: Vec<String>,
+    /// 排除的文件扩展名（不含点，不区分大小写）
+    #[serde(default)]
+    pub exclude_extensions: Vec<String>,
+    /// 遍历时跟随符号链接（默认跳过）
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// 不跨越扫描根路径所在的文件系统
+    #[serde(default)]
+    pub one_filesystem: bool,
     pub max_file_size_mb: u32,
     pub scan_archives: bool,
+    /// 增量扫描：目录扫描跳过上次扫描后未修改的干净文件
+    #[serde(default = "default_true")]
+    pub verdict_cache: bool,
+    /// 病毒库版本前进超过该值后，缓存的干净结果失效
+    #[serde(default = "default_verdict_cache_version_threshold")]
+    pub verdict_cache_version_threshold: u32,
+    /// 缓存记录保留天数，超过后删除（文件在下次扫描时完整扫描）
+    #[serde(default = "default_verdict_cache_max_age_days")]
+    pub verdict_cache_max_age_days: u32,
 }
 
 impl Default for ScanConfig {
@@ -39,9 +71,64 @@ impl Default for ScanConfig {
                 "/dev".to_string(),
                 "/run".to_string(),
             ],
+            exclude_extensions: Vec::new(),
+            follow_symlinks: false,
+            one_filesystem: false,
             max_file_size_mb: 100,
             scan_archives: true,
+            verdict_cache: true,
+            verdict_cache_version_threshold: default_verdict_cache_version_threshold(),
+            verdict_cache_max_age_days: default_verdict_cache_max_age_days(),
+        }
+    }
+}
+
+impl ScanConfig {
+    /// 构建扫描排除规则：全局配置在前，本次扫描附带的规则在后
+    ///
+    /// 配置中无法解析的规则记录警告后忽略（保存配置时由 validate_exclusions 拒绝），
+    /// 本次扫描附带的规则无效时返回错误
+    pub fn exclusion_rules(&self, scan_excludes: &[String]) -> Result<crate::clamav::ExclusionRules, crate::clamav::ExclusionError> {
+        use crate::clamav::{ExclusionRules, ExclusionSource};
+
+        ExclusionRules::new()
+            .with_valid_patterns(&self.exclude_paths, ExclusionSource::Config)
+            .with_extensions(&self.exclude_extensions, ExclusionSource::Config)
+            .with_patterns(scan_excludes, ExclusionSource::Scan)
+    }
+
+    /// 校验配置中的排除规则（保存配置前调用）
+    pub fn validate_exclusions(&self) -> Result<(), crate::clamav::ExclusionError> {
+        crate::clamav::ExclusionRules::new()
+            .with_patterns(&self.exclude_paths, crate::clamav::ExclusionSource::Config)
+            .map(|_| ())
+    }
+
+    /// 构建目录遍历选项；请求中的设置优先于配置
+    pub fn discovery_options(
+        &self,
+        scan_excludes: &[String],
+        follow_symlinks: Option<bool>,
+        one_filesystem: Option<bool>,
+    ) -> Result<crate::clamav::DiscoveryOptions, crate::clamav::ExclusionError> {
+        Ok(crate::clamav::DiscoveryOptions::new()
+            .with_exclusions(self.exclusion_rules(scan_excludes)?)
+            .with_follow_symlinks(follow_symlinks.unwrap_or(self.follow_symlinks))
+            .with_one_filesystem(one_filesystem.unwrap_or(self.one_filesystem)))
+    }
+
+    /// 构建本次扫描的缓存策略；未启用增量扫描或缓存不可用时返回 None
+    pub fn verdict_cache_policy(
+        &self,
+        store: Option<std::sync::Arc<dyn crate::clamav::VerdictCache>>,
+        force_full_rescan: bool,
+    ) -> Option<crate::clamav::VerdictCachePolicy> {
+        if !self.verdict_cache {
+            return None;
         }
+        store.map(|store| crate::clamav::VerdictCachePolicy::new(store)
+            .with_version_threshold(self.verdict_cache_version_threshold)
+            .with_force_rescan(force_full_rescan))
     }
 }
 
@@ -97,6 +184,99 @@ impl Default for HistoryConfig {
     }
 }
 
+/// 引擎配置（资源限制，单个文件大小限制见 ScanConfig.max_file_size_mb）
+///
+/// 所有限制值为 0 时表示不限制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// 单个文件（含解压内容）最大扫描数据量（MB）
+    pub max_scan_size_mb: u32,
+    /// 压缩包最大嵌套深度
+    pub max_recursion: u32,
+    /// 单个容器内最大扫描文件数
+    pub max_files: u32,
+    /// 嵌入式 PE 最大大小（MB）
+    pub max_embedded_pe_mb: u32,
+    /// 单个文件扫描超时时间（秒）
+    pub scan_timeout: u32,
+    /// 是否检测 PUA（潜在不受欢迎程序）
+    pub detect_pua: bool,
+    /// 仅检测这些 PUA 类别（为空表示全部）
+    pub pua_include_categories: Vec<String>,
+    /// 不检测这些 PUA 类别，优先于 pua_include_categories
+    pub pua_exclude_categories: Vec<String>,
+    /// 仅加载官方签名库
+    pub official_db_only: bool,
+    /// 是否加载钓鱼邮件签名
+    pub phishing_signatures: bool,
+    /// 是否加载钓鱼 URL 签名
+    pub phishing_scan_urls: bool,
+    /// 加密压缩包告警
+    pub alert_encrypted_archive: bool,
+    /// 加密文档告警
+    pub alert_encrypted_doc: bool,
+    /// 损坏可执行文件告警
+    pub alert_broken_executables: bool,
+    /// 钓鱼 SSL 链接不一致告警
+    pub alert_phishing_ssl_mismatch: bool,
+    /// 钓鱼伪装 URL 告警
+    pub alert_phishing_cloak: bool,
+    /// OLE2 宏告警
+    pub alert_ole2_macros: bool,
+    /// 磁盘分区重叠告警
+    pub alert_partition_intersection: bool,
+    /// 检测信用卡号（结构化数据）
+    pub detect_structured_cc: bool,
+    /// 检测社会安全号码（结构化数据）
+    pub detect_structured_ssn: bool,
+    /// 超出扫描限制告警
+    pub alert_exceeds_max: bool,
+    /// 是否启用启发式扫描
+    pub heuristic_scan: bool,
+    /// 仅加载受信任（官方签名）的字节码签名
+    pub bytecode_trusted_only: bool,
+    /// 单个字节码签名执行超时（毫秒）
+    pub bytecode_timeout_ms: u64,
+    /// 是否启用引擎缓存
+    pub engine_cache: bool,
+    /// 引擎缓存条目数（0 表示使用 libclamav 默认值）
+    pub cache_size: u32,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            max_scan_size_mb: 400,
+            max_recursion: 17,
+            max_files: 10000,
+            max_embedded_pe_mb: 40,
+            scan_timeout: 300,
+            detect_pua: false,
+            pua_include_categories: Vec::new(),
+            pua_exclude_categories: Vec::new(),
+            official_db_only: false,
+            phishing_signatures: true,
+            phishing_scan_urls: true,
+            alert_encrypted_archive: false,
+            alert_encrypted_doc: false,
+            alert_broken_executables: false,
+            alert_phishing_ssl_mismatch: false,
+            alert_phishing_cloak: false,
+            alert_ole2_macros: false,
+            alert_partition_intersection: false,
+            detect_structured_cc: false,
+            detect_structured_ssn: false,
+            alert_exceeds_max: false,
+            heuristic_scan: true,
+            bytecode_trusted_only: true,
+            bytecode_timeout_ms: 10000,
+            engine_cache: true,
+            cache_size: 0,
+        }
+    }
+}
+
 /// 配置响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigResponse {
@@ -104,6 +284,7 @@ pub struct ConfigResponse {
     pub threat: ThreatConfig,
     pub update: UpdateConfig,
     pub history: HistoryConfig,
+    pub engine: EngineConfig,
 }
 
 /// ClamAV 引擎配置
@@ -111,6 +292,9 @@ pub struct ConfigResponse {
 pub struct ClamAVConfig {
     /// 病毒库目录
     pub database_dir: String,
+    /// 自定义签名库目录（与官方病毒库一同加载）
+    #[serde(default)]
+    pub custom_sigs_dir: Option<String>,
     /// 证书目录
     pub certs_dir: Option<String>,
     /// libclamav.so 路径
@@ -121,17 +305,214 @@ pub struct ClamAVConfig {
     pub scan_timeout: u32,
     /// 是否启用启发式扫描
     pub heuristic_scan: bool,
+    /// 单个文件最大大小（MB）
+    pub max_file_size_mb: u32,
+    /// 单个文件（含解压内容）最大扫描数据量（MB）
+    pub max_scan_size_mb: u32,
+    /// 压缩包最大嵌套深度
+    pub max_recursion: u32,
+    /// 单个容器内最大扫描文件数
+    pub max_files: u32,
+    /// 嵌入式 PE 最大大小（MB）
+    pub max_embedded_pe_mb: u32,
+    /// 是否检测 PUA（潜在不受欢迎程序）
+    #[serde(default)]
+    pub detect_pua: bool,
+    /// 仅检测这些 PUA 类别
+    #[serde(default)]
+    pub pua_include_categories: Vec<String>,
+    /// 不检测这些 PUA 类别
+    #[serde(default)]
+    pub pua_exclude_categories: Vec<String>,
+    /// 仅加载官方签名库
+    #[serde(default)]
+    pub official_db_only: bool,
+    /// 是否加载钓鱼邮件签名
+    #[serde(default = "default_true")]
+    pub phishing_signatures: bool,
+    /// 是否加载钓鱼 URL 签名
+    #[serde(default = "default_true")]
+    pub phishing_scan_urls: bool,
+    /// 加密压缩包告警
+    #[serde(default)]
+    pub alert_encrypted_archive: bool,
+    /// 加密文档告警
+    #[serde(default)]
+    pub alert_encrypted_doc: bool,
+    /// 损坏可执行文件告警
+    #[serde(default)]
+    pub alert_broken_executables: bool,
+    /// 钓鱼 SSL 链接不一致告警
+    #[serde(default)]
+    pub alert_phishing_ssl_mismatch: bool,
+    /// 钓鱼伪装 URL 告警
+    #[serde(default)]
+    pub alert_phishing_cloak: bool,
+    /// OLE2 宏告警
+    #[serde(default)]
+    pub alert_ole2_macros: bool,
+    /// 磁盘分区重叠告警
+    #[serde(default)]
+    pub alert_partition_intersection: bool,
+    /// 检测信用卡号（结构化数据）
+    #[serde(default)]
+    pub detect_structured_cc: bool,
+    /// 检测社会安全号码（结构化数据）
+    #[serde(default)]
+    pub detect_structured_ssn: bool,
+    /// 超出扫描限制告警
+    #[serde(default)]
+    pub alert_exceeds_max: bool,
+    /// 仅加载受信任（官方签名）的字节码签名
+    #[serde(default = "default_true")]
+    pub bytecode_trusted_only: bool,
+    /// 单个字节码签名执行超时（毫秒）
+    #[serde(default = "default_bytecode_timeout_ms")]
+    pub bytecode_timeout_ms: u64,
+    /// 是否启用引擎缓存
+    #[serde(default = "default_true")]
+    pub engine_cache: bool,
+    /// 引擎缓存条目数（0 表示使用 libclamav 默认值）
+    #[serde(default)]
+    pub cache_size: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_verdict_cache_version_threshold() -> u32 {
+    crate::clamav::verdict_cache::DEFAULT_VERSION_THRESHOLD
+}
+
+fn default_verdict_cache_max_age_days() -> u32 {
+    30
+}
+
+fn default_bytecode_timeout_ms() -> u64 {
+    10000
 }
 
 impl Default for ClamAVConfig {
     fn default() -> Self {
         Self {
             database_dir: "/var/lib/clamav".to_string(),
+            custom_sigs_dir: None,
             certs_dir: None,
             lib_path: None,
             max_threads: 4,
             scan_timeout: 300,
             heuristic_scan: true,
+            max_file_size_mb: 100,
+            max_scan_size_mb: 400,
+            max_recursion: 17,
+            max_files: 10000,
+            max_embedded_pe_mb: 40,
+            detect_pua: false,
+            pua_include_categories: Vec::new(),
+            pua_exclude_categories: Vec::new(),
+            official_db_only: false,
+            phishing_signatures: true,
+            phishing_scan_urls: true,
+            alert_encrypted_archive: false,
+            alert_encrypted_doc: false,
+            alert_broken_executables: false,
+            alert_phishing_ssl_mismatch: false,
+            alert_phishing_cloak: false,
+            alert_ole2_macros: false,
+            alert_partition_intersection: false,
+            detect_structured_cc: false,
+            detect_structured_ssn: false,
+            alert_exceeds_max: false,
+            bytecode_trusted_only: true,
+            bytecode_timeout_ms: 10000,
+            engine_cache: true,
+            cache_size: 0,
+        }
+    }
+}
+
+impl ClamAVConfig {
+    /// 从应用配置同步引擎相关设置
+    pub fn apply_settings(&mut self, settings: &AppConfig) {
+        self.max_file_size_mb = settings.scan.max_file_size_mb;
+        self.scan_timeout = settings.engine.scan_timeout;
+        self.max_scan_size_mb = settings.engine.max_scan_size_mb;
+        self.max_recursion = settings.engine.max_recursion;
+        self.max_files = settings.engine.max_files;
+        self.max_embedded_pe_mb = settings.engine.max_embedded_pe_mb;
+        self.detect_pua = settings.engine.detect_pua;
+        self.pua_include_categories = settings.engine.pua_include_categories.clone();
+        self.pua_exclude_categories = settings.engine.pua_exclude_categories.clone();
+        self.official_db_only = settings.engine.official_db_only;
+        self.phishing_signatures = settings.engine.phishing_signatures;
+        self.phishing_scan_urls = settings.engine.phishing_scan_urls;
+        self.alert_encrypted_archive = settings.engine.alert_encrypted_archive;
+        self.alert_encrypted_doc = settings.engine.alert_encrypted_doc;
+        self.alert_broken_executables = settings.engine.alert_broken_executables;
+        self.alert_phishing_ssl_mismatch = settings.engine.alert_phishing_ssl_mismatch;
+        self.alert_phishing_cloak = settings.engine.alert_phishing_cloak;
+        self.alert_ole2_macros = settings.engine.alert_ole2_macros;
+        self.alert_partition_intersection = settings.engine.alert_partition_intersection;
+        self.detect_structured_cc = settings.engine.detect_structured_cc;
+        self.detect_structured_ssn = settings.engine.detect_structured_ssn;
+        self.alert_exceeds_max = settings.engine.alert_exceeds_max;
+        self.heuristic_scan = settings.engine.heuristic_scan;
+        self.bytecode_trusted_only = settings.engine.bytecode_trusted_only;
+        self.bytecode_timeout_ms = settings.engine.bytecode_timeout_ms;
+        self.engine_cache = settings.engine.engine_cache;
+        self.cache_size = settings.engine.cache_size;
+    }
+
+    /// 转换为 FFI 层使用的资源限制
+    pub fn engine_limits(&self) -> crate::clamav::EngineLimits {
+        const MB: u64 = 1024 * 1024;
+        crate::clamav::EngineLimits {
+            max_file_size: self.max_file_size_mb as u64 * MB,
+            max_scan_size: self.max_scan_size_mb as u64 * MB,
+            max_recursion: self.max_recursion,
+            max_files: self.max_files,
+            max_embedded_pe: self.max_embedded_pe_mb as u64 * MB,
+            max_scan_time_ms: self.scan_timeout as u64 * 1000,
+        }
+    }
+
+    /// 转换为 FFI 层使用的病毒库加载选项
+    pub fn database_options(&self) -> crate::clamav::DatabaseOptions {
+        crate::clamav::DatabaseOptions {
+            detect_pua: self.detect_pua,
+            pua_include: self.pua_include_categories.clone(),
+            pua_exclude: self.pua_exclude_categories.clone(),
+            official_only: self.official_db_only,
+            phishing_signatures: self.phishing_signatures,
+            phishing_urls: self.phishing_scan_urls,
+        }
+    }
+
+    /// 转换为 FFI 层使用的引擎运行设置
+    pub fn engine_settings(&self) -> crate::clamav::EngineSettings {
+        crate::clamav::EngineSettings {
+            bytecode_trusted_only: self.bytecode_trusted_only,
+            bytecode_timeout_ms: self.bytecode_timeout_ms,
+            cache_enabled: self.engine_cache,
+            cache_size: self.cache_size,
+            heuristic_scan: self.heuristic_scan,
+        }
+    }
+
+    /// 转换为 FFI 层使用的启发式告警类别
+    pub fn heuristic_alerts(&self) -> crate::clamav::HeuristicAlerts {
+        crate::clamav::HeuristicAlerts {
+            encrypted_archive: self.alert_encrypted_archive,
+            encrypted_doc: self.alert_encrypted_doc,
+            broken_executables: self.alert_broken_executables,
+            phishing_ssl_mismatch: self.alert_phishing_ssl_mismatch,
+            phishing_cloak: self.alert_phishing_cloak,
+            ole2_macros: self.alert_ole2_macros,
+            partition_intersection: self.alert_partition_intersection,
+            structured_cc: self.detect_structured_cc,
+            structured_ssn: self.detect_structured_ssn,
+            exceeds_max: self.alert_exceeds_max,
         }
     }
 }
@@ -4,9 +4,11 @@ pub mod update;
 pub mod config;
 pub mod threat;
 pub mod quarantine;
+pub mod signature;
 
 pub use scan::*;
 pub use update::*;
 pub use config::*;
 pub use threat::*;
 pub use quarantine::*;
+pub use signature::*;
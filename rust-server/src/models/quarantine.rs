@@ -19,6 +19,9 @@ pub struct QuarantineItem {
     pub virus_name: String,
     pub quarantined_at: i64,
     pub scan_id: String,
+    /// 隔离时记录的文件 SHA-256 哈希
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_hash: Option<String>,
 }
 
 /// 隔离文件元数据
@@ -58,6 +61,7 @@ impl QuarantineMetadata {
         virus_name: String,
         scan_id: String,
         file_size: u64,
+        file_hash: Option<String>,
     ) -> Self {
         let uuid = Uuid::new_v4().to_string();
         let original_name = original_path
@@ -71,7 +75,7 @@ impl QuarantineMetadata {
             original_path,
             original_name,
             file_size,
-            file_hash: None,
+            file_hash,
             quarantined_at: chrono::Utc::now().timestamp(),
             virus_name,
             scan_id,
@@ -25,6 +25,79 @@ pub struct ScanRequest {
     pub scan_type: ScanType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub paths: Option<Vec<String>>,
+    /// 本次扫描的解析器选项（未提供的字段使用默认值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<ScanRequestOptions>,
+    /// 本次扫描额外的排除规则（与配置中的 exclude_paths 格式相同）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// 是否跟随符号链接（未提供时使用配置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow_symlinks: Option<bool>,
+    /// 是否只扫描根路径所在的文件系统（未提供时使用配置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub one_filesystem: Option<bool>,
+    /// 强制完整扫描：不使用缓存跳过未修改的文件
+    #[serde(default)]
+    pub force_full_rescan: bool,
+}
+
+/// 扫描请求中的解析器选项
+///
+/// 所有字段可选，未设置的字段保持 ScanOptions 默认值（启用）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanRequestOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_archive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_pdf: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_elf: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_mail: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_ole2: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_html: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_pe: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_swf: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_hwp: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_xmldocs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_onenote: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heuristics: Option<bool>,
+}
+
+impl ScanRequestOptions {
+    /// 在默认扫描选项上应用请求中的覆盖值
+    pub fn to_scan_options(&self) -> crate::clamav::ScanOptions {
+        let mut opts = crate::clamav::ScanOptions::default();
+        let overrides = [
+            (&mut opts.scan_archive, self.scan_archive),
+            (&mut opts.scan_pdf, self.scan_pdf),
+            (&mut opts.scan_elf, self.scan_elf),
+            (&mut opts.scan_mail, self.scan_mail),
+            (&mut opts.scan_ole2, self.scan_ole2),
+            (&mut opts.scan_html, self.scan_html),
+            (&mut opts.scan_pe, self.scan_pe),
+            (&mut opts.scan_swf, self.scan_swf),
+            (&mut opts.scan_hwp, self.scan_hwp),
+            (&mut opts.scan_xmldocs, self.scan_xmldocs),
+            (&mut opts.scan_onenote, self.scan_onenote),
+            (&mut opts.heuristics, self.heuristics),
+        ];
+        for (field, value) in overrides {
+            if let Some(v) = value {
+                *field = v;
+            }
+        }
+        opts
+    }
 }
 
 /// 扫描响应
@@ -63,6 +136,23 @@ pub struct ScanProgress {
     /// 扫描速率（文件/秒）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scan_rate: Option<f32>,
+    /// 各扫描工作线程正在扫描的文件（按工作线程编号，空闲为 null）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub worker_files: Vec<Option<String>>,
+    /// 多根目录扫描时各根目录的进度
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<ScanRootProgress>,
+}
+
+/// 单个扫描根目录的进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRootProgress {
+    pub path: String,
+    pub discovered: u64,
+    pub scanned: u64,
+    pub threats: u32,
+    /// 该根目录是否已遍历完成
+    pub discovery_complete: bool,
 }
 
 /// 威胁信息
@@ -107,4 +197,5 @@ pub struct ThreatRecord {
     pub action_time: Option<i64>,
     pub original_location: Option<String>,
     pub file_hash: Option<String>,
+    pub file_type: Option<String>,
 }
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// 自定义签名文件信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureFile {
+    pub name: String,
+    /// 签名类型（文件扩展名，如 hdb / ndb / yar）
+    pub sig_type: String,
+    pub size: u64,
+    pub modified_at: i64,
+}
+
+/// 自定义签名列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureListResponse {
+    pub directory: String,
+    pub total: u32,
+    pub items: Vec<SignatureFile>,
+}
+
+/// 上传 / 校验签名文件请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureUploadRequest {
+    /// 文件名，扩展名决定签名类型
+    pub name: String,
+    /// 签名文件内容（文本）
+    pub content: String,
+}
+
+/// 签名文件操作响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureResponse {
+    pub success: bool,
+    pub name: String,
+    /// 试编译得到的签名数量
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signatures: Option<u32>,
+    /// 操作后引擎是否已重新加载
+    pub reloaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
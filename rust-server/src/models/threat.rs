@@ -22,6 +22,15 @@ pub struct ThreatItem {
     pub quarantine_uuid: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub action_time: Option<i64>,
+    /// 文件 SHA-256 哈希
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_hash: Option<String>,
+    /// ClamAV 识别的文件类型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<String>,
+    /// 命中成员在容器（压缩包等）内的路径，如 "inner.rar/evil.exe"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_path: Option<String>,
 }
 
 /// 威胁处理请求
@@ -13,27 +13,39 @@ use tokio::sync::RwLock;
 
 use crate::models::ClamAVConfig;
 use crate::clamav::{
-    ClamAVEngine, EngineManager,
+    ClamAVEngine, EngineManager, Scanner,
 };
-use crate::clamav::engine::{ScanTarget, TaskPriority, ScanEngine as ClamAVScanEngine, CompletionCallback};
-use crate::clamav::{ScanOptions, ScanProgress, ScanOutcome, VirusName, FilePath};
-
-/// 类型别名
-type ScanEngine = ClamAVScanEngine;
+use crate::clamav::engine::{ScanTarget, ScanTask, TaskPriority, ScanEngine, CompletionCallback};
+use crate::clamav::{ScanOptions, ScanProgress, ScanOutcome, DiscoveryOptions, ScanCheckpoint};
 
 /// ClamAV FFI 服务
-#[derive(Clone)]
-pub struct ClamavService {
-    engine_manager: Arc<EngineManager>,
-    scan_engine: Arc<RwLock<Option<Arc<ScanEngine>>>>,
+pub struct ClamavService<S: Scanner = ClamAVEngine> {
+    engine_manager: Arc<EngineManager<S>>,
+    scan_engine: Arc<RwLock<Option<Arc<ScanEngine<S>>>>>,
     config: ClamAVConfig,
 }
 
-impl ClamavService {
+impl<S: Scanner> Clone for ClamavService<S> {
+    fn clone(&self) -> Self {
+        Self {
+            engine_manager: self.engine_manager.clone(),
+            scan_engine: self.scan_engine.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl ClamavService<ClamAVEngine> {
     /// 创建新的 ClamAV 服务
     pub fn new(config: ClamAVConfig) -> Self {
         let engine_manager = Arc::new(EngineManager::new(config.clone()));
+        Self::with_engine_manager(config, engine_manager)
+    }
+}
 
+impl<S: Scanner> ClamavService<S> {
+    /// 使用指定的引擎管理器（扫描后端）创建服务
+    pub fn with_engine_manager(config: ClamAVConfig, engine_manager: Arc<EngineManager<S>>) -> Self {
         Self {
             engine_manager,
             scan_engine: Arc::new(RwLock::new(None)),
@@ -49,11 +61,14 @@ impl ClamavService {
     }
 
     /// 启动扫描引擎
+    ///
+    /// 扫描引擎持有句柄，每个文件扫描时获取当前引擎；引擎不可用时也可以启动，
+    /// 之后引擎初始化或重载成功即可开始扫描。目录扫描使用 max_threads 个工作线程
     pub async fn start_scan_engine(&self) -> Result<()> {
-        let engine = self.engine_manager.get_engine()
-            .map_err(|e| anyhow::anyhow!("Failed to get engine: {}", e))?;
-
-        let scan_engine = Arc::new(ScanEngine::new(engine));
+        let workers = self.engine_manager.get_config().max_threads as usize;
+        let scan_engine = Arc::new(
+            ScanEngine::new(self.engine_manager.engine_handle()).with_workers(workers)
+        );
 
         let mut se = self.scan_engine.write().await;
         *se = Some(scan_engine);
@@ -63,8 +78,8 @@ impl ClamavService {
     }
 
     /// 获取扫描引擎
-    async fn get_scan_engine(&self) -> Result<Arc<ScanEngine>> {
-        let se: tokio::sync::RwLockReadGuard<'_, Option<Arc<ScanEngine>>> = self.scan_engine.read().await;
+    async fn get_scan_engine(&self) -> Result<Arc<ScanEngine<S>>> {
+        let se: tokio::sync::RwLockReadGuard<'_, Option<Arc<ScanEngine<S>>>> = self.scan_engine.read().await;
         se.as_ref()
             .map(Arc::clone)
             .ok_or_else(|| anyhow::anyhow!("Scan engine not started"))
@@ -76,38 +91,45 @@ impl ClamavService {
         target: ScanTarget,
         priority: TaskPriority,
         options: ScanOptions,
+        discovery: DiscoveryOptions,
     ) -> Result<String> {
-        let scan_engine: Arc<ScanEngine> = self.get_scan_engine().await?;
-        scan_engine.submit_task(target, priority, options).await
+        let scan_engine: Arc<ScanEngine<S>> = self.get_scan_engine().await?;
+        scan_engine.submit_task(target, priority, options, discovery).await
+    }
+
+    /// 提交已构造的扫描任务（检查点恢复、扫描结果缓存等）
+    pub async fn submit_task(&self, task: ScanTask) -> Result<String> {
+        let scan_engine: Arc<ScanEngine<S>> = self.get_scan_engine().await?;
+        scan_engine.submit_scan_task(task).await
     }
 
     /// 取消扫描任务
     pub async fn cancel_scan(&self, task_id: &str) -> Result<bool> {
-        let scan_engine: Arc<ScanEngine> = self.get_scan_engine().await?;
+        let scan_engine: Arc<ScanEngine<S>> = self.get_scan_engine().await?;
         scan_engine.cancel_task(task_id).await
     }
 
     /// 暂停扫描任务
     pub async fn pause_scan(&self, task_id: &str) -> Result<bool> {
-        let scan_engine: Arc<ScanEngine> = self.get_scan_engine().await?;
+        let scan_engine: Arc<ScanEngine<S>> = self.get_scan_engine().await?;
         scan_engine.pause_task(task_id).await
     }
 
     /// 恢复扫描任务
     pub async fn resume_scan(&self, task_id: &str) -> Result<bool> {
-        let scan_engine: Arc<ScanEngine> = self.get_scan_engine().await?;
+        let scan_engine: Arc<ScanEngine<S>> = self.get_scan_engine().await?;
         scan_engine.resume_task(task_id).await
     }
 
     /// 获取任务状态
     pub async fn get_task(&self, task_id: &str) -> Result<crate::clamav::engine::ScanTask> {
-        let scan_engine: Arc<ScanEngine> = self.get_scan_engine().await?;
+        let scan_engine: Arc<ScanEngine<S>> = self.get_scan_engine().await?;
         scan_engine.get_task(task_id).await
     }
 
     /// 列出所有任务
     pub async fn list_tasks(&self) -> Result<Vec<crate::clamav::engine::ScanTask>> {
-        let scan_engine: Arc<ScanEngine> = self.get_scan_engine().await?;
+        let scan_engine: Arc<ScanEngine<S>> = self.get_scan_engine().await?;
         scan_engine.list_tasks().await
     }
 
@@ -118,19 +140,45 @@ impl ClamavService {
 
     /// 重新加载引擎
     pub async fn reload_engine(&self) -> Result<()> {
-        self.engine_manager.reload()
+        let engine_manager = self.engine_manager.clone();
+        tokio::task::spawn_blocking(move || engine_manager.reload())
+            .await?
+            .map_err(|e| anyhow::anyhow!("Reload failed: {}", e))
+    }
+
+    /// 应用新的引擎配置并重新加载引擎
+    pub async fn apply_config(&self, config: ClamAVConfig) -> Result<()> {
+        // 工作线程数从下一个扫描任务开始生效
+        if let Ok(scan_engine) = self.get_scan_engine().await {
+            scan_engine.set_workers(config.max_threads as usize);
+        }
+        self.engine_manager.update_config(config);
+
+        let engine_manager = self.engine_manager.clone();
+        tokio::task::spawn_blocking(move || engine_manager.reload())
+            .await?
             .map_err(|e| anyhow::anyhow!("Reload failed: {}", e))
     }
 
+    /// 获取引擎管理器
+    pub fn engine_manager(&self) -> Arc<EngineManager<S>> {
+        self.engine_manager.clone()
+    }
+
+    /// 获取当前引擎配置
+    pub fn get_config(&self) -> ClamAVConfig {
+        self.engine_manager.get_config()
+    }
+
     /// 关闭服务
     pub async fn shutdown(&self) -> Result<()> {
         // 关闭扫描引擎
         {
-            let mut se: tokio::sync::RwLockWriteGuard<'_, Option<Arc<ScanEngine>>> = self.scan_engine.write().await;
-            let scan_engine: Option<Arc<ScanEngine>> = se.take();
+            let mut se: tokio::sync::RwLockWriteGuard<'_, Option<Arc<ScanEngine<S>>>> = self.scan_engine.write().await;
+            let scan_engine: Option<Arc<ScanEngine<S>>> = se.take();
             drop(se);
             if let Some(engine) = scan_engine {
-                ScanEngine::shutdown(&*engine).await?;
+                ScanEngine::<S>::shutdown(&*engine).await?;
             }
         }
 
@@ -147,7 +195,7 @@ impl ClamavService {
     {
         match self.get_scan_engine().await {
             Ok(engine) => {
-                ScanEngine::set_progress_callback(&*engine, std::sync::Arc::new(callback)).await;
+                ScanEngine::<S>::set_progress_callback(&*engine, std::sync::Arc::new(callback)).await;
             }
             Err(_) => {}
         }
@@ -160,12 +208,30 @@ impl ClamavService {
     {
         match self.get_scan_engine().await {
             Ok(engine) => {
-                ScanEngine::set_completion_callback(&*engine, std::sync::Arc::new(callback)).await;
+                ScanEngine::<S>::set_completion_callback(&*engine, std::sync::Arc::new(callback)).await;
             }
             Err(_) => {}
         }
     }
 
+    /// 设置检查点回调 (task_id, checkpoint)
+    pub async fn set_checkpoint_callback<F>(&self, callback: F)
+    where
+        F: Fn(&str, ScanCheckpoint) + Send + Sync + 'static,
+    {
+        match self.get_scan_engine().await {
+            Ok(engine) => {
+                ScanEngine::<S>::set_checkpoint_callback(&*engine, std::sync::Arc::new(callback)).await;
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// 获取引擎加载信息（引擎未加载时为 None）
+    pub fn engine_info(&self) -> Option<crate::clamav::EngineInfo> {
+        self.engine_manager.engine_info()
+    }
+
     /// 获取引擎状态
     pub async fn get_engine_state(&self) -> crate::clamav::EngineState {
         self.engine_manager.get_state()
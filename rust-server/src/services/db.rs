@@ -2,15 +2,7 @@ use rusqlite::{Connection, Result as SqliteResult};
 use std::path::Path;
 
 pub fn init_db(db_path: &str) -> SqliteResult<()> {
-    let mut conn = Connection::open(db_path)?;
-
-    // 清理僵尸记录：将所有未完成的扫描标记为失败
-    // 这些记录可能是服务异常中断时遗留的
-    conn.execute(
-        "UPDATE scan_history SET status = 'failed', error_message = 'Service interrupted'
-         WHERE status = 'scanning'",
-        [],
-    )?;
+    let conn = Connection::open(db_path)?;
 
     // 创建扫描历史表
     conn.execute(
@@ -75,6 +67,94 @@ pub fn init_db(db_path: &str) -> SqliteResult<()> {
         [],
     )?;
 
+    // 创建扫描根目录结果表（多根目录扫描时每个根目录一行）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_roots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            root_path TEXT NOT NULL,
+            total_files INTEGER DEFAULT 0,
+            scanned_files INTEGER DEFAULT 0,
+            threats_found INTEGER DEFAULT 0,
+            error_count INTEGER DEFAULT 0,
+            error_message TEXT
+        )",
+        [],
+    )?;
+
+    // 创建扫描文件事件表（每个未被扫描且非干净的文件一行）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_file_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            category TEXT,
+            error_code INTEGER,
+            detail TEXT
+        )",
+        [],
+    )?;
+
+    // 创建扫描检查点表（每个可恢复的扫描一行，state 为已完成目录的累计计数）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_checkpoints (
+            scan_id TEXT PRIMARY KEY,
+            request TEXT NOT NULL,
+            state TEXT,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // 数据库迁移：旧的已完成目录表没有主键，重建后去除重复的目录
+    migrate_checkpoint_dirs(&conn)?;
+
+    // 创建检查点已完成目录表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_checkpoint_dirs (
+            scan_id TEXT NOT NULL,
+            dir_path TEXT NOT NULL,
+            PRIMARY KEY (scan_id, dir_path)
+        )",
+        [],
+    )?;
+
+    // 创建检查点威胁表（已完成目录中发现的威胁，每次检查点追加，JSON）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_checkpoint_threats (
+            scan_id TEXT NOT NULL,
+            threat TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // 创建检查点文件事件表（已完成目录中跳过或失败的文件，每次检查点追加，JSON）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_checkpoint_events (
+            scan_id TEXT NOT NULL,
+            event TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // 创建文件扫描结果缓存表（增量扫描：以 dev + inode 标识文件）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS file_verdicts (
+            dev INTEGER NOT NULL,
+            ino INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            mtime_ns INTEGER NOT NULL,
+            ctime_ns INTEGER NOT NULL,
+            verdict TEXT NOT NULL,
+            db_version INTEGER NOT NULL,
+            engine_fingerprint TEXT NOT NULL DEFAULT '',
+            scanned_at INTEGER NOT NULL,
+            PRIMARY KEY (dev, ino)
+        )",
+        [],
+    )?;
+
     // 创建索引
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_scan_history_start_time ON scan_history(start_time DESC)",
@@ -88,6 +168,26 @@ pub fn init_db(db_path: &str) -> SqliteResult<()> {
         "CREATE INDEX IF NOT EXISTS idx_threat_records_scan_id ON threat_records(scan_id)",
         [],
     )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_scan_roots_scan_id ON scan_roots(scan_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_scan_file_events_scan_id ON scan_file_events(scan_id, reason)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_scan_checkpoint_threats_scan_id ON scan_checkpoint_threats(scan_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_scan_checkpoint_events_scan_id ON scan_checkpoint_events(scan_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_file_verdicts_scanned_at ON file_verdicts(scanned_at)",
+        [],
+    )?;
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_update_history_start_time ON update_history(start_time DESC)",
         [],
@@ -98,26 +198,98 @@ pub fn init_db(db_path: &str) -> SqliteResult<()> {
     )?;
 
     // 数据库迁移：添加 current_file 字段（如果不存在）
-    // SQLite 不支持 IF NOT EXISTS for ALTER TABLE，所以需要检查列是否存在
-    let has_current_file: SqliteResult<bool> = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('scan_history') WHERE name='current_file'",
+    add_column_if_missing(&conn, "scan_history", "current_file", "TEXT")?;
+
+    // 数据库迁移：威胁记录的文件类型
+    add_column_if_missing(&conn, "threat_records", "file_type", "TEXT")?;
+
+    // 数据库迁移：威胁所在的容器成员路径
+    add_column_if_missing(&conn, "threat_records", "container_path", "TEXT")?;
+
+    // 数据库迁移：未被扫描文件的分类计数
+    add_column_if_missing(&conn, "scan_history", "skipped_files", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(&conn, "scan_history", "excluded_files", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(&conn, "scan_history", "errored_files", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(&conn, "scan_history", "timed_out_files", "INTEGER DEFAULT 0")?;
+
+    // 数据库迁移：使用缓存结果跳过的文件数
+    add_column_if_missing(&conn, "scan_history", "cached_files", "INTEGER DEFAULT 0")?;
+
+    // 数据库迁移：缓存记录的引擎指纹（旧记录为空，不会命中）
+    add_column_if_missing(&conn, "file_verdicts", "engine_fingerprint", "TEXT NOT NULL DEFAULT ''")?;
+
+    // 清理僵尸记录：服务异常中断时遗留的未完成扫描（需要在建表之后执行）
+    // 有检查点的扫描标记为 interrupted，可以从检查点继续；其余标记为失败
+    let interrupted = conn.execute(
+        "UPDATE scan_history SET status = 'interrupted', error_message = 'Service interrupted'
+         WHERE status IN ('scanning', 'paused')
+           AND scan_id IN (SELECT scan_id FROM scan_checkpoints)",
+        [],
+    )?;
+    if interrupted > 0 {
+        tracing::info!("{} interrupted scan(s) can be resumed from checkpoint", interrupted);
+    }
+    conn.execute(
+        "UPDATE scan_history SET status = 'failed', error_message = 'Service interrupted'
+         WHERE status IN ('scanning', 'paused')",
         [],
-        |row| row.get(0).map(|count: i64| count > 0),
-    );
+    )?;
+
+    Ok(())
+}
 
-    if has_current_file.unwrap_or(false) {
-        // 字段已存在，不需要迁移
-    } else {
-        // 添加 current_file 字段
+/// 为表添加字段（如果不存在）
+/// SQLite 不支持 IF NOT EXISTS for ALTER TABLE，所以需要检查列是否存在
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> SqliteResult<()> {
+    let exists: bool = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name='{}'", table, column),
+            [],
+            |row| row.get(0).map(|count: i64| count > 0),
+        )
+        .unwrap_or(false);
+
+    if !exists {
         conn.execute(
-            "ALTER TABLE scan_history ADD COLUMN current_file TEXT",
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
             [],
         )?;
     }
-
     Ok(())
 }
 
+/// 为检查点已完成目录表添加主键（旧表存在且没有主键时重建）
+fn migrate_checkpoint_dirs(conn: &Connection) -> SqliteResult<()> {
+    let columns: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('scan_checkpoint_dirs')",
+        [],
+        |row| row.get(0),
+    )?;
+    let keyed: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('scan_checkpoint_dirs') WHERE pk > 0",
+        [],
+        |row| row.get(0),
+    )?;
+    if columns == 0 || keyed > 0 {
+        return Ok(());
+    }
+
+    conn.execute_batch(
+        "BEGIN;
+         DROP INDEX IF EXISTS idx_scan_checkpoint_dirs_scan_id;
+         ALTER TABLE scan_checkpoint_dirs RENAME TO scan_checkpoint_dirs_old;
+         CREATE TABLE scan_checkpoint_dirs (
+             scan_id TEXT NOT NULL,
+             dir_path TEXT NOT NULL,
+             PRIMARY KEY (scan_id, dir_path)
+         );
+         INSERT OR IGNORE INTO scan_checkpoint_dirs (scan_id, dir_path)
+             SELECT scan_id, dir_path FROM scan_checkpoint_dirs_old;
+         DROP TABLE scan_checkpoint_dirs_old;
+         COMMIT;",
+    )
+}
+
 #[derive(Clone)]
 pub struct Database {
     db_path: String,
@@ -154,17 +326,17 @@ impl Database {
     pub fn update_scan_progress(&self, scan_id: &str, scanned_files: i32, total_files: i32, current_file: Option<&str>) -> SqliteResult<()> {
         let conn = self.get_conn()?;
 
-        // 关键：只更新状态为 "scanning" 的记录
+        // 关键：只更新状态为 "scanning" / "paused" 的记录
         // 这样可以防止异步进度更新覆盖已完成的状态
         // 一旦 finish_scan 将状态改为 completed/failed，后续的异步进度更新将被忽略
         if let Some(file) = current_file {
             conn.execute(
-                "UPDATE scan_history SET scanned_files = ?1, total_files = ?2, current_file = ?3 WHERE scan_id = ?4 AND status = 'scanning'",
+                "UPDATE scan_history SET scanned_files = ?1, total_files = ?2, current_file = ?3 WHERE scan_id = ?4 AND status IN ('scanning', 'paused')",
                 [scanned_files.to_string(), total_files.to_string(), file.to_string(), scan_id.to_string()],
             )?;
         } else {
             conn.execute(
-                "UPDATE scan_history SET scanned_files = ?1, total_files = ?2 WHERE scan_id = ?3 AND status = 'scanning'",
+                "UPDATE scan_history SET scanned_files = ?1, total_files = ?2 WHERE scan_id = ?3 AND status IN ('scanning', 'paused')",
                 [scanned_files.to_string(), total_files.to_string(), scan_id.to_string()],
             )?;
         }
@@ -212,8 +384,11 @@ impl Database {
         // 首先查询正在扫描的记录
         let mut stmt = conn.prepare(
             "SELECT id, scan_id, scan_type, paths, status, start_time, end_time,
-                    total_files, scanned_files, threats_found, current_file, error_message
-             FROM scan_history WHERE status = 'scanning' LIMIT 1"
+                    total_files, scanned_files, threats_found, current_file, error_message,
+                    COALESCE(skipped_files, 0), COALESCE(excluded_files, 0),
+                    COALESCE(errored_files, 0), COALESCE(timed_out_files, 0),
+                    COALESCE(cached_files, 0)
+             FROM scan_history WHERE status IN ('scanning', 'paused') LIMIT 1"
         )?;
 
         let mut rows = stmt.query([])?;
@@ -232,6 +407,13 @@ impl Database {
                 threats_found: row.get(9)?,
                 current_file: row.get(10)?,
                 error_message: row.get(11)?,
+                file_counts: ScanFileCounts {
+                    skipped_files: row.get(12)?,
+                    excluded_files: row.get(13)?,
+                    errored_files: row.get(14)?,
+                    timed_out_files: row.get(15)?,
+                    cached_files: row.get(16)?,
+                },
             }));
         }
 
@@ -239,7 +421,10 @@ impl Database {
         let recent_threshold = chrono::Utc::now().timestamp() - 5;
         let mut stmt = conn.prepare(
             "SELECT id, scan_id, scan_type, paths, status, start_time, end_time,
-                    total_files, scanned_files, threats_found, current_file, error_message
+                    total_files, scanned_files, threats_found, current_file, error_message,
+                    COALESCE(skipped_files, 0), COALESCE(excluded_files, 0),
+                    COALESCE(errored_files, 0), COALESCE(timed_out_files, 0),
+                    COALESCE(cached_files, 0)
              FROM scan_history WHERE status IN ('completed', 'failed') AND end_time > ?1
              ORDER BY end_time DESC LIMIT 1"
         )?;
@@ -260,6 +445,13 @@ impl Database {
                 threats_found: row.get(9)?,
                 current_file: row.get(10)?,
                 error_message: row.get(11)?,
+                file_counts: ScanFileCounts {
+                    skipped_files: row.get(12)?,
+                    excluded_files: row.get(13)?,
+                    errored_files: row.get(14)?,
+                    timed_out_files: row.get(15)?,
+                    cached_files: row.get(16)?,
+                },
             }));
         }
 
@@ -271,7 +463,10 @@ impl Database {
         let conn = self.get_conn()?;
         let mut stmt = conn.prepare(
             "SELECT id, scan_id, scan_type, paths, status, start_time, end_time,
-                    total_files, scanned_files, threats_found, current_file, error_message
+                    total_files, scanned_files, threats_found, current_file, error_message,
+                    COALESCE(skipped_files, 0), COALESCE(excluded_files, 0),
+                    COALESCE(errored_files, 0), COALESCE(timed_out_files, 0),
+                    COALESCE(cached_files, 0)
              FROM scan_history WHERE scan_id = ?1"
         )?;
 
@@ -291,6 +486,13 @@ impl Database {
                 threats_found: row.get(9)?,
                 current_file: row.get(10)?,
                 error_message: row.get(11)?,
+                file_counts: ScanFileCounts {
+                    skipped_files: row.get(12)?,
+                    excluded_files: row.get(13)?,
+                    errored_files: row.get(14)?,
+                    timed_out_files: row.get(15)?,
+                    cached_files: row.get(16)?,
+                },
             }));
         }
 
@@ -301,7 +503,10 @@ impl Database {
         let conn = self.get_conn()?;
         let mut stmt = conn.prepare(
             "SELECT id, scan_id, scan_type, paths, status, start_time, end_time,
-                    total_files, scanned_files, threats_found, current_file, error_message
+                    total_files, scanned_files, threats_found, current_file, error_message,
+                    COALESCE(skipped_files, 0), COALESCE(excluded_files, 0),
+                    COALESCE(errored_files, 0), COALESCE(timed_out_files, 0),
+                    COALESCE(cached_files, 0)
              FROM scan_history ORDER BY start_time DESC LIMIT ?1"
         )?;
 
@@ -322,6 +527,294 @@ impl Database {
                 threats_found: row.get(9)?,
                 current_file: row.get(10)?,
                 error_message: row.get(11)?,
+                file_counts: ScanFileCounts {
+                    skipped_files: row.get(12)?,
+                    excluded_files: row.get(13)?,
+                    errored_files: row.get(14)?,
+                    timed_out_files: row.get(15)?,
+                    cached_files: row.get(16)?,
+                },
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// 保存未被扫描文件的分类计数
+    pub fn update_scan_file_counts(&self, scan_id: &str, counts: &ScanFileCounts) -> SqliteResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "UPDATE scan_history SET skipped_files = ?1, excluded_files = ?2, errored_files = ?3, timed_out_files = ?4,
+                    cached_files = ?5
+             WHERE scan_id = ?6",
+            rusqlite::params![
+                counts.skipped_files,
+                counts.excluded_files,
+                counts.errored_files,
+                counts.timed_out_files,
+                counts.cached_files,
+                scan_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 批量保存扫描文件事件
+    pub fn add_scan_file_events(&self, scan_id: &str, events: &[ScanFileEventRecord]) -> SqliteResult<()> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO scan_file_events (scan_id, file_path, reason, category, error_code, detail)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            )?;
+            for event in events {
+                stmt.execute(rusqlite::params![
+                    scan_id,
+                    event.file_path,
+                    event.reason,
+                    event.category,
+                    event.error_code,
+                    event.detail,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// 分页获取扫描文件事件，可按原因过滤，返回 (当前页, 总数)
+    pub fn get_scan_file_events(
+        &self,
+        scan_id: &str,
+        reason: Option<&str>,
+        offset: i64,
+        limit: i64,
+    ) -> SqliteResult<(Vec<ScanFileEventRecord>, i64)> {
+        let conn = self.get_conn()?;
+
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM scan_file_events WHERE scan_id = ?1 AND (?2 IS NULL OR reason = ?2)",
+            rusqlite::params![scan_id, reason],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, file_path, reason, category, error_code, detail
+             FROM scan_file_events WHERE scan_id = ?1 AND (?2 IS NULL OR reason = ?2)
+             ORDER BY id LIMIT ?3 OFFSET ?4"
+        )?;
+
+        let rows = stmt.query_map(rusqlite::params![scan_id, reason, limit, offset], |row| {
+            Ok(ScanFileEventRecord {
+                id: row.get(0)?,
+                file_path: row.get(1)?,
+                reason: row.get(2)?,
+                category: row.get(3)?,
+                error_code: row.get(4)?,
+                detail: row.get(5)?,
+            })
+        })?;
+
+        Ok((rows.collect::<SqliteResult<Vec<_>>>()?, total))
+    }
+
+    /// 保存扫描各根目录的结果
+    pub fn save_scan_roots(&self, scan_id: &str, roots: &[ScanRootRecord]) -> SqliteResult<()> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM scan_roots WHERE scan_id = ?1", [scan_id])?;
+        for root in roots {
+            tx.execute(
+                "INSERT INTO scan_roots (scan_id, root_path, total_files, scanned_files, threats_found, error_count, error_message)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    scan_id,
+                    root.root_path,
+                    root.total_files,
+                    root.scanned_files,
+                    root.threats_found,
+                    root.error_count,
+                    root.error_message,
+                ],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// 获取扫描各根目录的结果（按提交顺序）
+    pub fn get_scan_roots(&self, scan_id: &str) -> SqliteResult<Vec<ScanRootRecord>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT root_path, total_files, scanned_files, threats_found, error_count, error_message
+             FROM scan_roots WHERE scan_id = ?1 ORDER BY id"
+        )?;
+
+        let rows = stmt.query_map([scan_id], |row| {
+            Ok(ScanRootRecord {
+                root_path: row.get(0)?,
+                total_files: row.get(1)?,
+                scanned_files: row.get(2)?,
+                threats_found: row.get(3)?,
+                error_count: row.get(4)?,
+                error_message: row.get(5)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    // === 扫描检查点 ===
+
+    /// 创建扫描检查点记录（保存恢复扫描所需的原始请求）
+    pub fn create_scan_checkpoint(&self, scan_id: &str, request_json: &str) -> SqliteResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO scan_checkpoints (scan_id, request, state, updated_at) VALUES (?1, ?2, NULL, ?3)",
+            rusqlite::params![scan_id, request_json, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 保存检查点：更新累计计数，追加新完成的目录及其威胁和文件事件（JSON）
+    ///
+    /// 检查点记录已删除（扫描已结束）时不做任何修改
+    pub fn save_scan_checkpoint(
+        &self,
+        scan_id: &str,
+        counters_json: &str,
+        completed_dirs: &[String],
+        threats_json: &[String],
+        events_json: &[String],
+    ) -> SqliteResult<()> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        let updated = tx.execute(
+            "UPDATE scan_checkpoints SET state = ?1, updated_at = ?2 WHERE scan_id = ?3",
+            rusqlite::params![counters_json, chrono::Utc::now().timestamp(), scan_id],
+        )?;
+        if updated == 0 {
+            return Ok(());
+        }
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO scan_checkpoint_dirs (scan_id, dir_path) VALUES (?1, ?2)"
+            )?;
+            for dir in completed_dirs {
+                stmt.execute([scan_id, dir.as_str()])?;
+            }
+
+            let mut stmt = tx.prepare(
+                "INSERT INTO scan_checkpoint_threats (scan_id, threat) VALUES (?1, ?2)"
+            )?;
+            for threat in threats_json {
+                stmt.execute([scan_id, threat.as_str()])?;
+            }
+
+            let mut stmt = tx.prepare(
+                "INSERT INTO scan_checkpoint_events (scan_id, event) VALUES (?1, ?2)"
+            )?;
+            for event in events_json {
+                stmt.execute([scan_id, event.as_str()])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// 获取扫描检查点
+    pub fn get_scan_checkpoint(&self, scan_id: &str) -> SqliteResult<Option<ScanCheckpointRecord>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT scan_id, request, state, updated_at FROM scan_checkpoints WHERE scan_id = ?1"
+        )?;
+
+        let mut rows = stmt.query_map([scan_id], |row| {
+            Ok(ScanCheckpointRecord {
+                scan_id: row.get(0)?,
+                request: row.get(1)?,
+                state: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?;
+
+        rows.next().transpose()
+    }
+
+    /// 获取检查点中已完成的目录
+    pub fn get_scan_checkpoint_dirs(&self, scan_id: &str) -> SqliteResult<Vec<String>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT dir_path FROM scan_checkpoint_dirs WHERE scan_id = ?1"
+        )?;
+        let rows = stmt.query_map([scan_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 获取检查点中已完成目录的威胁（JSON，按保存顺序）
+    pub fn get_scan_checkpoint_threats(&self, scan_id: &str) -> SqliteResult<Vec<String>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT threat FROM scan_checkpoint_threats WHERE scan_id = ?1 ORDER BY rowid"
+        )?;
+        let rows = stmt.query_map([scan_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 获取检查点中已完成目录的文件事件（JSON，按保存顺序）
+    pub fn get_scan_checkpoint_events(&self, scan_id: &str) -> SqliteResult<Vec<String>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT event FROM scan_checkpoint_events WHERE scan_id = ?1 ORDER BY rowid"
+        )?;
+        let rows = stmt.query_map([scan_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 删除扫描检查点（扫描结束或放弃恢复时）
+    pub fn delete_scan_checkpoint(&self, scan_id: &str) -> SqliteResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute("DELETE FROM scan_checkpoint_dirs WHERE scan_id = ?1", [scan_id])?;
+        conn.execute("DELETE FROM scan_checkpoint_threats WHERE scan_id = ?1", [scan_id])?;
+        conn.execute("DELETE FROM scan_checkpoint_events WHERE scan_id = ?1", [scan_id])?;
+        conn.execute("DELETE FROM scan_checkpoints WHERE scan_id = ?1", [scan_id])?;
+        Ok(())
+    }
+
+    /// 获取可以从检查点恢复的扫描（服务重启时被中断）
+    pub fn get_interrupted_scans(&self) -> SqliteResult<Vec<ScanRecord>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, scan_id, scan_type, paths, status, start_time, end_time,
+                    total_files, scanned_files, threats_found, current_file, error_message,
+                    COALESCE(skipped_files, 0), COALESCE(excluded_files, 0),
+                    COALESCE(errored_files, 0), COALESCE(timed_out_files, 0),
+                    COALESCE(cached_files, 0)
+             FROM scan_history WHERE status = 'interrupted' ORDER BY start_time DESC"
+        )?;
+
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+
+        while let Some(row) = rows.next()? {
+            results.push(ScanRecord {
+                id: row.get(0)?,
+                scan_id: row.get(1)?,
+                scan_type: row.get(2)?,
+                paths: row.get(3)?,
+                status: row.get(4)?,
+                start_time: row.get(5)?,
+                end_time: row.get(6)?,
+                total_files: row.get(7)?,
+                scanned_files: row.get(8)?,
+                threats_found: row.get(9)?,
+                current_file: row.get(10)?,
+                error_message: row.get(11)?,
+                file_counts: ScanFileCounts {
+                    skipped_files: row.get(12)?,
+                    excluded_files: row.get(13)?,
+                    errored_files: row.get(14)?,
+                    timed_out_files: row.get(15)?,
+                    cached_files: row.get(16)?,
+                },
             });
         }
 
@@ -331,6 +824,30 @@ impl Database {
     /// 删除单条扫描历史记录
     pub fn delete_scan_history(&self, id: i64) -> SqliteResult<()> {
         let conn = self.get_conn()?;
+        conn.execute(
+            "DELETE FROM scan_checkpoint_dirs WHERE scan_id = (SELECT scan_id FROM scan_history WHERE id = ?1)",
+            [id],
+        )?;
+        conn.execute(
+            "DELETE FROM scan_checkpoint_threats WHERE scan_id = (SELECT scan_id FROM scan_history WHERE id = ?1)",
+            [id],
+        )?;
+        conn.execute(
+            "DELETE FROM scan_checkpoint_events WHERE scan_id = (SELECT scan_id FROM scan_history WHERE id = ?1)",
+            [id],
+        )?;
+        conn.execute(
+            "DELETE FROM scan_checkpoints WHERE scan_id = (SELECT scan_id FROM scan_history WHERE id = ?1)",
+            [id],
+        )?;
+        conn.execute(
+            "DELETE FROM scan_roots WHERE scan_id = (SELECT scan_id FROM scan_history WHERE id = ?1)",
+            [id],
+        )?;
+        conn.execute(
+            "DELETE FROM scan_file_events WHERE scan_id = (SELECT scan_id FROM scan_history WHERE id = ?1)",
+            [id],
+        )?;
         conn.execute(
             "DELETE FROM scan_history WHERE id = ?1",
             [id],
@@ -341,6 +858,12 @@ impl Database {
     /// 清空所有扫描历史记录
     pub fn clear_scan_history(&self) -> SqliteResult<()> {
         let conn = self.get_conn()?;
+        conn.execute("DELETE FROM scan_roots", [])?;
+        conn.execute("DELETE FROM scan_file_events", [])?;
+        conn.execute("DELETE FROM scan_checkpoint_dirs", [])?;
+        conn.execute("DELETE FROM scan_checkpoint_threats", [])?;
+        conn.execute("DELETE FROM scan_checkpoint_events", [])?;
+        conn.execute("DELETE FROM scan_checkpoints", [])?;
         conn.execute("DELETE FROM scan_history", [])?;
         Ok(())
     }
@@ -352,12 +875,15 @@ impl Database {
         scan_id: &str,
         file_path: &str,
         virus_name: &str,
+        file_hash: Option<&str>,
+        file_type: Option<&str>,
+        container_path: Option<&str>,
     ) -> SqliteResult<i64> {
         let conn = self.get_conn()?;
         conn.execute(
-            "INSERT INTO threat_records (scan_id, file_path, virus_name)
-             VALUES (?1, ?2, ?3)",
-            [scan_id, file_path, virus_name],
+            "INSERT INTO threat_records (scan_id, file_path, virus_name, file_hash, file_type, container_path)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![scan_id, file_path, virus_name, file_hash, file_type, container_path],
         )?;
         Ok(conn.last_insert_rowid())
     }
@@ -392,10 +918,10 @@ impl Database {
         // 返回所有威胁记录（完整历史）
         // action_taken 用于前端显示处理状态：null=待处理，ignored=已忽略，quarantined=已隔离，deleted=已删除
         let sql = if scan_id.is_some() {
-            "SELECT id, scan_id, file_path, virus_name, action_taken, action_time, original_location, file_hash
+            "SELECT id, scan_id, file_path, virus_name, action_taken, action_time, original_location, file_hash, file_type, container_path
              FROM threat_records WHERE scan_id = ?1 ORDER BY id DESC LIMIT ?2"
         } else {
-            "SELECT id, scan_id, file_path, virus_name, action_taken, action_time, original_location, file_hash
+            "SELECT id, scan_id, file_path, virus_name, action_taken, action_time, original_location, file_hash, file_type, container_path
              FROM threat_records ORDER BY id DESC LIMIT ?1"
         };
 
@@ -418,6 +944,8 @@ impl Database {
                 action_time: row.get(5)?,
                 original_location: row.get(6)?,
                 file_hash: row.get(7)?,
+                file_type: row.get(8)?,
+                container_path: row.get(9)?,
             });
         }
 
@@ -427,7 +955,7 @@ impl Database {
     pub fn get_threat_by_id(&self, threat_id: i64) -> SqliteResult<Option<ThreatRecord>> {
         let conn = self.get_conn()?;
         let mut stmt = conn.prepare(
-            "SELECT id, scan_id, file_path, virus_name, action_taken, action_time, original_location, file_hash
+            "SELECT id, scan_id, file_path, virus_name, action_taken, action_time, original_location, file_hash, file_type, container_path
              FROM threat_records WHERE id = ?1"
         )?;
 
@@ -443,6 +971,8 @@ impl Database {
                 action_time: row.get(5)?,
                 original_location: row.get(6)?,
                 file_hash: row.get(7)?,
+                file_type: row.get(8)?,
+                container_path: row.get(9)?,
             }))
         } else {
             Ok(None)
@@ -612,6 +1142,50 @@ pub struct ScanRecord {
     pub threats_found: i32,
     pub current_file: Option<String>,
     pub error_message: Option<String>,
+    pub file_counts: ScanFileCounts,
+}
+
+/// 未被扫描文件的分类计数
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScanFileCounts {
+    pub skipped_files: i32,
+    pub excluded_files: i32,
+    pub errored_files: i32,
+    pub timed_out_files: i32,
+    /// 未修改、使用缓存结果跳过的文件数（计入已扫描）
+    pub cached_files: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanFileEventRecord {
+    pub id: i64,
+    pub file_path: String,
+    pub reason: String,
+    pub category: Option<String>,
+    pub error_code: Option<i32>,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanCheckpointRecord {
+    pub scan_id: String,
+    /// 原始扫描请求（JSON）
+    pub request: String,
+    /// 已完成目录的累计计数（JSON），尚未保存过检查点时为 None
+    ///
+    /// 威胁和文件事件保存在 scan_checkpoint_threats、scan_checkpoint_events 中
+    pub state: Option<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanRootRecord {
+    pub root_path: String,
+    pub total_files: i32,
+    pub scanned_files: i32,
+    pub threats_found: i32,
+    pub error_count: i32,
+    pub error_message: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -624,6 +1198,8 @@ pub struct ThreatRecord {
     pub action_time: Option<i64>,
     pub original_location: Option<String>,
     pub file_hash: Option<String>,
+    pub file_type: Option<String>,
+    pub container_path: Option<String>,
 }
 
 #[derive(Debug, Clone)]
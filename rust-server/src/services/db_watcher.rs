@@ -0,0 +1,143 @@
+use crate::clamav::database::database_version;
+use crate::clamav::{ClamAVEngine, DatabaseStat, EngineManager, Scanner};
+use crate::services::Database;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 检查病毒库目录的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 最后一次变化后等待的时间，freshclam 会依次替换多个文件
+const DEBOUNCE: Duration = Duration::from_secs(10);
+
+/// 病毒库目录监视器
+///
+/// 使用 cl_statinidir / cl_statchkdir 检测 freshclam 或手动复制导致的病毒库变化，
+/// 去抖后触发引擎热重载（加载前会校验每个数据库文件），每次重载记录到 update_history
+pub struct DbWatcher<S: Scanner = ClamAVEngine> {
+    db_dir: String,
+    engine_manager: Arc<EngineManager<S>>,
+    db: Arc<Database>,
+    stop: Arc<AtomicBool>,
+}
+
+impl<S: Scanner> DbWatcher<S> {
+    pub fn new(db_dir: String, engine_manager: Arc<EngineManager<S>>, db: Arc<Database>) -> Self {
+        Self {
+            db_dir,
+            engine_manager,
+            db,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// 在独立线程中启动监视，返回停止标志
+    pub fn spawn(self) -> Arc<AtomicBool> {
+        let stop = self.stop.clone();
+        std::thread::Builder::new()
+            .name("clamav-db-watcher".to_string())
+            .spawn(move || self.run())
+            .expect("Failed to spawn database watcher thread");
+        stop
+    }
+
+    fn run(self) {
+        tracing::info!("Watching virus database directory: {}", self.db_dir);
+
+        let mut stat = self.init_stat();
+        let mut last_change: Option<Instant> = None;
+        // 当前引擎加载的 daily 版本
+        let mut loaded_version = database_version(&self.db_dir, "daily").map(|v| v.to_string());
+
+        while !self.stop.load(Ordering::Relaxed) {
+            std::thread::sleep(POLL_INTERVAL);
+
+            // 检查失败时只重新初始化，不重置去抖计时，避免每次轮询都推迟重载
+            let changed = match stat.as_ref().map(|s| s.has_changed()) {
+                Some(Ok(changed)) => changed,
+                Some(Err(e)) => {
+                    tracing::warn!("Failed to check database directory: {}", e);
+                    stat = self.init_stat();
+                    false
+                }
+                // 之前初始化失败（例如目录不存在或 libclamav 未加载），重新尝试；
+                // 成功时目录内容可能已变化，按一次变化处理
+                None => {
+                    stat = self.init_stat();
+                    stat.is_some()
+                }
+            };
+
+            if changed {
+                tracing::debug!("Virus database directory changed, waiting for it to settle");
+                last_change = Some(Instant::now());
+                stat = self.init_stat();
+                continue;
+            }
+
+            match last_change {
+                Some(t) if t.elapsed() >= DEBOUNCE => {
+                    last_change = None;
+                    loaded_version = self.reload(loaded_version);
+                    // 重载时会把损坏的文件移入 rejected/ 或从 known-good/ 恢复，
+                    // 以重载后的目录状态为基准，避免把这些变化当作新的更新再次重载
+                    stat = self.init_stat();
+                }
+                _ => {}
+            }
+        }
+
+        tracing::info!("Database watcher stopped");
+    }
+
+    fn init_stat(&self) -> Option<DatabaseStat> {
+        match DatabaseStat::new(&self.db_dir) {
+            Ok(stat) => Some(stat),
+            Err(e) => {
+                tracing::warn!("Failed to stat database directory {}: {}", self.db_dir, e);
+                None
+            }
+        }
+    }
+
+    /// 重新加载引擎，返回成功后的版本
+    fn reload(&self, old_version: Option<String>) -> Option<String> {
+        let result = self.engine_manager.reload();
+
+        // 校验后实际使用的版本（损坏的文件可能已被拒绝并恢复为旧版本）
+        let new_version = database_version(&self.db_dir, "daily").map(|v| v.to_string());
+
+        match &result {
+            Ok(()) => tracing::info!(
+                "Engine reloaded after database change: {:?} -> {:?}",
+                old_version, new_version
+            ),
+            Err(e) => tracing::error!("Database change not applied: {}", e),
+        }
+
+        // 成功重载但有文件被拒绝时，同样记录原因
+        let message = match &result {
+            Ok(()) => self.engine_manager.engine_info()
+                .filter(|info| !info.rejected_files.is_empty())
+                .map(|info| {
+                    info.rejected_files.iter()
+                        .map(|r| format!("rejected {}: {}", r.file, r.reason))
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                }),
+            Err(e) => Some(e.clone()),
+        };
+
+        if let Err(e) = self.db.add_update_history(
+            old_version.as_deref(),
+            new_version.as_deref(),
+            if result.is_ok() { "success" } else { "failed" },
+            message.as_deref(),
+        ) {
+            tracing::error!("Failed to record reload in update history: {}", e);
+        }
+
+        if result.is_ok() { new_version } else { old_version }
+    }
+}
@@ -4,6 +4,9 @@ mod scan;
 mod update;
 mod clamav;
 mod quarantine;
+mod signature;
+mod db_watcher;
+mod verdict_store;
 
 pub use state::AppState;
 pub use db::{init_db, Database};
@@ -11,3 +14,6 @@ pub use scan::ScanService;
 pub use update::UpdateService;
 pub use clamav::ClamavService;
 pub use quarantine::QuarantineService;
+pub use signature::SignatureService;
+pub use db_watcher::DbWatcher;
+pub use verdict_store::VerdictStore;
@@ -20,12 +20,14 @@ impl QuarantineService {
         virus_name: &str,
         scan_id: &str,
         file_size: u64,
+        file_hash: Option<&str>,
     ) -> Result<String, String> {
         let metadata = QuarantineMetadata::new(
             original_path.to_string(),
             virus_name.to_string(),
             scan_id.to_string(),
             file_size,
+            file_hash.map(|h| h.to_string()),
         );
 
         let files_dir = format!("{}/files", self.env.quarantine_dir());
@@ -140,6 +142,7 @@ impl QuarantineService {
                 virus_name: metadata.virus_name,
                 quarantined_at: metadata.quarantined_at,
                 scan_id: metadata.scan_id,
+                file_hash: metadata.file_hash,
             });
         }
 
@@ -10,17 +10,23 @@ use std::collections::HashMap;
 use anyhow::{Result, Context};
 use tokio::sync::RwLock;
 
-use crate::services::Database;
+use crate::services::{Database, VerdictStore};
 use crate::services::clamav::{ClamavService, ScanRequest};
-use crate::clamav::engine::{ScanTarget, TaskPriority};
-use crate::clamav::ScanOptions;
+use crate::clamav::{ClamAVEngine, Scanner};
+use crate::clamav::engine::{ScanTarget, ScanTask, TaskPriority};
+use crate::clamav::{CheckpointState, DiscoveryOptions, ResumeState, ScanOptions, VerdictCache, VerdictCachePolicy};
 use crate::clamav::ScanProgress;
+use crate::models::config::AppConfig;
+use crate::models::scan::ScanRootProgress;
+use crate::services::db::{ScanFileCounts, ScanFileEventRecord, ScanRootRecord};
 
 /// 扫描服务
-pub struct ScanService {
+pub struct ScanService<S: Scanner = ClamAVEngine> {
     db: Arc<Database>,
-    pub clamav: ClamavService,
+    pub clamav: ClamavService<S>,
     active_scans: Arc<RwLock<HashMap<String, ActiveScan>>>,
+    /// 扫描结果缓存（增量扫描），未设置时每次都完整扫描
+    verdict_store: Option<Arc<VerdictStore>>,
 }
 
 /// 活跃的扫描任务
@@ -36,26 +42,68 @@ pub struct ActiveScan {
     pub discovered_files: u32,  // 已发现的文件数（两线程模式）
     pub scan_rate: f32,         // 扫描速率（文件/秒，EMA 计算）
     pub current_file: Option<String>,
+    pub worker_files: Vec<Option<String>>,  // 各扫描工作线程正在扫描的文件
+    pub roots: Vec<ScanRootProgress>,       // 多根目录扫描时各根目录的进度
     pub threats_found: u32,
     pub status: String,  // "scanning", "completed", "failed", "paused"
 }
 
-impl ScanService {
+/// 提交扫描任务的参数（新扫描和从检查点继续共用）
+struct LaunchOptions {
+    priority: TaskPriority,
+    options: ScanOptions,
+    discovery: DiscoveryOptions,
+    cache: Option<VerdictCachePolicy>,
+    resume: Option<ResumeState>,
+}
+
+impl<S: Scanner> ScanService<S> {
     /// 创建新的扫描服务
-    pub fn new(db: Arc<Database>, clamav: ClamavService) -> Self {
+    pub fn new(db: Arc<Database>, clamav: ClamavService<S>) -> Self {
         Self {
             db,
             clamav,
             active_scans: Arc::new(RwLock::new(HashMap::new())),
+            verdict_store: None,
         }
     }
 
+    /// 使用扫描结果缓存
+    pub fn with_verdict_store(mut self, store: Arc<VerdictStore>) -> Self {
+        self.verdict_store = Some(store);
+        self
+    }
+
+    /// 扫描结果缓存的存储（统计、清空）
+    pub fn verdict_store(&self) -> Option<Arc<VerdictStore>> {
+        self.verdict_store.clone()
+    }
+
+    /// 扫描使用的扫描结果缓存
+    pub fn verdict_cache(&self) -> Option<Arc<dyn VerdictCache>> {
+        self.verdict_store.clone().map(|store| store as Arc<dyn VerdictCache>)
+    }
+
+    /// 在后台删除过期的缓存记录
+    pub fn prune_verdict_cache(&self, max_age_days: u32) {
+        let Some(store) = self.verdict_store.clone() else {
+            return;
+        };
+        tokio::task::spawn_blocking(move || match store.prune(max_age_days) {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Pruned {} verdict cache entries older than {} days", n, max_age_days),
+            Err(e) => tracing::warn!("Failed to prune verdict cache: {}", e),
+        });
+    }
+
     /// 初始化回调（只调用一次）
     pub async fn initialize_callbacks(&self) {
         let db = self.db.clone();
         let active_scans = self.active_scans.clone();
         let active_scans_for_progress = self.active_scans.clone();
         let db_for_progress = self.db.clone();
+        let active_scans_for_checkpoint = self.active_scans.clone();
+        let db_for_checkpoint = self.db.clone();
 
         // 设置进度回调（只设置一次）
         self.clamav.set_progress_callback(move |progress| {
@@ -66,6 +114,18 @@ impl ScanService {
             let discovered = progress.discovered_files.0;
             let rate = progress.scan_rate.map(|r| r.0).unwrap_or(0.0);
             let current_file = progress.current_file.as_ref().map(|f| f.0.clone());
+            let worker_files: Vec<Option<String>> = progress.worker_files.iter()
+                .map(|f| f.as_ref().map(|f| f.0.clone()))
+                .collect();
+            let roots: Vec<ScanRootProgress> = progress.roots.iter()
+                .map(|r| ScanRootProgress {
+                    path: r.path.0.clone(),
+                    discovered: r.discovered_files.0 as u64,
+                    scanned: r.scanned_files.0 as u64,
+                    threats: r.threats_found.0,
+                    discovery_complete: r.discovery_complete,
+                })
+                .collect();
             let threats = progress.threats_found.0;
 
             // 使用 block_in_place 在同步回调中执行异步操作
@@ -73,7 +133,8 @@ impl ScanService {
                 let scans = active_scans.try_read();
                 if let Ok(scans) = scans {
                     scans.iter()
-                        .find(|(_, s)| s.status == "scanning")
+                        // 暂停时仍需记录暂停前正在处理的文件
+                        .find(|(_, s)| s.status == "scanning" || s.status == "paused")
                         .map(|(id, _)| id.clone())
                 } else {
                     None
@@ -94,6 +155,8 @@ impl ScanService {
                         s.discovered_files = discovered;
                         s.scan_rate = rate;
                         s.current_file = current_file.clone();
+                        s.worker_files = worker_files;
+                        s.roots = roots;
                         s.threats_found = threats;
                     }
                 });
@@ -114,6 +177,57 @@ impl ScanService {
             }
         }).await;
 
+        // 设置检查点回调（只设置一次）
+        self.clamav.set_checkpoint_callback(move |task_id, checkpoint| {
+            let active_scans = active_scans_for_checkpoint.clone();
+            let db = db_for_checkpoint.clone();
+
+            // 根据 task_id 查找对应的 scan_id
+            let scan_id = tokio::task::block_in_place(|| {
+                let scans = active_scans.try_read();
+                if let Ok(scans) = scans {
+                    scans.iter()
+                        .find(|(_, s)| s.task_id == task_id)
+                        .map(|(id, _)| id.clone())
+                } else {
+                    None
+                }
+            });
+
+            let Some(scan_id) = scan_id else {
+                return;
+            };
+
+            // 计数整体覆盖，威胁和文件事件只追加新完成目录中的部分
+            let serialized = serde_json::to_string(&checkpoint.counters).and_then(|counters| {
+                let threats = checkpoint.threats.iter()
+                    .map(serde_json::to_string)
+                    .collect::<serde_json::Result<Vec<_>>>()?;
+                let events = checkpoint.file_events.iter()
+                    .map(serde_json::to_string)
+                    .collect::<serde_json::Result<Vec<_>>>()?;
+                Ok((counters, threats, events))
+            });
+            let (counters, threats, events) = match serialized {
+                Ok(serialized) => serialized,
+                Err(e) => {
+                    tracing::error!("Failed to serialize checkpoint for scan {}: {}", scan_id, e);
+                    return;
+                }
+            };
+            let dirs: Vec<String> = checkpoint.completed_dirs.iter()
+                .map(|d| d.to_string_lossy().to_string())
+                .collect();
+
+            tokio::spawn(async move {
+                if let Err(e) = db.save_scan_checkpoint(&scan_id, &counters, &dirs, &threats, &events) {
+                    tracing::error!("Failed to save checkpoint for scan {}: {}", scan_id, e);
+                } else {
+                    tracing::debug!("Saved checkpoint for scan {}: {} new completed dirs", scan_id, dirs.len());
+                }
+            });
+        }).await;
+
         // 设置完成回调（只设置一次）
         self.clamav.set_completion_callback(move |task_id, result| {
             let db = db.clone();
@@ -148,13 +262,67 @@ impl ScanService {
                     } else {
                         "扫描完成，发现威胁"
                     };
-                    tracing::info!("Scan {} completed: total={}, threats={}", scan_id, total, threats_count);
+                    tracing::info!("Scan {} completed: total={}, threats={}, errors={}", scan_id, total, threats_count, outcome.errors.total());
+                    if outcome.errors.total() > 0 {
+                        tracing::info!("Scan {} error breakdown: {:?}", scan_id, outcome.errors);
+                    }
 
                     // 保存威胁记录到数据库
-                    for (file_path, virus_name) in &outcome.threats {
-                        tracing::info!("Saving threat: {} -> {}", file_path.0, virus_name.0);
-                        if let Err(e) = db.add_threat(&scan_id, &file_path.0, &virus_name.0) {
-                            tracing::error!("Failed to save threat {}: {}", file_path.0, e);
+                    for threat in &outcome.threats {
+                        tracing::info!("Saving threat: {} -> {}", threat.file_path.0, threat.virus_name.0);
+                        if let Err(e) = db.add_threat(
+                            &scan_id,
+                            &threat.file_path.0,
+                            &threat.virus_name.0,
+                            threat.file_hash.as_deref(),
+                            threat.file_type.as_deref(),
+                            threat.container_path.as_deref(),
+                        ) {
+                            tracing::error!("Failed to save threat {}: {}", threat.file_path.0, e);
+                        }
+                    }
+
+                    // 保存各根目录的结果
+                    if !outcome.roots.is_empty() {
+                        let roots: Vec<ScanRootRecord> = outcome.roots.iter()
+                            .map(|r| ScanRootRecord {
+                                root_path: r.path.clone(),
+                                total_files: r.total_files as i32,
+                                scanned_files: r.scanned_files as i32,
+                                threats_found: r.threats_found as i32,
+                                error_count: r.errors.total() as i32,
+                                error_message: r.error_message.clone(),
+                            })
+                            .collect();
+                        if let Err(e) = db.save_scan_roots(&scan_id, &roots) {
+                            tracing::error!("Failed to save root results for scan {}: {}", scan_id, e);
+                        }
+                    }
+
+                    // 保存未被扫描文件的计数和明细
+                    let counts = ScanFileCounts {
+                        skipped_files: outcome.skipped_files as i32,
+                        excluded_files: outcome.excluded_files as i32,
+                        errored_files: outcome.errored_files as i32,
+                        timed_out_files: outcome.timed_out_files as i32,
+                        cached_files: outcome.cached_files as i32,
+                    };
+                    if let Err(e) = db.update_scan_file_counts(&scan_id, &counts) {
+                        tracing::error!("Failed to save file counts for scan {}: {}", scan_id, e);
+                    }
+                    if !outcome.file_events.is_empty() {
+                        let events: Vec<ScanFileEventRecord> = outcome.file_events.iter()
+                            .map(|e| ScanFileEventRecord {
+                                id: 0,
+                                file_path: e.path.clone(),
+                                reason: e.reason.as_str().to_string(),
+                                category: e.category.map(|c| c.as_str().to_string()),
+                                error_code: e.error_code,
+                                detail: e.detail.clone(),
+                            })
+                            .collect();
+                        if let Err(e) = db.add_scan_file_events(&scan_id, &events) {
+                            tracing::error!("Failed to save file events for scan {}: {}", scan_id, e);
                         }
                     }
 
@@ -166,6 +334,11 @@ impl ScanService {
                 }
             }
 
+            // 扫描已结束，不再需要检查点
+            if let Err(e) = db.delete_scan_checkpoint(&scan_id) {
+                tracing::error!("Failed to delete checkpoint for scan {}: {}", scan_id, e);
+            }
+
             // 从 active_scans 移除
             tokio::spawn(async move {
                 let mut scans = active_scans.write().await;
@@ -181,8 +354,66 @@ impl ScanService {
         paths: Vec<String>,
         priority: TaskPriority,
         options: ScanOptions,
+        discovery: DiscoveryOptions,
+        cache: Option<VerdictCachePolicy>,
     ) -> Result<String> {
-        tracing::info!("Starting scan {} with paths: {:?}", scan_id, paths);
+        tracing::info!("Starting scan {} with paths: {:?} ({} exclusion rules, follow_symlinks={}, one_filesystem={}, verdict cache: {:?})",
+                      scan_id, paths, discovery.exclusions.len(), discovery.follow_symlinks, discovery.one_filesystem, cache);
+
+        let launch = LaunchOptions { priority, options, discovery, cache, resume: None };
+        self.launch_scan(scan_id, paths, launch).await
+    }
+
+    /// 从检查点继续被服务重启中断的扫描（沿用同一个 scan_id）
+    pub async fn resume_interrupted_scan(&self, scan_id: &str, config: &AppConfig) -> Result<String> {
+        let record = self.db.get_scan_by_id(scan_id)?
+            .ok_or_else(|| anyhow::anyhow!("Scan not found: {}", scan_id))?;
+        if record.status != "interrupted" {
+            return Err(anyhow::anyhow!("Scan {} is not interrupted (status: {})", scan_id, record.status));
+        }
+        let checkpoint = self.db.get_scan_checkpoint(scan_id)?
+            .ok_or_else(|| anyhow::anyhow!("No checkpoint for scan: {}", scan_id))?;
+
+        // 按原始请求重建扫描参数（排除规则与当前配置合并）
+        let request: crate::models::scan::ScanRequest = serde_json::from_str(&checkpoint.request)
+            .context("Invalid checkpoint request")?;
+        let paths: Vec<String> = serde_json::from_str(&record.paths)
+            .context("Invalid scan paths")?;
+        let discovery = config.scan.discovery_options(&request.exclude, request.follow_symlinks, request.one_filesystem)?;
+        let options = request.options
+            .as_ref()
+            .map(|o| o.to_scan_options())
+            .unwrap_or_default();
+
+        let mut state: CheckpointState = match checkpoint.state.as_deref() {
+            Some(state) => serde_json::from_str(state).context("Invalid checkpoint state")?,
+            None => CheckpointState::default(),
+        };
+        for threat in self.db.get_scan_checkpoint_threats(scan_id)? {
+            state.threats.push(serde_json::from_str(&threat).context("Invalid checkpoint threat")?);
+        }
+        for event in self.db.get_scan_checkpoint_events(scan_id)? {
+            state.file_events.push(serde_json::from_str(&event).context("Invalid checkpoint file event")?);
+        }
+        let completed_dirs = self.db.get_scan_checkpoint_dirs(scan_id)?
+            .into_iter()
+            .map(std::path::PathBuf::from)
+            .collect();
+
+        tracing::info!("Resuming scan {} from checkpoint: {} files scanned, {} threats found",
+                      scan_id, state.counters.scanned_files(), state.counters.threats_found());
+
+        let _ = self.db.update_scan_status(scan_id, "scanning");
+
+        let cache = config.scan.verdict_cache_policy(self.verdict_cache(), request.force_full_rescan);
+        let resume = ResumeState { completed_dirs, state };
+        let launch = LaunchOptions { priority: TaskPriority::Normal, options, discovery, cache, resume: Some(resume) };
+        self.launch_scan(scan_id.to_string(), paths, launch).await
+    }
+
+    /// 记录活跃扫描并提交扫描任务
+    async fn launch_scan(&self, scan_id: String, paths: Vec<String>, launch: LaunchOptions) -> Result<String> {
+        let LaunchOptions { priority, options, discovery, cache, resume } = launch;
 
         // 创建扫描请求
         let request = ScanRequest::new(paths.clone())
@@ -196,9 +427,12 @@ impl ScanService {
             return Err(anyhow::anyhow!("No valid paths to scan").into());
         }
 
-        // 对于多路径扫描，我们为每个路径创建一个任务
-        // 这里简化处理：只处理第一个目标
-        let target = targets.into_iter().next().unwrap();
+        // 所有路径在同一个任务中扫描；不存在的路径保留在目标中，作为该根目录的失败结果记录
+        let target = if targets.len() == 1 && paths.len() == 1 {
+            targets.into_iter().next().unwrap()
+        } else {
+            ScanTarget::from_paths(&paths).unwrap()
+        };
         tracing::info!("Scan target: {:?}", target);
 
         // 记录活跃扫描
@@ -207,12 +441,14 @@ impl ScanService {
             task_id: String::new(), // 临时值，下面会更新
             paths,
             created_at: chrono::Utc::now(),
-            scanned_files: 0,
+            scanned_files: resume.as_ref().map_or(0, |r| r.state.counters.scanned_files()),
             total_files: 0,
-            discovered_files: 0,
+            discovered_files: resume.as_ref().map_or(0, |r| r.state.counters.discovered_files()),
             scan_rate: 0.0,
             current_file: None,
-            threats_found: 0,
+            worker_files: Vec::new(),
+            roots: Vec::new(),
+            threats_found: resume.as_ref().map_or(0, |r| r.state.counters.threats_found()),
             status: "scanning".to_string(),
         };
 
@@ -224,7 +460,14 @@ impl ScanService {
 
         // 提交扫描任务
         tracing::info!("Submitting scan task to engine...");
-        let task_id = self.clamav.submit_scan(target, priority, options).await?;
+        let mut task = ScanTask::new(target, priority, options).with_discovery(discovery);
+        if let Some(cache) = cache {
+            task = task.with_cache(cache);
+        }
+        if let Some(resume) = resume {
+            task = task.with_resume(resume);
+        }
+        let task_id = self.clamav.submit_task(task).await?;
         tracing::info!("Scan task submitted with task_id={}", task_id);
 
         // 更新活跃扫描的 task_id
@@ -250,6 +493,7 @@ impl ScanService {
 
         // 更新数据库
         let _ = self.db.finish_scan(scan_id, "stopped", 0, 0, Some("Stopped by user"));
+        let _ = self.db.delete_scan_checkpoint(scan_id);
 
         // 移除活跃扫描
         let mut scans = self.active_scans.write().await;
@@ -266,7 +510,11 @@ impl ScanService {
         let task_id = active.task_id.clone();
         drop(scans);
 
-        self.clamav.pause_scan(&task_id).await?;
+        if !self.clamav.pause_scan(&task_id).await? {
+            return Err(anyhow::anyhow!("Scan is not running: {}", scan_id));
+        }
+
+        self.set_active_status(scan_id, "paused").await;
 
         // 更新数据库状态
         let _ = self.db.update_scan_status(scan_id, "paused");
@@ -282,14 +530,26 @@ impl ScanService {
         let task_id = active.task_id.clone();
         drop(scans);
 
-        self.clamav.resume_scan(&task_id).await?;
+        if !self.clamav.resume_scan(&task_id).await? {
+            return Err(anyhow::anyhow!("Scan is not paused: {}", scan_id));
+        }
+
+        self.set_active_status(scan_id, "scanning").await;
 
         // 更新数据库状态
-        let _ = self.db.update_scan_status(scan_id, "running");
+        let _ = self.db.update_scan_status(scan_id, "scanning");
 
         Ok(())
     }
 
+    /// 更新内存中的扫描状态
+    async fn set_active_status(&self, scan_id: &str, status: &str) {
+        let mut scans = self.active_scans.write().await;
+        if let Some(s) = scans.get_mut(scan_id) {
+            s.status = status.to_string();
+        }
+    }
+
     /// 获取扫描状态
     pub async fn get_scan_status(&self, scan_id: &str) -> Result<ScanStatus> {
         let scans = self.active_scans.read().await;
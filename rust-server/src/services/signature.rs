@@ -0,0 +1,194 @@
+use crate::clamav::{is_custom_signature_file, ClamAVEngine};
+use crate::env::FnosEnv;
+use crate::models::signature::SignatureFile;
+use std::fs;
+use std::path::Path;
+
+/// 自定义签名库服务
+///
+/// 管理数据目录下 custom-sigs 中的本地签名文件（.hdb/.hsb/.ndb/.ldb/.yar/.ign2），
+/// 这些文件在引擎初始化时与官方病毒库一同加载
+pub struct SignatureService {
+    dir: String,
+}
+
+impl SignatureService {
+    pub fn new(env: FnosEnv) -> Self {
+        Self { dir: env.custom_sigs_dir() }
+    }
+
+    /// 签名库目录
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    /// 列出自定义签名文件
+    pub fn list_files(&self) -> Result<Vec<SignatureFile>, String> {
+        let mut items = Vec::new();
+
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read signature dir: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if !is_custom_signature_file(&name) {
+                continue;
+            }
+
+            let metadata = entry.metadata()
+                .map_err(|e| format!("Failed to read metadata: {}", e))?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let modified_at = metadata.modified().ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+
+            items.push(SignatureFile {
+                sig_type: signature_type(&name),
+                name,
+                size: metadata.len(),
+                modified_at,
+            });
+        }
+
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(items)
+    }
+
+    /// 校验签名文件内容（不安装）
+    ///
+    /// 写入暂存目录后用独立引擎试编译，返回签名数量
+    ///
+    /// 每次校验使用单独的暂存目录，同名文件的并发校验互不覆盖
+    pub fn validate(&self, name: &str, content: &str) -> Result<u32, String> {
+        check_file_name(name)?;
+
+        let staging_dir = format!("{}/.staging/{}", self.dir, uuid::Uuid::new_v4());
+        fs::create_dir_all(&staging_dir)
+            .map_err(|e| format!("Failed to create staging dir: {}", e))?;
+
+        // 暂存文件保留原扩展名，libclamav 依据扩展名识别签名类型
+        let staging_path = format!("{}/{}", staging_dir, name);
+        let result = fs::write(&staging_path, content)
+            .map_err(|e| format!("Failed to write staging file: {}", e))
+            .and_then(|_| ClamAVEngine::validate_signatures(&staging_path).map_err(|e| e.to_string()));
+
+        let _ = fs::remove_dir_all(&staging_dir);
+        result
+    }
+
+    /// 校验并安装签名文件，同名文件将被覆盖
+    pub fn install(&self, name: &str, content: &str) -> Result<u32, String> {
+        let signatures = self.validate(name, content)?;
+
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create signature dir: {}", e))?;
+
+        // 先写临时文件再重命名，避免引擎加载到写了一半的文件
+        let tmp_path = format!("{}/.{}.{}.tmp", self.dir, name, uuid::Uuid::new_v4());
+        let final_path = format!("{}/{}", self.dir, name);
+        fs::write(&tmp_path, content)
+            .map_err(|e| format!("Failed to write signature file: {}", e))?;
+        fs::rename(&tmp_path, &final_path)
+            .map_err(|e| format!("Failed to install signature file: {}", e))?;
+
+        Ok(signatures)
+    }
+
+    /// 删除签名文件
+    pub fn delete_file(&self, name: &str) -> Result<(), String> {
+        check_file_name(name)?;
+
+        let path = format!("{}/{}", self.dir, name);
+        if !Path::new(&path).is_file() {
+            return Err(format!("Signature file not found: {}", name));
+        }
+
+        fs::remove_file(&path)
+            .map_err(|e| format!("Failed to delete signature file: {}", e))
+    }
+}
+
+/// 校验文件名：只允许目录内的普通文件名，且扩展名为受支持的签名类型
+fn check_file_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains('/')
+        || name.contains('\\')
+        || name.contains('\0')
+    {
+        return Err(format!("Invalid signature file name: {}", name));
+    }
+
+    if !is_custom_signature_file(name) {
+        return Err(format!(
+            "Unsupported signature file type: {} (expected .hdb/.hsb/.ndb/.ldb/.yar/.ign2)",
+            name
+        ));
+    }
+
+    Ok(())
+}
+
+/// 由文件扩展名得到签名类型
+fn signature_type(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_file_name() {
+        assert!(check_file_name("company.hdb").is_ok());
+        assert!(check_file_name("rules.yar").is_ok());
+        assert!(check_file_name("../etc/passwd.hdb").is_err());
+        assert!(check_file_name(".hidden.hdb").is_err());
+        assert!(check_file_name("notes.txt").is_err());
+        assert!(check_file_name("").is_err());
+    }
+
+    #[test]
+    fn test_list_and_delete_files() {
+        let dir = std::env::temp_dir().join(format!("custom-sigs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("local.hdb"), "44d88612fea8a8f36de82e1278abb02f:68:Eicar-Test\n").unwrap();
+        fs::write(dir.join("readme.txt"), "ignored").unwrap();
+
+        let service = SignatureService { dir: dir.to_string_lossy().to_string() };
+
+        let items = service.list_files().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "local.hdb");
+        assert_eq!(items[0].sig_type, "hdb");
+
+        assert!(service.delete_file("local.hdb").is_ok());
+        assert!(service.delete_file("local.hdb").is_err());
+        assert!(service.list_files().unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_validate_removes_staging_dir() {
+        let dir = std::env::temp_dir().join(format!("custom-sigs-{}", uuid::Uuid::new_v4()));
+        let service = SignatureService { dir: dir.to_string_lossy().to_string() };
+
+        // 无论校验是否通过，暂存目录都被清理
+        let _ = service.validate("local.hdb", "44d88612fea8a8f36de82e1278abb02f:68:Eicar-Test\n");
+        let _ = service.validate("local.hdb", "not a signature");
+        assert_eq!(fs::read_dir(dir.join(".staging")).unwrap().count(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
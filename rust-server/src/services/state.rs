@@ -1,37 +1,68 @@
 use crate::env::FnosEnv;
-use crate::services::{Database, ClamavService, ScanService, UpdateService};
-use crate::models::config::ClamAVConfig;
+use crate::services::{Database, ClamavService, ScanService, UpdateService, VerdictStore};
+use crate::models::config::{AppConfig, ClamAVConfig};
+use crate::clamav::{ClamAVEngine, Scanner};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 /// 应用共享状态
-#[derive(Clone)]
-pub struct AppState {
+pub struct AppState<S: Scanner = ClamAVEngine> {
     pub env: FnosEnv,
     pub db: Arc<Database>,
-    pub clamav: Arc<ClamavService>,
-    pub scan_service: Arc<tokio::sync::RwLock<ScanService>>,
+    pub clamav: Arc<ClamavService<S>>,
+    pub scan_service: Arc<tokio::sync::RwLock<ScanService<S>>>,
     pub update_service: Arc<tokio::sync::RwLock<UpdateService>>,
 }
 
-impl AppState {
+impl<S: Scanner> Clone for AppState<S> {
+    fn clone(&self) -> Self {
+        Self {
+            env: self.env.clone(),
+            db: self.db.clone(),
+            clamav: self.clamav.clone(),
+            scan_service: self.scan_service.clone(),
+            update_service: self.update_service.clone(),
+        }
+    }
+}
+
+impl AppState<ClamAVEngine> {
     pub fn new(env: FnosEnv) -> Self {
         let db = Arc::new(Database::new(&env.history_db()));
 
         // 创建 ClamAV 配置
-        let clamav_config = ClamAVConfig {
+        let mut clamav_config = ClamAVConfig {
             database_dir: env.clamav_db_dir(),
+            custom_sigs_dir: Some(env.custom_sigs_dir()),
             certs_dir: Some(format!("{}/certs", env.app_dest)),
             lib_path: Some(format!("{}/lib/libclamav.so", env.app_dest)),
             ..Default::default()
         };
 
+        // 应用用户保存的引擎设置
+        clamav_config.apply_settings(&AppConfig::load_or_default(&env.settings_file()));
+
         // 创建 ClamAV 服务
-        let clamav = Arc::new(ClamavService::new(clamav_config));
+        let clamav = ClamavService::new(clamav_config);
 
-        let scan_service = Arc::new(tokio::sync::RwLock::new(
-            ScanService::new(db.clone(), (*clamav).clone())
-        ));
+        Self::with_clamav(env, db, clamav)
+    }
+}
+
+impl<S: Scanner> AppState<S> {
+    /// 使用指定的 ClamAV 服务（扫描后端）构建应用状态
+    pub fn with_clamav(env: FnosEnv, db: Arc<Database>, clamav: ClamavService<S>) -> Self {
+        let clamav = Arc::new(clamav);
+
+        // 扫描结果缓存与扫描历史共用数据库，打开失败时每次完整扫描
+        let mut scan_service = ScanService::new(db.clone(), (*clamav).clone());
+        // 每个扫描工作线程一个只读连接
+        let readers = clamav.get_config().max_threads as usize;
+        match VerdictStore::open(&env.history_db(), readers) {
+            Ok(store) => scan_service = scan_service.with_verdict_store(Arc::new(store)),
+            Err(e) => tracing::warn!("Verdict cache unavailable, incremental scanning disabled: {}", e),
+        }
+        let scan_service = Arc::new(tokio::sync::RwLock::new(scan_service));
 
         let update_service = Arc::new(tokio::sync::RwLock::new(
             UpdateService::new(db.clone(), env.clone())
@@ -0,0 +1,280 @@
+// 文件扫描结果缓存的 SQLite 存储
+//
+// 缓存保存在扫描历史数据库的 file_verdicts 表中：
+// - 查询使用连接池（每个扫描工作线程一个只读连接），工作线程之间不争用同一把锁
+// - 写入先缓冲，每 FLUSH_BATCH 条或扫描结束时在写连接的一个事务中提交
+// - 超过保留天数的记录在每次扫描开始前清理（已删除文件的记录不会无限增长）
+// - 读写失败只记录日志，缓存不可用时照常扫描
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result as SqliteResult};
+use serde::Serialize;
+
+use crate::clamav::{CachedVerdict, FileIdentity, Verdict, VerdictCache};
+
+/// 缓冲的写入条数达到该值时提交
+const FLUSH_BATCH: usize = 256;
+
+/// 等待其他连接释放数据库锁的时间（与扫描历史的写入共用数据库文件）
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 缓存统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VerdictStoreStats {
+    pub entries: i64,
+    pub clean: i64,
+    pub infected: i64,
+    /// 最早一条记录的扫描时间（Unix 时间戳）
+    pub oldest_scanned_at: Option<i64>,
+}
+
+/// 基于 SQLite 的扫描结果缓存
+pub struct VerdictStore {
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
+    writer: Mutex<Connection>,
+    pending: Mutex<Vec<CachedVerdict>>,
+}
+
+impl VerdictStore {
+    /// 打开数据库（表由 init_db 创建），readers 为只读连接数（通常等于扫描工作线程数）
+    pub fn open(db_path: &str, readers: usize) -> SqliteResult<Self> {
+        let writer = Connection::open(db_path)?;
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+
+        let readers = (0..readers.max(1))
+            .map(|_| {
+                let conn = Connection::open_with_flags(
+                    db_path,
+                    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                )?;
+                conn.busy_timeout(BUSY_TIMEOUT)?;
+                Ok(Mutex::new(conn))
+            })
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(Self {
+            readers,
+            next_reader: AtomicUsize::new(0),
+            writer: Mutex::new(writer),
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// 缓存统计
+    pub fn stats(&self) -> SqliteResult<VerdictStoreStats> {
+        self.reader().query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(verdict = 'clean'), 0),
+                    COALESCE(SUM(verdict = 'infected'), 0),
+                    MIN(scanned_at)
+             FROM file_verdicts",
+            [],
+            |row| Ok(VerdictStoreStats {
+                entries: row.get(0)?,
+                clean: row.get(1)?,
+                infected: row.get(2)?,
+                oldest_scanned_at: row.get(3)?,
+            }),
+        )
+    }
+
+    /// 清空缓存（下次扫描时所有文件重新扫描）
+    pub fn clear(&self) -> SqliteResult<usize> {
+        self.pending.lock().unwrap().clear();
+        self.writer.lock().unwrap().execute("DELETE FROM file_verdicts", [])
+    }
+
+    /// 删除超过 max_age_days 天未重新扫描的记录，返回删除的条数
+    ///
+    /// 命中缓存不会刷新扫描时间，未修改的文件在记录过期后完整扫描一次
+    pub fn prune(&self, max_age_days: u32) -> SqliteResult<usize> {
+        let cutoff = chrono::Utc::now().timestamp() - i64::from(max_age_days) * 86_400;
+        self.writer.lock().unwrap()
+            .execute("DELETE FROM file_verdicts WHERE scanned_at < ?1", [cutoff])
+    }
+
+    /// 获取一个只读连接：优先选择空闲的连接，全部忙碌时轮流等待
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        let count = self.readers.len();
+        (0..count)
+            .find_map(|i| self.readers[(start + i) % count].try_lock().ok())
+            .unwrap_or_else(|| self.readers[start % count].lock().unwrap())
+    }
+
+    fn lookup(&self, dev: u64, ino: u64) -> SqliteResult<Option<CachedVerdict>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT size, mtime_ns, ctime_ns, verdict, db_version, engine_fingerprint
+             FROM file_verdicts WHERE dev = ?1 AND ino = ?2"
+        )?;
+        let row = stmt.query_row(params![dev as i64, ino as i64], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        }).optional()?;
+
+        Ok(row.and_then(|(size, mtime_ns, ctime_ns, verdict, db_version, engine_fingerprint)| {
+            Some(CachedVerdict {
+                identity: FileIdentity { dev, ino, size: size as u64, mtime_ns, ctime_ns },
+                verdict: Verdict::parse(&verdict)?,
+                db_version: db_version as u32,
+                engine_fingerprint,
+            })
+        }))
+    }
+
+    fn write(&self, entries: &[CachedVerdict]) -> SqliteResult<()> {
+        let mut conn = self.writer.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO file_verdicts
+                 (dev, ino, size, mtime_ns, ctime_ns, verdict, db_version, engine_fingerprint, scanned_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+            )?;
+            let scanned_at = chrono::Utc::now().timestamp();
+            for entry in entries {
+                let id = &entry.identity;
+                stmt.execute(params![
+                    id.dev as i64,
+                    id.ino as i64,
+                    id.size as i64,
+                    id.mtime_ns,
+                    id.ctime_ns,
+                    entry.verdict.as_str(),
+                    entry.db_version as i64,
+                    entry.engine_fingerprint,
+                    scanned_at,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+}
+
+impl VerdictCache for VerdictStore {
+    fn get(&self, dev: u64, ino: u64) -> Option<CachedVerdict> {
+        match self.lookup(dev, ino) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Failed to read verdict cache: {}", e);
+                None
+            }
+        }
+    }
+
+    fn put(&self, entry: CachedVerdict) {
+        let batch = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push(entry);
+            if pending.len() < FLUSH_BATCH {
+                return;
+            }
+            std::mem::take(&mut *pending)
+        };
+        if let Err(e) = self.write(&batch) {
+            tracing::warn!("Failed to write {} verdict cache entries: {}", batch.len(), e);
+        }
+    }
+
+    fn flush(&self) {
+        let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+        if batch.is_empty() {
+            return;
+        }
+        if let Err(e) = self.write(&batch) {
+            tracing::warn!("Failed to write {} verdict cache entries: {}", batch.len(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(readers: usize) -> (String, VerdictStore) {
+        let path = std::env::temp_dir().join(format!("verdict-store-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_string_lossy().to_string();
+        crate::services::init_db(&path).unwrap();
+        let store = VerdictStore::open(&path, readers).unwrap();
+        (path, store)
+    }
+
+    fn entry(ino: u64, verdict: Verdict) -> CachedVerdict {
+        CachedVerdict {
+            identity: FileIdentity { dev: u64::MAX, ino, size: 10, mtime_ns: 1, ctime_ns: 2 },
+            verdict,
+            db_version: 27000,
+            engine_fingerprint: "0123456789abcdef".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_entries_persist_after_flush() {
+        let (path, store) = temp_store(2);
+        store.put(entry(7, Verdict::Clean));
+        assert_eq!(store.get(u64::MAX, 7), None);
+
+        store.flush();
+        assert_eq!(store.get(u64::MAX, 7), Some(entry(7, Verdict::Clean)));
+
+        // 重新打开后仍然存在
+        drop(store);
+        let store = VerdictStore::open(&path, 1).unwrap();
+        assert_eq!(store.get(u64::MAX, 7), Some(entry(7, Verdict::Clean)));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_stats_prune_and_clear() {
+        let (path, store) = temp_store(1);
+        store.put(entry(1, Verdict::Clean));
+        store.put(entry(2, Verdict::Clean));
+        store.put(entry(3, Verdict::Infected));
+        store.flush();
+
+        let stats = store.stats().unwrap();
+        assert_eq!((stats.entries, stats.clean, stats.infected), (3, 2, 1));
+        assert!(stats.oldest_scanned_at.is_some());
+
+        // 一条记录超过保留期
+        store.writer.lock().unwrap()
+            .execute("UPDATE file_verdicts SET scanned_at = scanned_at - 31 * 86400 WHERE ino = 1", [])
+            .unwrap();
+        assert_eq!(store.prune(30).unwrap(), 1);
+        assert_eq!(store.get(u64::MAX, 1), None);
+        assert_eq!(store.stats().unwrap().entries, 2);
+
+        assert_eq!(store.clear().unwrap(), 2);
+        assert_eq!(store.stats().unwrap(), VerdictStoreStats::default());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_concurrent_lookups_use_separate_connections() {
+        let (path, store) = temp_store(4);
+        store.put(entry(1, Verdict::Clean));
+        store.flush();
+
+        // 一个连接被占用时，其他线程仍然可以查询
+        let store = std::sync::Arc::new(store);
+        let held = store.reader();
+        let other = store.clone();
+        let found = std::thread::spawn(move || other.get(u64::MAX, 1)).join().unwrap();
+        drop(held);
+        assert_eq!(found, Some(entry(1, Verdict::Clean)));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}